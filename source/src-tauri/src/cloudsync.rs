@@ -3,12 +3,19 @@
 //! This module handles:
 //! - Parsing Steam's remotecache.vdf files
 //! - Resolving save file paths from VDF root values
-//! - WebDAV client operations (upload, download, list)
+//! - WebDAV storage backend (see `cloudsync_storage` for the others)
 //! - Sync logic with conflict resolution
 
+use crate::cloudsync_roots::{resolve_root, GameLocation};
+use crate::cloudsync_storage::{
+    discard_partial, file_body, resume_point, send_with_progress, transfer_client, walk_collection,
+    write_response_to_file, CloudStorage, Progress, RemoteEntry,
+};
+use crate::vdf::RemoteCache;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // ============================================================================
 // Types
@@ -18,10 +25,81 @@ use std::path::PathBuf;
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CloudSyncConfig {
     pub enabled: bool,
-    pub provider: String, // "webdav", "gdrive", "dropbox", "onedrive"
+    pub provider: String, // "webdav", "local", "s3", "gdrive", "dropbox", "onedrive"
     pub webdav_url: String,
     pub username: String,
     pub password: String,
+    /// Target directory for the "local" provider (e.g. a NAS mount)
+    #[serde(default)]
+    pub local_path: String,
+    /// S3-compatible endpoint, e.g. "https://s3.eu-central-1.amazonaws.com" or "http://nas:9000"
+    #[serde(default)]
+    pub s3_endpoint: String,
+    #[serde(default)]
+    pub s3_bucket: String,
+    #[serde(default)]
+    pub s3_region: String,
+    #[serde(default)]
+    pub s3_access_key: String,
+    #[serde(default)]
+    pub s3_secret_key: String,
+    /// Number of save snapshots to keep per game (0 disables snapshots)
+    #[serde(default = "default_snapshot_retention_count")]
+    pub snapshot_retention_count: u32,
+    /// Maximum snapshot age in days (0 = no age limit)
+    #[serde(default = "default_snapshot_retention_days")]
+    pub snapshot_retention_days: u32,
+    /// Encrypt file contents and names client-side (passphrase is stored separately)
+    #[serde(default)]
+    pub encryption_enabled: bool,
+    /// Also sync files found on disk under each game's cloud roots, not only
+    /// the ones listed in remotecache.vdf
+    #[serde(default)]
+    pub scan_local_tree: bool,
+    /// Per-game file rules, keyed by app ID
+    #[serde(default)]
+    pub game_rules: HashMap<String, GameFileRules>,
+    /// What happens to saves when their deletion is passed on to the other side
+    #[serde(default)]
+    pub deletion_mode: DeletionMode,
+    /// Also sync the config files PCGamingWiki lists for games without
+    /// Steam Cloud saves
+    #[serde(default)]
+    pub sync_config_files: bool,
+}
+
+/// How deleted saves are removed from the remote. Local saves are always
+/// backed up into a rollback point before they are deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionMode {
+    /// Remote files are moved to boilerroom/.trash
+    #[default]
+    Trash,
+    /// Remote files are deleted outright
+    Delete,
+}
+
+/// Per-game rules for the on-disk scan
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameFileRules {
+    /// Overrides `scan_local_tree` for this game
+    #[serde(default)]
+    pub scan_local_tree: Option<bool>,
+    /// Globs (relative to the cloud root) a scanned file must match; empty matches everything
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs for scanned files to skip
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+fn default_snapshot_retention_count() -> u32 {
+    10
+}
+
+fn default_snapshot_retention_days() -> u32 {
+    30
 }
 
 /// Sync status for a single game
@@ -55,6 +133,12 @@ pub struct GameCloudStatus {
     pub error_message: Option<String>,
     #[serde(default = "default_source")]
     pub source: String, // "steam_cloud", "pcgamingwiki", "none"
+    /// Files the next sync would upload (relative paths)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files_to_upload: Vec<String>,
+    /// Files the next sync would download (relative paths)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files_to_download: Vec<String>,
 }
 
 fn default_source() -> String {
@@ -93,82 +177,80 @@ pub struct SyncResult {
     pub message: String,
     pub files_uploaded: u32,
     pub files_downloaded: u32,
+    /// Files deleted on either side
+    #[serde(default)]
+    pub files_deleted: u32,
     pub conflicts: Vec<String>,
+    /// What a dry run would do with each file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<crate::cloudsync_state::PlannedFile>,
 }
 
-// ============================================================================
-// VDF Parser for remotecache.vdf
-// ============================================================================
-
-/// Parse remotecache.vdf content and extract cloud files
-pub fn parse_remotecache_vdf(content: &str) -> Result<HashMap<String, CloudFile>, String> {
-    let mut files = HashMap::new();
-    let mut current_file: Option<String> = None;
-    let mut current_data: HashMap<String, String> = HashMap::new();
-    let mut brace_depth = 0;
-    let mut in_file_block = false;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        // Track braces
-        if trimmed == "{" {
-            brace_depth += 1;
-            if brace_depth == 2 && current_file.is_some() {
-                in_file_block = true;
-            }
-            continue;
-        }
-        if trimmed == "}" {
-            if in_file_block && brace_depth == 2 {
-                // End of file block, save the file
-                if let Some(ref file_path) = current_file {
-                    if let Some(cloud_file) = build_cloud_file(file_path, &current_data) {
-                        files.insert(file_path.clone(), cloud_file);
-                    }
-                }
-                current_file = None;
-                current_data.clear();
-                in_file_block = false;
-            }
-            brace_depth -= 1;
-            continue;
-        }
+/// Which directions a sync may transfer in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Upload and download as needed
+    Full,
+    /// Report what a full sync would do, without transferring anything
+    DryRun,
+    /// Only push local changes; remote changes are left for a later sync
+    UploadOnly,
+    /// Only bring local saves up to date (e.g. right before launching a game)
+    DownloadOnly,
+    /// Make the remote copy match the local saves, overriding conflicts
+    ForceUpload,
+    /// Make the local saves match the remote copy, overriding conflicts
+    ForceDownload,
+}
 
-        // Parse key-value pairs
-        let parts: Vec<&str> = trimmed.split('"').filter(|s| !s.trim().is_empty()).collect();
-        
-        if parts.len() >= 2 {
-            let key = parts[0];
-            let value = parts[1];
-
-            if brace_depth == 1 && !in_file_block {
-                // This is a file path definition (not ChangeNumber or app_id)
-                if key != "ChangeNumber" && !key.chars().all(|c| c.is_ascii_digit()) {
-                    current_file = Some(key.to_string());
-                }
-            } else if in_file_block {
-                // Store the key-value in current file data
-                current_data.insert(key.to_string(), value.to_string());
-            }
-        }
-    }
+/// Outcome of one game in a bulk sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSyncOutcome {
+    pub app_id: String,
+    /// Set when the sync ran
+    pub result: Option<SyncResult>,
+    /// Set when the sync failed outright
+    pub error: Option<String>,
+}
 
-    Ok(files)
+/// Result of syncing every game, totalled over the per-game outcomes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkSyncResult {
+    pub success: bool,
+    pub message: String,
+    pub files_uploaded: u32,
+    pub files_downloaded: u32,
+    pub files_deleted: u32,
+    /// Conflicting files as "<app_id>/<path>"
+    pub conflicts: Vec<String>,
+    pub games: Vec<GameSyncOutcome>,
 }
 
-/// Build a CloudFile from parsed VDF data
-fn build_cloud_file(path: &str, data: &HashMap<String, String>) -> Option<CloudFile> {
-    Some(CloudFile {
-        path: path.to_string(),
-        root: data.get("root").and_then(|v| v.parse().ok()).unwrap_or(0),
-        size: data.get("size").and_then(|v| v.parse().ok()).unwrap_or(0),
-        localtime: data.get("localtime").and_then(|v| v.parse().ok()).unwrap_or(0),
-        remotetime: data.get("remotetime").and_then(|v| v.parse().ok()).unwrap_or(0),
-        sha: data.get("sha").cloned().unwrap_or_default(),
-        syncstate: data.get("syncstate").and_then(|v| v.parse().ok()).unwrap_or(0),
-        resolved_path: None,
-    })
+// ============================================================================
+// remotecache.vdf
+// ============================================================================
+
+/// Parse remotecache.vdf content and extract cloud files (keyed by `file_key`)
+pub fn parse_remotecache_vdf(content: &str) -> Result<HashMap<String, CloudFile>, String> {
+    let cache = RemoteCache::parse(content)?;
+    Ok(cache
+        .files
+        .into_iter()
+        .map(|(path, file)| {
+            let cloud_file = CloudFile {
+                path: path.clone(),
+                root: file.root,
+                size: file.size,
+                localtime: file.localtime,
+                remotetime: file.remotetime,
+                sha: file.sha,
+                syncstate: file.syncstate,
+                resolved_path: None,
+            };
+            (file_key(file.root, &path), cloud_file)
+        })
+        .collect())
 }
 
 // ============================================================================
@@ -176,60 +258,14 @@ fn build_cloud_file(path: &str, data: &HashMap<String, String>) -> Option<CloudF
 // ============================================================================
 
 /// Resolve the actual file system path for a cloud file based on its root type
-/// 
-/// Root value mappings (from Steam documentation):
-/// - 0: userdata/[user_id]/[app_id]/remote/
-/// - 1: Game installation directory
-/// - 2: ~/Documents/
-/// - 3: ~/.config/ (Windows %appdata%)
-/// - 4: ~/.local/share/ (Windows %localappdata%)
-/// - 12: ~/.local/share/ (Windows %localappdata%/Low)
+/// (see `cloudsync_roots` for the root mapping, including Proton prefixes)
 pub fn resolve_cloud_file_path(
     file: &CloudFile,
     app_id: &str,
     user_id: &str,
-    game_install_path: Option<&str>,
+    location: &GameLocation,
 ) -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    
-    let base_path = match file.root {
-        0 => {
-            // Steam userdata remote folder
-            let steam_path = if cfg!(target_os = "macos") {
-                home.join("Library/Application Support/Steam")
-            } else {
-                home.join(".local/share/Steam")
-            };
-            Some(steam_path.join("userdata").join(user_id).join(app_id).join("remote"))
-        }
-        1 => {
-            // Game installation directory
-            game_install_path.map(PathBuf::from)
-        }
-        2 => {
-            // Documents folder
-            dirs::document_dir()
-        }
-        3 => {
-            // Config/AppData Roaming
-            if cfg!(target_os = "macos") {
-                Some(home.join("Library/Application Support"))
-            } else {
-                dirs::config_dir()
-            }
-        }
-        4 | 12 => {
-            // Local data / LocalLow
-            if cfg!(target_os = "macos") {
-                Some(home.join("Library/Application Support"))
-            } else {
-                dirs::data_local_dir()
-            }
-        }
-        _ => None,
-    };
-
-    base_path.map(|base| base.join(&file.path))
+    resolve_root(file.root, app_id, user_id, location).map(|base| base.join(&file.path))
 }
 
 // ============================================================================
@@ -237,47 +273,161 @@ pub fn resolve_cloud_file_path(
 // ============================================================================
 
 /// Encode a file path for WebDAV storage (replace / with __)
+///
+/// Legacy flat layout, only used to find and migrate old uploads.
 pub fn encode_webdav_path(path: &str) -> String {
     path.replace('/', "__").replace('\\', "__")
 }
 
 /// Decode a WebDAV path back to original (replace __ with /)
+///
+/// Legacy flat layout, only used to find and migrate old uploads.
 pub fn decode_webdav_path(encoded: &str) -> String {
     encoded.replace("__", "/")
 }
 
-/// Build the full WebDAV URL for a file
-pub fn build_webdav_url(base_url: &str, app_id: &str, file_path: &str) -> String {
-    let encoded_path = encode_webdav_path(file_path);
-    let base = base_url.trim_end_matches('/');
-    format!("{}/boilerroom/{}/{}", base, app_id, encoded_path)
+/// Normalize a save file path for remote storage ('/' separators, no leading slash)
+pub fn normalize_remote_path(file_path: &str) -> String {
+    file_path.replace('\\', "/").trim_start_matches('/').to_string()
+}
+
+/// Build the remote path of a game's save file (given by its key, see
+/// `file_key`), relative to the storage root. The remote tree mirrors the
+/// local directory structure.
+pub fn remote_file_path(app_id: &str, file_path: &str) -> String {
+    format!("boilerroom/{}/{}", app_id, normalize_remote_path(file_path))
+}
+
+/// Directory prefix keeping files on cloud roots other than 0 apart
+const ROOT_DIR_PREFIX: &str = ".root";
+
+/// Key of a save file, naming it in the remote tree, the sync state and the
+/// remote index: files on root 0 by their path, files on other roots under
+/// `.root<N>/`, so the same path on two roots never collides. Fallback files
+/// (negative roots) are keyed by their path as well.
+pub fn file_key(root: i32, path: &str) -> String {
+    if root > 0 {
+        format!("{}{}/{}", ROOT_DIR_PREFIX, root, path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// Root and path of a file key (see `file_key`)
+pub fn split_file_key(key: &str) -> (i32, &str) {
+    key.strip_prefix(ROOT_DIR_PREFIX)
+        .and_then(|rest| rest.split_once('/'))
+        .and_then(|(root, path)| Some((root.parse::<i32>().ok().filter(|r| *r > 0)?, path)))
+        .unwrap_or((0, key))
 }
 
 /// WebDAV client for cloud sync operations
 pub struct WebDavClient {
     client: reqwest::Client,
     base_url: String,
+    /// Decoded path component of `base_url`, used to make PROPFIND hrefs relative
+    base_path: String,
     username: String,
     password: String,
+    /// Collections known to exist, so MKCOL is only sent once per directory
+    created_dirs: std::sync::Mutex<std::collections::HashSet<String>>,
 }
 
+/// Properties requested from PROPFIND
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop>
+    <d:resourcetype/>
+    <d:getcontentlength/>
+    <d:getlastmodified/>
+    <d:getetag/>
+  </d:prop>
+</d:propfind>"#;
+
 impl WebDavClient {
     pub fn new(config: &CloudSyncConfig) -> Result<Self, String> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let client = transfer_client()?;
+
+        let base_url = config.webdav_url.trim_end_matches('/').to_string();
+        let base_path = reqwest::Url::parse(&base_url)
+            .map_err(|e| format!("Invalid WebDAV URL: {}", e))?
+            .path()
+            .to_string();
 
         Ok(Self {
             client,
-            base_url: config.webdav_url.trim_end_matches('/').to_string(),
+            base_path: decode_href(&base_path).trim_end_matches('/').to_string(),
+            base_url,
             username: config.username.clone(),
             password: config.password.clone(),
+            created_dirs: Default::default(),
         })
     }
 
+    /// Build the full URL for a path relative to the server base URL,
+    /// percent-encoding each segment
+    fn url(&self, path: &str) -> String {
+        let encoded: Vec<String> = path
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect();
+        format!("{}/{}", self.base_url, encoded.join("/").trim_start_matches('/'))
+    }
+
+    /// Create a single directory on WebDAV server
+    pub async fn create_directory(&self, path: &str) -> Result<(), String> {
+        let url = self.url(&format!("{}/", path.trim_matches('/')));
+        
+        let response = self.client
+            .request(reqwest::Method::from_bytes(b"MKCOL").unwrap(), &url)
+            .basic_auth(&self.username, Some(&self.password))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+        match response.status().as_u16() {
+            200..=299 | 405 => Ok(()), // 405 = already exists
+            status => Err(format!("Failed to create directory: status {}", status)),
+        }
+    }
+
+    /// PROPFIND a collection. Returns None if the server refuses the requested depth.
+    async fn propfind(&self, path: &str, depth: &str) -> Result<Option<Vec<RemoteEntry>>, String> {
+        let collection = path.trim_matches('/');
+
+        let response = self.client
+            .request(
+                reqwest::Method::from_bytes(b"PROPFIND").unwrap(),
+                self.url(&format!("{}/", collection)),
+            )
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Depth", depth)
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list files: {}", e))?;
+
+        match response.status().as_u16() {
+            200..=299 => {}
+            404 => return Ok(Some(Vec::new())), // Directory doesn't exist yet
+            // Servers may forbid Depth: infinity (DAV:propfind-finite-depth)
+            403 if depth == "infinity" => return Ok(None),
+            status => return Err(format!("Failed to list files: status {}", status)),
+        }
+
+        let body = response.text().await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+
+        let collection_path = format!("{}/{}", self.base_path, collection);
+        parse_propfind_multistatus(&body, &collection_path).map(Some)
+    }
+}
+
+#[async_trait]
+impl CloudStorage for WebDavClient {
     /// Test connection to WebDAV server
-    pub async fn test_connection(&self) -> Result<String, String> {
+    async fn test_connection(&self) -> Result<String, String> {
         let url = format!("{}/boilerroom/", self.base_url);
         
         // Try PROPFIND to check if we can access the directory
@@ -302,51 +452,80 @@ impl WebDavClient {
         }
     }
 
-    /// Create a directory on WebDAV server
-    pub async fn create_directory(&self, path: &str) -> Result<(), String> {
-        let url = format!("{}/{}/", self.base_url, path);
-        
+    /// List the direct children of a collection
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        Ok(self.propfind(path, "1").await?.unwrap_or_default())
+    }
+
+    /// List a whole tree with a single Depth: infinity PROPFIND, walking it
+    /// level by level if the server doesn't allow that
+    async fn list_recursive(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        match self.propfind(path, "infinity").await? {
+            Some(entries) => Ok(entries),
+            None => walk_collection(self, path).await,
+        }
+    }
+
+    /// Check if a file exists and get its size and modification time
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String> {
         let response = self.client
-            .request(reqwest::Method::from_bytes(b"MKCOL").unwrap(), &url)
+            .head(self.url(path))
             .basic_auth(&self.username, Some(&self.password))
             .send()
             .await
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
+            .map_err(|e| format!("Failed to check file: {}", e))?;
 
-        match response.status().as_u16() {
-            200..=299 | 405 => Ok(()), // 405 = already exists
-            status => Err(format!("Failed to create directory: status {}", status)),
+        if response.status().as_u16() == 404 {
+            return Ok(None); // File doesn't exist
+        }
+        if !response.status().is_success() {
+            return Err(format!("Failed to check file: status {}", response.status()));
         }
-    }
 
-    /// Upload a file to WebDAV
-    pub async fn upload_file(&self, app_id: &str, relative_path: &str, content: Vec<u8>) -> Result<(), String> {
-        // Ensure app directory exists
-        self.create_directory(&format!("boilerroom/{}", app_id)).await?;
+        // Parse HTTP date format from Last-Modified
+        let modified = response
+            .headers()
+            .get("last-modified")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| chrono::DateTime::parse_from_rfc2822(s).ok())
+            .map(|dt| dt.timestamp() as u64);
+
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_matches('"').to_string());
+
+        Ok(Some(RemoteEntry {
+            name: path.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string(),
+            is_dir: path.ends_with('/'),
+            size: response.content_length().unwrap_or(0),
+            modified,
+            etag,
+        }))
+    }
 
-        let url = build_webdav_url(&self.base_url, app_id, relative_path);
-        
+    /// Upload raw content
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String> {
         let response = self.client
-            .put(&url)
+            .put(self.url(path))
             .basic_auth(&self.username, Some(&self.password))
             .body(content)
             .send()
             .await
             .map_err(|e| format!("Upload failed: {}", e))?;
 
-        if response.status().is_success() || response.status().as_u16() == 201 {
+        if response.status().is_success() {
             Ok(())
         } else {
             Err(format!("Upload failed: status {}", response.status()))
         }
     }
 
-    /// Download a file from WebDAV
-    pub async fn download_file(&self, app_id: &str, relative_path: &str) -> Result<Vec<u8>, String> {
-        let url = build_webdav_url(&self.base_url, app_id, relative_path);
-        
+    /// Download raw content
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String> {
         let response = self.client
-            .get(&url)
+            .get(self.url(path))
             .basic_auth(&self.username, Some(&self.password))
             .send()
             .await
@@ -363,149 +542,245 @@ impl WebDavClient {
         }
     }
 
-    /// Check if a file exists on WebDAV and get its modification time
-    pub async fn get_file_info(&self, app_id: &str, relative_path: &str) -> Result<Option<u64>, String> {
-        let url = build_webdav_url(&self.base_url, app_id, relative_path);
-        
-        let response = self.client
-            .head(&url)
+    /// Upload a local file as a streamed PUT
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let (body, total, sent) = file_body(local).await?;
+        let request = self.client
+            .put(self.url(path))
             .basic_auth(&self.username, Some(&self.password))
-            .send()
+            .header("Content-Length", total)
+            .body(body);
+        let response = send_with_progress(request, sent, total, progress)
             .await
-            .map_err(|e| format!("Failed to check file: {}", e))?;
+            .map_err(|e| format!("Upload failed: {}", e))?;
 
         if response.status().is_success() {
-            // Try to get Last-Modified header
-            if let Some(last_modified) = response.headers().get("last-modified") {
-                if let Ok(date_str) = last_modified.to_str() {
-                    // Parse HTTP date format
-                    if let Ok(datetime) = chrono::DateTime::parse_from_rfc2822(date_str) {
-                        return Ok(Some(datetime.timestamp() as u64));
-                    }
-                }
-            }
-            Ok(Some(0)) // File exists but no timestamp
-        } else if response.status().as_u16() == 404 {
-            Ok(None) // File doesn't exist
+            progress(total, total);
+            Ok(())
         } else {
-            Err(format!("Failed to check file: status {}", response.status()))
+            Err(format!("Upload failed: status {}", response.status()))
         }
     }
 
-    /// List files in a directory on WebDAV
-    pub async fn list_files(&self, app_id: &str) -> Result<Vec<String>, String> {
-        let url = format!("{}/boilerroom/{}/", self.base_url, app_id);
-        
+    /// Download to a local file, resuming a partial one with a Range request
+    /// as long as the file's ETag hasn't changed
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let (offset, etag) = resume_point(local).await;
+        let mut request = self.client
+            .get(self.url(path))
+            .basic_auth(&self.username, Some(&self.password));
+        if let Some(etag) = etag {
+            request = request
+                .header("Range", format!("bytes={}-", offset))
+                .header("If-Range", etag);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Download failed: {}", e))?;
+
+        match response.status().as_u16() {
+            206 => write_response_to_file(response, local, offset, progress).await,
+            // The file changed since the partial download (or the server
+            // ignored the Range header) and the whole file was sent
+            200..=299 => write_response_to_file(response, local, 0, progress).await,
+            404 => Err("File not found on server".to_string()),
+            416 => {
+                // The partial file doesn't fit the remote one; start over
+                discard_partial(local).await;
+                self.download_to(path, local, progress).await
+            }
+            status => Err(format!("Download failed: status {}", status)),
+        }
+    }
+
+    /// Delete a file or collection (recursively)
+    async fn delete(&self, path: &str) -> Result<(), String> {
         let response = self.client
-            .request(reqwest::Method::from_bytes(b"PROPFIND").unwrap(), &url)
+            .delete(self.url(path))
             .basic_auth(&self.username, Some(&self.password))
-            .header("Depth", "1")
             .send()
             .await
-            .map_err(|e| format!("Failed to list files: {}", e))?;
+            .map_err(|e| format!("Delete failed: {}", e))?;
 
-        if !response.status().is_success() && response.status().as_u16() != 207 {
-            if response.status().as_u16() == 404 {
-                return Ok(Vec::new()); // Directory doesn't exist yet
-            }
-            return Err(format!("Failed to list files: status {}", response.status()));
+        match response.status().as_u16() {
+            200..=299 | 404 => Ok(()), // 404 = already gone
+            status => Err(format!("Delete failed: status {}", status)),
         }
+    }
 
-        let body = response.text().await
-            .map_err(|e| format!("Failed to read response: {}", e))?;
+    /// Create a directory and all of its missing parents
+    async fn mkdir(&self, path: &str) -> Result<(), String> {
+        let mut current = String::new();
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(segment);
 
-        // Simple XML parsing to extract file names from PROPFIND response
-        let mut files = Vec::new();
-        for line in body.lines() {
-            if line.contains("<D:href>") || line.contains("<d:href>") {
-                if let Some(start) = line.find('>') {
-                    if let Some(end) = line[start + 1..].find('<') {
-                        let href = &line[start + 1..start + 1 + end];
-                        if let Some(filename) = href.split('/').last() {
-                            if !filename.is_empty() && filename != app_id {
-                                files.push(decode_webdav_path(filename));
-                            }
-                        }
-                    }
-                }
+            if self.created_dirs.lock().unwrap().contains(&current) {
+                continue;
             }
+            self.create_directory(&current).await?;
+            self.created_dirs.lock().unwrap().insert(current.clone());
         }
-
-        Ok(files)
+        Ok(())
     }
-}
 
-// ============================================================================
-// Sync Operations
-// ============================================================================
+    /// Copy a file server-side (WebDAV COPY), falling back to download + upload
+    /// for servers that don't implement COPY
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        let response = self.client
+            .request(reqwest::Method::from_bytes(b"COPY").unwrap(), self.url(from))
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Destination", self.url(to))
+            .header("Overwrite", "T")
+            .send()
+            .await
+            .map_err(|e| format!("Copy failed: {}", e))?;
 
-/// Find all remotecache.vdf files for BoilerRoom games
-#[allow(dead_code)]
-pub fn find_remotecache_files(boilerroom_app_ids: &[String]) -> Vec<(String, PathBuf)> {
-    let mut results = Vec::new();
-    
-    let home = match dirs::home_dir() {
-        Some(h) => h,
-        None => return results,
-    };
+        match response.status().as_u16() {
+            200..=299 => Ok(()),
+            405 | 501 => {
+                let data = self.download(from).await?;
+                self.upload(to, data).await
+            }
+            status => Err(format!("Copy failed: status {}", status)),
+        }
+    }
+}
 
-    let steam_path = if cfg!(target_os = "macos") {
-        home.join("Library/Application Support/Steam")
+/// Turn a PROPFIND href (absolute URL or absolute path) into a decoded path
+fn decode_href(href: &str) -> String {
+    let path = if href.starts_with("http://") || href.starts_with("https://") {
+        reqwest::Url::parse(href)
+            .map(|u| u.path().to_string())
+            .unwrap_or_else(|_| href.to_string())
     } else {
-        home.join(".local/share/Steam")
+        href.to_string()
     };
 
-    let userdata_path = steam_path.join("userdata");
-    
-    if !userdata_path.exists() {
-        return results;
-    }
+    urlencoding::decode(&path)
+        .map(|p| p.into_owned())
+        .unwrap_or(path)
+}
 
-    // Iterate through user directories
-    if let Ok(user_dirs) = std::fs::read_dir(&userdata_path) {
-        for user_entry in user_dirs.flatten() {
-            if !user_entry.path().is_dir() {
-                continue;
+/// Parse a PROPFIND multistatus response into entries below `collection_path`
+/// (the decoded server path of the listed collection). Entry names are relative
+/// to the collection; the collection itself is skipped. Namespace prefixes are
+/// ignored, so `D:`, `d:`, `ns0:` and default-namespace responses all work.
+pub fn parse_propfind_multistatus(
+    body: &str,
+    collection_path: &str,
+) -> Result<Vec<RemoteEntry>, String> {
+    let collection_path = collection_path.trim_end_matches('/');
+    let mut reader = quick_xml::Reader::from_str(body);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+
+    let mut href = String::new();
+    let mut is_dir = false;
+    let mut size = 0u64;
+    let mut modified: Option<u64> = None;
+    let mut etag: Option<String> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid PROPFIND response: {}", e))?;
+
+        match event {
+            quick_xml::events::Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if name == "response" {
+                    href.clear();
+                    is_dir = false;
+                    size = 0;
+                    modified = None;
+                    etag = None;
+                }
+                if name == "collection" && stack.last().map(|s| s.as_str()) == Some("resourcetype") {
+                    is_dir = true;
+                }
+                stack.push(name);
             }
-
-            // Check each app_id directory
-            for app_id in boilerroom_app_ids {
-                let remotecache = user_entry.path()
-                    .join(app_id)
-                    .join("remotecache.vdf");
-                
-                if remotecache.exists() {
-                    results.push((app_id.clone(), remotecache));
+            quick_xml::events::Event::Empty(e) => {
+                let in_resourcetype = stack.last().map(|s| s.as_str()) == Some("resourcetype");
+                is_dir |= in_resourcetype && e.local_name().as_ref() == b"collection";
+            }
+            quick_xml::events::Event::Text(t) => {
+                let text = t
+                    .unescape()
+                    .map_err(|e| format!("Invalid PROPFIND response: {}", e))?
+                    .to_string();
+                match stack.last().map(|s| s.as_str()) {
+                    Some("href") => href = text,
+                    Some("getcontentlength") => size = text.parse().unwrap_or(0),
+                    Some("getlastmodified") => {
+                        modified = chrono::DateTime::parse_from_rfc2822(&text)
+                            .ok()
+                            .map(|dt| dt.timestamp() as u64);
+                    }
+                    Some("getetag") => etag = Some(text.trim_matches('"').to_string()),
+                    _ => {}
                 }
             }
+            quick_xml::events::Event::End(_) => {
+                let closed = stack.pop();
+                if closed.as_deref() != Some("response") || href.is_empty() {
+                    continue;
+                }
+
+                let path = decode_href(&href);
+                let relative = match path.trim_end_matches('/').strip_prefix(collection_path) {
+                    Some("") => continue, // The collection itself
+                    Some(rest) if rest.starts_with('/') => rest.trim_start_matches('/'),
+                    _ => {
+                        eprintln!("[CloudSync] Ignoring PROPFIND entry outside collection: {}", href);
+                        continue;
+                    }
+                };
+
+                let is_dir = is_dir || path.ends_with('/');
+                entries.push(RemoteEntry {
+                    name: relative.to_string(),
+                    is_dir,
+                    size: if is_dir { 0 } else { size },
+                    modified,
+                    etag: etag.take(),
+                });
+            }
+            quick_xml::events::Event::Eof => break,
+            _ => {}
         }
     }
 
-    results
+    Ok(entries)
 }
 
-/// Get the Steam user ID from userdata directory
-pub fn get_steam_user_id() -> Option<String> {
-    let home = dirs::home_dir()?;
-    
-    let steam_path = if cfg!(target_os = "macos") {
-        home.join("Library/Application Support/Steam")
-    } else {
-        home.join(".local/share/Steam")
+// ============================================================================
+// Sync Operations
+// ============================================================================
+
+/// Find the remotecache.vdf files of an account's BoilerRoom games
+pub fn find_remotecache_files(account_id: &str, boilerroom_app_ids: &[String]) -> Vec<(String, PathBuf)> {
+    let user_dir = match crate::cloudsync_roots::steam_root() {
+        Some(root) => root.join("userdata").join(account_id),
+        None => return Vec::new(),
     };
 
-    let userdata_path = steam_path.join("userdata");
-    
-    if let Ok(mut dirs) = std::fs::read_dir(&userdata_path) {
-        // Return the first user ID found (most users have only one)
-        if let Some(Ok(entry)) = dirs.next() {
-            if entry.path().is_dir() {
-                return entry.file_name().into_string().ok();
-            }
-        }
-    }
-    
-    None
+    boilerroom_app_ids
+        .iter()
+        .map(|app_id| (app_id.clone(), user_dir.join(app_id).join("remotecache.vdf")))
+        .filter(|(_, remotecache)| remotecache.exists())
+        .collect()
+}
+
+/// Get the account ID of the active Steam user (see `cloudsync_accounts`)
+pub fn get_steam_user_id() -> Option<String> {
+    crate::cloudsync_accounts::active_account_id()
 }
 
 #[cfg(test)]
@@ -542,6 +817,18 @@ mod tests {
         assert_eq!(file.sha, "c7bdc563982fc4ddddb9e6f8853b298967d858f9");
     }
 
+    #[test]
+    fn test_file_keys() {
+        assert_eq!(file_key(0, "saves/slot1.sav"), "saves/slot1.sav");
+        assert_eq!(file_key(-1, "config/settings.ini"), "config/settings.ini");
+        assert_eq!(file_key(12, "saves/slot1.sav"), ".root12/saves/slot1.sav");
+
+        assert_eq!(split_file_key(".root12/saves/slot1.sav"), (12, "saves/slot1.sav"));
+        assert_eq!(split_file_key("saves/slot1.sav"), (0, "saves/slot1.sav"));
+        assert_eq!(split_file_key(".rootx/slot1.sav"), (0, ".rootx/slot1.sav"));
+        assert_eq!(split_file_key(".root0/slot1.sav"), (0, ".root0/slot1.sav"));
+    }
+
     #[test]
     fn test_encode_decode_webdav_path() {
         let path = "saves/slot1/game.sav";
@@ -553,15 +840,73 @@ mod tests {
     }
 
     #[test]
-    fn test_build_webdav_url() {
-        let url = build_webdav_url(
-            "https://cloud.example.com/remote.php/dav/files/user",
-            "730",
-            "saves/game.sav"
-        );
+    fn test_parse_propfind_multistatus() {
+        // Nextcloud-style response with Depth: infinity and encoded names
+        let body = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/user/boilerroom/730/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype><d:getetag>"dir"</d:getetag></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/user/boilerroom/730/save%20slot__1.sav</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype/>
+        <d:getcontentlength>1024</d:getcontentlength>
+        <d:getlastmodified>Sat, 14 Mar 2026 15:09:26 GMT</d:getlastmodified>
+        <d:getetag>&quot;abc123&quot;</d:getetag>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/user/boilerroom/730/profiles/</d:href>
+    <d:propstat>
+      <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/remote.php/dav/files/user/boilerroom/730/profiles/p%C3%A9.cfg</d:href>
+    <d:propstat>
+      <d:prop><d:getcontentlength>7</d:getcontentlength></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+        let entries =
+            parse_propfind_multistatus(body, "/remote.php/dav/files/user/boilerroom/730").unwrap();
+        assert_eq!(entries.len(), 3);
         assert_eq!(
-            url,
-            "https://cloud.example.com/remote.php/dav/files/user/boilerroom/730/saves__game.sav"
+            entries[0],
+            RemoteEntry {
+                name: "save slot__1.sav".to_string(),
+                is_dir: false,
+                size: 1024,
+                modified: Some(1773500966),
+                etag: Some("abc123".to_string()),
+            }
         );
+        assert_eq!(entries[1].name, "profiles");
+        assert!(entries[1].is_dir);
+        assert_eq!(entries[2].name, "profiles/pé.cfg");
+        assert_eq!(entries[2].size, 7);
+    }
+
+    #[test]
+    fn test_parse_propfind_single_line_other_prefix() {
+        // Everything on one line, a different prefix and absolute URL hrefs
+        let body = r#"<ns0:multistatus xmlns:ns0="DAV:"><ns0:response><ns0:href>https://dav.example.com/saves/boilerroom/730/</ns0:href><ns0:propstat><ns0:prop><ns0:resourcetype><ns0:collection/></ns0:resourcetype></ns0:prop></ns0:propstat></ns0:response><ns0:response><ns0:href>https://dav.example.com/saves/boilerroom/730/a.sav</ns0:href><ns0:propstat><ns0:prop><ns0:resourcetype/><ns0:getcontentlength>3</ns0:getcontentlength></ns0:prop></ns0:propstat></ns0:response></ns0:multistatus>"#;
+
+        let entries = parse_propfind_multistatus(body, "/saves/boilerroom/730/").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a.sav");
+        assert_eq!(entries[0].size, 3);
+        assert!(!entries[0].is_dir);
     }
 }
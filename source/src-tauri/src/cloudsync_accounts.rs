@@ -0,0 +1,437 @@
+//! CloudSync Accounts - Steam accounts and their remote namespaces
+//!
+//! A machine can have several Steam accounts, each with its own
+//! `userdata/<account_id>` folder. The active one is the account Steam marks
+//! as `MostRecent` in `config/loginusers.vdf`.
+//!
+//! Every account gets its own remote namespace:
+//! - boilerroom/accounts/<account_id>/<app_id>/...
+//! - boilerroom/accounts/<account_id>/.<area>/<app_id>...   (snapshots, index, keys)
+//!
+//! Games synced before accounts were separated live at boilerroom/<app_id>
+//! (and boilerroom/.<area>/<app_id>), with their local sync state directly in
+//! the state folder. The layout migration command moves both to the account
+//! it is run for; until then the account starts with no sync history.
+
+use crate::cloudsync_roots;
+use crate::cloudsync_storage::{create_backend, parent_path, CloudStorage, Progress, RemoteEntry};
+use crate::cloudsync::CloudSyncConfig;
+use crate::cloudsync_crypto::{load_passphrase, EncryptedStorage};
+use crate::vdf::LoginUsers;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::Path;
+
+/// SteamID64 of account ID 0; the account ID is the SteamID64 minus this
+const STEAM_ID64_BASE: u64 = 76561197960265728;
+
+/// A Steam account known on this machine
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SteamAccount {
+    /// 32-bit account ID, as used for userdata/<account_id>
+    pub account_id: String,
+    pub steam_id: Option<String>,
+    pub account_name: Option<String>,
+    pub persona_name: Option<String>,
+    /// True for the account Steam logged in last
+    pub most_recent: bool,
+    /// Whether the account has a userdata folder (and so possibly saves)
+    pub has_userdata: bool,
+    #[serde(skip)]
+    timestamp: u64,
+}
+
+// ============================================================================
+// Account Discovery
+// ============================================================================
+
+/// Parse the accounts of loginusers.vdf
+fn parse_loginusers(content: &str) -> Vec<SteamAccount> {
+    let login_users = match LoginUsers::parse(content) {
+        Ok(login_users) => login_users,
+        Err(e) => {
+            eprintln!("[CloudSync] Ignoring loginusers.vdf: {}", e);
+            return Vec::new();
+        }
+    };
+
+    login_users
+        .users
+        .into_iter()
+        .filter_map(|(steam_id, user)| {
+            let account_id = match steam_id.parse::<u64>() {
+                Ok(id) if id > STEAM_ID64_BASE => (id - STEAM_ID64_BASE).to_string(),
+                _ => return None,
+            };
+            Some(SteamAccount {
+                account_id,
+                steam_id: Some(steam_id),
+                account_name: user.account_name,
+                persona_name: user.persona_name,
+                most_recent: user.most_recent,
+                has_userdata: false,
+                timestamp: user.timestamp,
+            })
+        })
+        .collect()
+}
+
+fn is_account_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+}
+
+fn list_accounts_in(steam_root: &Path) -> Vec<SteamAccount> {
+    let mut accounts = std::fs::read_to_string(steam_root.join("config/loginusers.vdf"))
+        .map(|content| parse_loginusers(&content))
+        .unwrap_or_default();
+
+    // Accounts that only left a userdata folder behind
+    let mut userdata_ids: Vec<String> = std::fs::read_dir(steam_root.join("userdata"))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|id| is_account_id(id) && id != "0")
+                .collect()
+        })
+        .unwrap_or_default();
+    userdata_ids.sort();
+
+    for account in &mut accounts {
+        account.has_userdata = userdata_ids.contains(&account.account_id);
+    }
+    for id in userdata_ids {
+        if !accounts.iter().any(|a| a.account_id == id) {
+            accounts.push(SteamAccount {
+                account_id: id,
+                steam_id: None,
+                account_name: None,
+                persona_name: None,
+                most_recent: false,
+                has_userdata: true,
+                timestamp: 0,
+            });
+        }
+    }
+
+    accounts
+}
+
+/// The account Steam logged in last: `MostRecent`, else the latest login,
+/// else the first userdata folder
+fn active_account(accounts: &[SteamAccount]) -> Option<String> {
+    accounts
+        .iter()
+        .find(|a| a.most_recent)
+        .or_else(|| {
+            accounts
+                .iter()
+                .filter(|a| a.timestamp > 0)
+                .max_by_key(|a| a.timestamp)
+        })
+        .or_else(|| accounts.iter().find(|a| a.has_userdata))
+        .map(|a| a.account_id.clone())
+}
+
+/// List the Steam accounts on this machine
+pub fn list_accounts() -> Vec<SteamAccount> {
+    cloudsync_roots::steam_root()
+        .map(|root| list_accounts_in(&root))
+        .unwrap_or_default()
+}
+
+/// Account ID of the active Steam account
+pub fn active_account_id() -> Option<String> {
+    active_account(&list_accounts())
+}
+
+/// Use the requested account, or the active one if none was given
+pub fn resolve_account(requested: Option<&str>) -> Result<String, String> {
+    match requested {
+        Some(id) if is_account_id(id) => Ok(id.to_string()),
+        Some(id) => Err(format!("Invalid Steam account ID: {}", id)),
+        None => active_account_id().ok_or_else(|| "No Steam account found".to_string()),
+    }
+}
+
+// ============================================================================
+// Remote Namespace
+// ============================================================================
+
+/// Folder under `boilerroom` holding the account namespaces. Not numeric, so
+/// it can't be mistaken for a game from before accounts were separated.
+pub const ACCOUNTS_DIR: &str = "accounts";
+
+/// Map a storage path into an account's namespace: `accounts/<account_id>`
+/// goes right after `boilerroom`
+fn account_path(path: &str, account_id: &str) -> String {
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.first() != Some(&"boilerroom") {
+        return path.to_string();
+    }
+
+    segments.splice(1..1, [ACCOUNTS_DIR, account_id]);
+
+    let mut mapped = segments.join("/");
+    if path.ends_with('/') {
+        mapped.push('/');
+    }
+    mapped
+}
+
+/// Storage wrapper confining everything to one account's namespace
+pub struct AccountStorage {
+    inner: Box<dyn CloudStorage>,
+    account_id: String,
+}
+
+impl AccountStorage {
+    pub fn new(inner: Box<dyn CloudStorage>, account_id: &str) -> Self {
+        Self {
+            inner,
+            account_id: account_id.to_string(),
+        }
+    }
+
+    fn map(&self, path: &str) -> String {
+        account_path(path, &self.account_id)
+    }
+}
+
+#[async_trait]
+impl CloudStorage for AccountStorage {
+    async fn test_connection(&self) -> Result<String, String> {
+        self.inner.test_connection().await
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        self.inner.list(&self.map(path)).await
+    }
+
+    async fn list_recursive(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        self.inner.list_recursive(&self.map(path)).await
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String> {
+        self.inner.stat(&self.map(path)).await
+    }
+
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String> {
+        self.inner.upload(&self.map(path), content).await
+    }
+
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String> {
+        self.inner.download(&self.map(path)).await
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        self.inner.delete(&self.map(path)).await
+    }
+
+    async fn mkdir(&self, path: &str) -> Result<(), String> {
+        self.inner.mkdir(&self.map(path)).await
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        self.inner.copy(&self.map(from), &self.map(to)).await
+    }
+
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        self.inner.upload_from(&self.map(path), local, progress).await
+    }
+
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        self.inner.download_to(&self.map(path), local, progress).await
+    }
+}
+
+/// Move a game's data from the shared pre-account layout into an account's
+/// namespace, unless the account already has data for it. Works on the raw
+/// backend: encrypted names are kept as they are, together with the key file.
+/// Only run from the layout migration, never while syncing. Returns the
+/// number of files moved.
+pub async fn adopt_legacy_data(
+    backend: &dyn CloudStorage,
+    account_id: &str,
+    app_id: &str,
+) -> Result<u32, String> {
+    let legacy_dir = format!("boilerroom/{}", app_id);
+    let legacy_files: Vec<RemoteEntry> = backend
+        .list_recursive(&legacy_dir)
+        .await?
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .collect();
+    if legacy_files.is_empty() {
+        return Ok(0);
+    }
+
+    let account_dir = account_path(&legacy_dir, account_id);
+    if !backend.list(&account_dir).await?.is_empty() {
+        return Ok(0);
+    }
+
+    eprintln!(
+        "[CloudSync] Moving saves of {} into the namespace of account {}",
+        app_id, account_id
+    );
+
+    let mut moves: Vec<String> = legacy_files
+        .iter()
+        .map(|e| format!("{}/{}", legacy_dir, e.name))
+        .collect();
+    let snapshots_dir = format!("boilerroom/.snapshots/{}", app_id);
+    for entry in backend.list_recursive(&snapshots_dir).await? {
+        if !entry.is_dir {
+            moves.push(format!("{}/{}", snapshots_dir, entry.name));
+        }
+    }
+    for metadata in [
+        format!("boilerroom/.index/{}.json", app_id),
+        format!("boilerroom/.keys/{}.json", app_id),
+    ] {
+        if backend.stat(&metadata).await?.is_some() {
+            moves.push(metadata);
+        }
+    }
+
+    let mut moved = 0;
+    for source in &moves {
+        let target = account_path(source, account_id);
+        backend.mkdir(parent_path(&target)).await?;
+        backend.copy(source, &target).await?;
+        backend.delete(source).await?;
+        moved += 1;
+    }
+
+    backend.delete(&format!("{}/", legacy_dir)).await?;
+    backend.delete(&format!("{}/", snapshots_dir)).await?;
+    Ok(moved)
+}
+
+/// Create the storage of an account: the configured backend confined to the
+/// account's namespace, encrypted if enabled. Has no side effects on the
+/// remote.
+pub async fn create_account_storage(
+    config: &CloudSyncConfig,
+    account_id: &str,
+) -> Result<Box<dyn CloudStorage>, String> {
+    let backend = create_backend(config)?;
+    let storage: Box<dyn CloudStorage> = Box::new(AccountStorage::new(backend, account_id));
+    if !config.encryption_enabled {
+        return Ok(storage);
+    }
+
+    let passphrase = load_passphrase().ok_or("Encryption is enabled but no passphrase is set")?;
+    Ok(Box::new(EncryptedStorage::new(storage, passphrase)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsync_storage::LocalStorage;
+
+    const LOGINUSERS_VDF: &str = r#"
+"users"
+{
+	"76561197960287930"
+	{
+		"AccountName"		"gaben"
+		"PersonaName"		"Gabe"
+		"RememberPassword"		"1"
+		"MostRecent"		"0"
+		"Timestamp"		"1700000000"
+	}
+	"76561198000000000"
+	{
+		"AccountName"		"deck"
+		"PersonaName"		"Deck User"
+		"RememberPassword"		"1"
+		"MostRecent"		"1"
+		"Timestamp"		"1600000000"
+	}
+}
+"#;
+
+    #[test]
+    fn test_parse_loginusers() {
+        let accounts = parse_loginusers(LOGINUSERS_VDF);
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].account_id, "22202");
+        assert_eq!(accounts[0].account_name.as_deref(), Some("gaben"));
+        assert!(!accounts[0].most_recent);
+        assert_eq!(accounts[1].account_id, "39734272");
+        assert!(accounts[1].most_recent);
+
+        // MostRecent wins over a later timestamp
+        assert_eq!(active_account(&accounts), Some("39734272".to_string()));
+    }
+
+    #[test]
+    fn test_active_account_fallbacks() {
+        let steam = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(steam.path().join("userdata/555")).unwrap();
+        std::fs::create_dir_all(steam.path().join("userdata/0")).unwrap();
+
+        // No loginusers.vdf: the userdata folder decides
+        let accounts = list_accounts_in(steam.path());
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(active_account(&accounts), Some("555".to_string()));
+
+        // Without MostRecent, the latest login is the active one
+        std::fs::create_dir_all(steam.path().join("config")).unwrap();
+        std::fs::write(
+            steam.path().join("config/loginusers.vdf"),
+            LOGINUSERS_VDF.replace("\"MostRecent\"\t\t\"1\"", "\"MostRecent\"\t\t\"0\""),
+        )
+        .unwrap();
+        let accounts = list_accounts_in(steam.path());
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(active_account(&accounts), Some("22202".to_string()));
+    }
+
+    #[test]
+    fn test_account_path() {
+        assert_eq!(
+            account_path("boilerroom/730/saves/a.sav", "42"),
+            "boilerroom/accounts/42/730/saves/a.sav"
+        );
+        assert_eq!(
+            account_path("boilerroom/.index/730.json", "42"),
+            "boilerroom/accounts/42/.index/730.json"
+        );
+        assert_eq!(
+            account_path("boilerroom/.snapshots/730/", "42"),
+            "boilerroom/accounts/42/.snapshots/730/"
+        );
+        assert_eq!(account_path("boilerroom", "42"), "boilerroom/accounts/42");
+        assert_eq!(account_path("other/730", "42"), "other/730");
+    }
+
+    #[tokio::test]
+    async fn test_adopt_legacy_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalStorage::new(dir.path().to_str().unwrap()).unwrap();
+        backend.mkdir("boilerroom/730/saves").await.unwrap();
+        backend.upload("boilerroom/730/saves/a.sav", b"save".to_vec()).await.unwrap();
+        backend.mkdir("boilerroom/.index").await.unwrap();
+        backend.upload("boilerroom/.index/730.json", b"{}".to_vec()).await.unwrap();
+
+        assert_eq!(adopt_legacy_data(&backend, "42", "730").await.unwrap(), 2);
+        assert_eq!(
+            backend.download("boilerroom/accounts/42/730/saves/a.sav").await.unwrap(),
+            b"save"
+        );
+        assert!(backend.stat("boilerroom/accounts/42/.index/730.json").await.unwrap().is_some());
+        assert!(backend.list("boilerroom/730").await.unwrap().is_empty());
+
+        // Through the account view the game is where it always was
+        let storage = AccountStorage::new(Box::new(backend), "42");
+        assert_eq!(storage.list_files("730").await.unwrap(), vec!["saves/a.sav".to_string()]);
+
+        // Another account finds nothing left to adopt
+        let backend = LocalStorage::new(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(adopt_legacy_data(&backend, "43", "730").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,612 @@
+//! CloudSync Archives - Portable export and import of a game's saves
+//!
+//! An archive holds the resolved save files of a game next to a manifest of
+//! where each one came from: its `CloudFile` (root and relative path) and the
+//! path it had on the exporting machine. Importing resolves every file again
+//! with `resolve_cloud_file_path`, so the Steam account and Proton prefix of
+//! the importing machine are used. Files PCGamingWiki located (root -1) only
+//! have an absolute path, which is remapped from the old prefix, install
+//! directory and home onto the new ones.
+//!
+//! Archives can come from anywhere, so the manifest is validated before
+//! anything is written: relative paths may only have plain segments, and
+//! every target has to stay inside the root it was resolved from (a save
+//! location of the game on this machine for root -1).
+//!
+//! Archive layout (zip or 7z):
+//! - manifest.json
+//! - saves/<root>/<path>        (saves/custom/<path> for root -1)
+
+use crate::cloudsync::{resolve_cloud_file_path, CloudFile};
+use crate::cloudsync_rollback::{copy_atomic, RollbackRestore, RollbackSession};
+use crate::cloudsync_roots::{self, GameLocation};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Magic bytes at the start of every 7z file
+const SEVENZ_MAGIC: &[u8] = b"7z\xBC\xAF\x27\x1C";
+
+/// Container format of an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    #[default]
+    Zip,
+    #[serde(rename = "7z")]
+    SevenZ,
+}
+
+/// A save file inside an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// Where the file is stored in the archive
+    pub archive_path: String,
+    /// The file as tracked on the exporting machine; `resolved_path` is
+    /// where it was found there
+    pub file: CloudFile,
+}
+
+/// Describes an archive (stored as manifest.json inside it)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub app_id: String,
+    /// Steam account the saves were exported from
+    pub account_id: String,
+    pub created_at: String,
+    /// Home directory and game location of the exporting machine, for
+    /// remapping absolute paths
+    pub source_home: Option<PathBuf>,
+    pub source_location: GameLocation,
+    pub files: Vec<ArchivedFile>,
+}
+
+impl ArchiveManifest {
+    pub fn new(
+        app_id: &str,
+        account_id: &str,
+        location: GameLocation,
+        files: Vec<ArchivedFile>,
+    ) -> Self {
+        Self {
+            version: ARCHIVE_VERSION,
+            app_id: app_id.to_string(),
+            account_id: account_id.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            source_home: dirs::home_dir(),
+            source_location: location,
+            files,
+        }
+    }
+}
+
+/// Turn a relative path into archive-safe segments, dropping anything that
+/// could escape the archive root (`..`, absolute prefixes)
+fn safe_segments(path: &str) -> Vec<String> {
+    let normalized = path.replace('\\', "/");
+    Path::new(&normalized)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(segment) => Some(segment.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a relative path has only plain segments (no `..`, no root or
+/// drive prefix)
+pub fn is_safe_relative(path: &str) -> bool {
+    let normalized = path.replace('\\', "/");
+    !normalized.is_empty()
+        && Path::new(&normalized)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn is_numeric_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Where a save file is stored in an archive
+pub fn archive_path_for(file: &CloudFile) -> String {
+    let root = if file.root < 0 {
+        "custom".to_string()
+    } else {
+        file.root.to_string()
+    };
+    format!("saves/{}/{}", root, safe_segments(&file.path).join("/"))
+}
+
+// ============================================================================
+// Writing
+// ============================================================================
+
+/// Write an archive: the manifest plus, for every file in it, the local file
+/// it was resolved to
+pub fn write_archive(
+    dest: &Path,
+    format: ArchiveFormat,
+    manifest: &ArchiveManifest,
+) -> Result<(), String> {
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize archive manifest: {}", e))?;
+    let sources: Vec<(&str, &Path)> = manifest
+        .files
+        .iter()
+        .filter_map(|f| Some((f.archive_path.as_str(), f.file.resolved_path.as_deref()?)))
+        .collect();
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    // Written next to the destination and renamed, so a failed export never
+    // leaves a truncated archive behind
+    let mut tmp_name = dest.as_os_str().to_owned();
+    tmp_name.push(".boilerroom-tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let result = match format {
+        ArchiveFormat::Zip => write_zip(&tmp_path, &manifest_json, &sources),
+        ArchiveFormat::SevenZ => write_7z(&tmp_path, &manifest_json, &sources),
+    };
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    std::fs::rename(&tmp_path, dest).map_err(|e| format!("Failed to save archive: {}", e))
+}
+
+fn write_zip(dest: &Path, manifest_json: &[u8], sources: &[(&str, &Path)]) -> Result<(), String> {
+    let file = File::create(dest).map_err(|e| format!("Failed to create archive: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file(MANIFEST_FILE, options)
+        .and_then(|_| zip.write_all(manifest_json).map_err(Into::into))
+        .map_err(|e| format!("Failed to write archive manifest: {}", e))?;
+
+    for (archive_path, local) in sources {
+        let mut source =
+            File::open(local).map_err(|e| format!("Failed to open {}: {}", local.display(), e))?;
+        zip.start_file(*archive_path, options)
+            .map_err(|e| format!("Failed to add {} to archive: {}", archive_path, e))?;
+        std::io::copy(&mut source, &mut zip)
+            .map_err(|e| format!("Failed to add {} to archive: {}", archive_path, e))?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(())
+}
+
+fn write_7z(dest: &Path, manifest_json: &[u8], sources: &[(&str, &Path)]) -> Result<(), String> {
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+
+    // 7z entries are added from files on disk
+    let staging = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {}", e))?;
+    let manifest_path = staging.path().join(MANIFEST_FILE);
+    std::fs::write(&manifest_path, manifest_json)
+        .map_err(|e| format!("Failed to write archive manifest: {}", e))?;
+
+    let mut writer =
+        SevenZWriter::create(dest).map_err(|e| format!("Failed to create archive: {}", e))?;
+    let entries =
+        std::iter::once((MANIFEST_FILE, manifest_path.as_path())).chain(sources.iter().copied());
+    for (archive_path, local) in entries {
+        let source =
+            File::open(local).map_err(|e| format!("Failed to open {}: {}", local.display(), e))?;
+        writer
+            .push_archive_entry(
+                SevenZArchiveEntry::from_path(local, archive_path.to_string()),
+                Some(source),
+            )
+            .map_err(|e| format!("Failed to add {} to archive: {}", archive_path, e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+/// Unpack an archive into `dir` and read its manifest
+pub fn extract_archive(src: &Path, dir: &Path) -> Result<ArchiveManifest, String> {
+    let mut magic = [0u8; 6];
+    File::open(src)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+    if magic == SEVENZ_MAGIC {
+        // Unlike zip's extract, sevenz_rust joins entry names onto the
+        // destination as they are
+        sevenz_rust::decompress_file_with_extract_fn(src, dir, |entry, reader, dest| {
+            if !is_safe_relative(entry.name()) {
+                return Err(sevenz_rust::Error::other(format!(
+                    "unsafe entry name {}",
+                    entry.name()
+                )));
+            }
+            sevenz_rust::default_entry_extract_fn(entry, reader, dest)
+        })
+        .map_err(|e| format!("Failed to extract archive: {}", e))?;
+    } else {
+        let file = File::open(src).map_err(|e| format!("Failed to read archive: {}", e))?;
+        zip::ZipArchive::new(file)
+            .and_then(|mut zip| zip.extract(dir))
+            .map_err(|e| format!("Failed to extract archive: {}", e))?;
+    }
+
+    let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|_| "Not a save archive (no manifest.json)".to_string())?;
+    let manifest: ArchiveManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse archive manifest: {}", e))?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} is newer than this version of BoilerRoom supports",
+            manifest.version
+        ));
+    }
+    validate_manifest(&manifest)?;
+    Ok(manifest)
+}
+
+/// Reject manifests whose ids or relative paths could point outside the
+/// save roots
+fn validate_manifest(manifest: &ArchiveManifest) -> Result<(), String> {
+    if !is_numeric_id(&manifest.app_id) {
+        return Err(format!("Invalid AppID in archive: {}", manifest.app_id));
+    }
+    if !is_numeric_id(&manifest.account_id) {
+        return Err(format!(
+            "Invalid account ID in archive: {}",
+            manifest.account_id
+        ));
+    }
+    for archived in &manifest.files {
+        if !archived.archive_path.starts_with("saves/")
+            || !is_safe_relative(&archived.archive_path)
+        {
+            return Err(format!(
+                "Invalid file in archive: {}",
+                archived.archive_path
+            ));
+        }
+        if archived.file.root >= 0 && !is_safe_relative(&archived.file.path) {
+            return Err(format!("Invalid save path in archive: {}", archived.file.path));
+        }
+    }
+    Ok(())
+}
+
+/// Move `path` from under `from` to under `to`, if it is under `from`. The
+/// part below `from` may only have plain segments.
+fn rebase(path: &Path, from: Option<&Path>, to: Option<&Path>) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from?).ok()?;
+    if !rest
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(to?.join(rest))
+}
+
+/// Map an absolute path of the exporting machine onto this one: the Proton
+/// prefix and install directory first, then the home directory. Steam
+/// userdata folders are moved to the importing account.
+pub fn remap_path(
+    path: &Path,
+    manifest: &ArchiveManifest,
+    account_id: &str,
+    location: &GameLocation,
+    home: Option<&Path>,
+) -> Option<PathBuf> {
+    let source = &manifest.source_location;
+    let mapped = rebase(
+        path,
+        source.proton_prefix.as_deref(),
+        location.proton_prefix.as_deref(),
+    )
+    .or_else(|| {
+        rebase(
+            path,
+            source.install_dir.as_deref(),
+            location.install_dir.as_deref(),
+        )
+    })
+    .or_else(|| rebase(path, manifest.source_home.as_deref(), home))?;
+
+    let mut out = PathBuf::new();
+    let mut after_userdata = false;
+    for component in mapped.components() {
+        let segment = component.as_os_str();
+        if after_userdata && segment == manifest.account_id.as_str() {
+            out.push(account_id);
+        } else {
+            out.push(segment);
+        }
+        after_userdata = segment == "userdata";
+    }
+    Some(out)
+}
+
+/// Where an archived file goes on this machine. Files PCGamingWiki located
+/// must land inside one of `save_roots`, the game's save locations here;
+/// Steam Cloud files inside their resolved root.
+pub fn import_target(
+    archived: &ArchivedFile,
+    manifest: &ArchiveManifest,
+    account_id: &str,
+    location: &GameLocation,
+    home: Option<&Path>,
+    save_roots: &[PathBuf],
+) -> Option<PathBuf> {
+    if archived.file.root < 0 {
+        let original = archived.file.resolved_path.as_deref()?;
+        let target = remap_path(original, manifest, account_id, location, home)?;
+        save_roots
+            .iter()
+            .any(|root| target.starts_with(root) && target != *root)
+            .then_some(target)
+    } else {
+        if !is_safe_relative(&archived.file.path) {
+            return None;
+        }
+        let base =
+            cloudsync_roots::resolve_root(archived.file.root, &manifest.app_id, account_id, location)?;
+        resolve_cloud_file_path(&archived.file, &manifest.app_id, account_id, location)
+            .filter(|target| target.starts_with(&base) && *target != base)
+    }
+}
+
+/// Copy the files of an extracted archive into place, backing up the local
+/// files they replace
+pub fn import_files(
+    dir: &Path,
+    manifest: &ArchiveManifest,
+    account_id: &str,
+    location: &GameLocation,
+    save_roots: &[PathBuf],
+    rollback: Option<&RollbackSession>,
+) -> RollbackRestore {
+    let home = dirs::home_dir();
+    let mut result = RollbackRestore::default();
+
+    for archived in &manifest.files {
+        let path = &archived.file.path;
+        let target = match import_target(
+            archived,
+            manifest,
+            account_id,
+            location,
+            home.as_deref(),
+            save_roots,
+        ) {
+            Some(t) => t,
+            None => {
+                result
+                    .errors
+                    .push(format!("{}: no matching location on this machine", path));
+                continue;
+            }
+        };
+
+        let mut source = dir.to_path_buf();
+        source.extend(safe_segments(&archived.archive_path));
+
+        let restored = match rollback {
+            Some(rollback) => rollback.backup(path, &target),
+            None => Ok(()),
+        }
+        .and_then(|_| copy_atomic(&source, &target));
+        match restored {
+            Ok(_) => result.files_restored += 1,
+            Err(e) => result.errors.push(format!("{}: {}", path, e)),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cloud_file(path: &str, root: i32, resolved: &Path) -> CloudFile {
+        CloudFile {
+            path: path.to_string(),
+            root,
+            size: 0,
+            localtime: 0,
+            remotetime: 0,
+            sha: String::new(),
+            syncstate: 0,
+            resolved_path: Some(resolved.to_path_buf()),
+        }
+    }
+
+    #[test]
+    fn test_archive_path_for() {
+        let file = cloud_file("saves\\slot1.sav", 0, Path::new("/x"));
+        assert_eq!(archive_path_for(&file), "saves/0/saves/slot1.sav");
+        let file = cloud_file("../../etc/passwd", -1, Path::new("/x"));
+        assert_eq!(archive_path_for(&file), "saves/custom/etc/passwd");
+    }
+
+    #[test]
+    fn test_remap_path() {
+        let mut manifest =
+            ArchiveManifest::new("1245620", "111", GameLocation::default(), Vec::new());
+        manifest.source_home = Some(PathBuf::from("/home/alice"));
+        manifest.source_location.proton_prefix = Some(PathBuf::from(
+            "/home/alice/.local/share/Steam/steamapps/compatdata/1245620/pfx",
+        ));
+
+        let location = GameLocation {
+            install_dir: None,
+            proton_prefix: Some(PathBuf::from("/mnt/games/steamapps/compatdata/1245620/pfx")),
+        };
+        let home = Some(Path::new("/home/deck"));
+
+        assert_eq!(
+            remap_path(
+                Path::new("/home/alice/.local/share/Steam/steamapps/compatdata/1245620/pfx/drive_c/users/steamuser/AppData/Roaming/EldenRing/ER0000.sl2"),
+                &manifest,
+                "222",
+                &location,
+                home
+            ),
+            Some(PathBuf::from(
+                "/mnt/games/steamapps/compatdata/1245620/pfx/drive_c/users/steamuser/AppData/Roaming/EldenRing/ER0000.sl2"
+            ))
+        );
+        assert_eq!(
+            remap_path(
+                Path::new("/home/alice/.local/share/Steam/userdata/111/1245620/remote/a.sav"),
+                &manifest,
+                "222",
+                &location,
+                home
+            ),
+            Some(PathBuf::from(
+                "/home/deck/.local/share/Steam/userdata/222/1245620/remote/a.sav"
+            ))
+        );
+        assert_eq!(
+            remap_path(
+                Path::new("/opt/elsewhere"),
+                &manifest,
+                "222",
+                &location,
+                home
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let saves = tempfile::tempdir().unwrap();
+        let slot = saves.path().join("slot1.sav");
+        std::fs::write(&slot, b"save data").unwrap();
+
+        for format in [ArchiveFormat::Zip, ArchiveFormat::SevenZ] {
+            let out = tempfile::tempdir().unwrap();
+            let archive = out.path().join("export");
+            let file = cloud_file("slot1.sav", -1, &slot);
+            let mut manifest = ArchiveManifest::new(
+                "730",
+                "111",
+                GameLocation::default(),
+                vec![ArchivedFile {
+                    archive_path: archive_path_for(&file),
+                    file,
+                }],
+            );
+            manifest.source_home = Some(saves.path().to_path_buf());
+            write_archive(&archive, format, &manifest).unwrap();
+
+            let extracted = tempfile::tempdir().unwrap();
+            let read = extract_archive(&archive, extracted.path()).unwrap();
+            assert_eq!(read.files.len(), 1);
+
+            // Restored under this machine's home
+            let target_home = tempfile::tempdir().unwrap();
+            let target = import_target(
+                &read.files[0],
+                &read,
+                "222",
+                &GameLocation::default(),
+                Some(target_home.path()),
+                &[target_home.path().to_path_buf()],
+            );
+            assert_eq!(target, Some(target_home.path().join("slot1.sav")));
+
+            let archived = &read.files[0];
+            let mut source = extracted.path().to_path_buf();
+            source.extend(safe_segments(&archived.archive_path));
+            assert_eq!(std::fs::read(source).unwrap(), b"save data");
+        }
+    }
+
+    #[test]
+    fn test_hostile_manifest() {
+        let home = tempfile::tempdir().unwrap();
+        let saves = home.path().join("Game/saves");
+        let location = GameLocation::default();
+        let mut manifest = ArchiveManifest::new("730", "111", location.clone(), Vec::new());
+        manifest.source_home = Some(PathBuf::from("/home/alice"));
+
+        // Steam Cloud paths climbing out of their root
+        for path in ["../../.bashrc", "/etc/x", "saves/../../../x"] {
+            let file = cloud_file(path, 0, Path::new("/x"));
+            let archived = ArchivedFile {
+                archive_path: "saves/0/x".to_string(),
+                file,
+            };
+            let mut hostile = manifest.clone();
+            hostile.files = vec![archived.clone()];
+            assert!(validate_manifest(&hostile).is_err(), "{}", path);
+            assert_eq!(
+                import_target(&archived, &manifest, "222", &location, Some(home.path()), &[]),
+                None
+            );
+        }
+
+        // Absolute paths outside this machine's save locations
+        let roots = [saves.clone()];
+        for (source_home, resolved) in [
+            ("/home/alice", "/home/alice/.bashrc"),
+            ("/home/alice", "/home/alice/Game/saves/../../.bashrc"),
+            ("/", "/etc/x"),
+        ] {
+            let mut hostile = manifest.clone();
+            hostile.source_home = Some(PathBuf::from(source_home));
+            let archived = ArchivedFile {
+                archive_path: "saves/custom/x".to_string(),
+                file: cloud_file("x", -1, Path::new(resolved)),
+            };
+            assert_eq!(
+                import_target(&archived, &hostile, "222", &location, Some(home.path()), &roots),
+                None,
+                "{}",
+                resolved
+            );
+        }
+        let archived = ArchivedFile {
+            archive_path: "saves/custom/slot1.sav".to_string(),
+            file: cloud_file("slot1.sav", -1, Path::new("/home/alice/Game/saves/slot1.sav")),
+        };
+        assert_eq!(
+            import_target(&archived, &manifest, "222", &location, Some(home.path()), &roots),
+            Some(saves.join("slot1.sav"))
+        );
+
+        // Ids that end up in paths
+        let mut hostile = manifest.clone();
+        hostile.app_id = "../730".to_string();
+        assert!(validate_manifest(&hostile).is_err());
+        let mut hostile = manifest.clone();
+        hostile.account_id = "..".to_string();
+        assert!(validate_manifest(&hostile).is_err());
+        let mut hostile = manifest.clone();
+        hostile.files = vec![ArchivedFile {
+            archive_path: "../manifest.json".to_string(),
+            file: cloud_file("x", -1, Path::new("/x")),
+        }];
+        assert!(validate_manifest(&hostile).is_err());
+    }
+}
@@ -0,0 +1,1189 @@
+//! CloudSync Crypto - Optional client-side encryption of remote saves
+//!
+//! When `CloudSyncConfig.encryption_enabled` is set, the storage backend is
+//! wrapped in `EncryptedStorage`, which encrypts before anything leaves the machine:
+//! - Contents: XChaCha20-Poly1305 in the STREAM construction (1 MiB chunks
+//!   under a random per-file nonce prefix), behind a small header naming the
+//!   key that was used. Transfers encrypt and decrypt chunk by chunk between
+//!   disk files, so the size of a save doesn't matter.
+//! - Names: every path segment below a game's collection is encrypted
+//!   deterministically (synthetic nonce) and base32 encoded, so a save path
+//!   always maps to the same remote path.
+//!
+//! Files written before contents were chunked ("BRE1", one piece) can still be
+//! read; their listed size is only exact below one chunk, and rotating the
+//! game's key rewrites them in the chunked format.
+//!
+//! Each game has its own data key, derived with Argon2id from the user's
+//! passphrase and a per-game salt kept in `boilerroom/.keys/<app_id>.json`.
+//! Rotating a game's key picks a new salt and re-encrypts its remote set; the
+//! old key stays in the key file until no file uses it any more.
+//!
+//! The passphrase is never written to the settings store: it lives in
+//! ~/.local/share/boilerroom/cloudsync/passphrase (mode 0600), or comes from
+//! the BOILERROOM_CLOUDSYNC_PASSPHRASE environment variable.
+
+use crate::cloudsync_storage::{parent_path, CloudStorage, Progress, RemoteEntry};
+use argon2::{Algorithm, Argon2, Params, Version};
+use async_trait::async_trait;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"BRE2";
+/// Header of files encrypted in one piece, before contents were chunked
+const LEGACY_MAGIC: &[u8; 4] = b"BRE1";
+const KEY_ID_LEN: usize = 8;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+
+/// MAGIC | key id | nonce
+const HEADER_LEN: usize = MAGIC.len() + KEY_ID_LEN + NONCE_LEN;
+/// Plaintext bytes per chunk; every chunk but the last is full
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// STREAM nonce prefix: the nonce less its 5 byte chunk counter
+const STREAM_PREFIX_LEN: usize = NONCE_LEN - 5;
+
+const PASSPHRASE_ENV: &str = "BOILERROOM_CLOUDSYNC_PASSPHRASE";
+
+/// Argon2id cost (memory KiB, iterations, parallelism)
+#[cfg(not(test))]
+const KDF_COST: (u32, u32, u32) = (19 * 1024, 2, 1);
+#[cfg(test)]
+const KDF_COST: (u32, u32, u32) = (64, 1, 1);
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Serializes key creation and rotation. Process-wide, since every sync
+/// builds its own `EncryptedStorage`.
+static KEY_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// ============================================================================
+// Keys
+// ============================================================================
+
+/// Argon2id parameters and salt of one data key (not secret)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyParams {
+    pub salt: String,
+    /// Fingerprint of the derived key, used to detect a wrong passphrase
+    pub key_id: String,
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+/// Key file of a game, stored unencrypted at boilerroom/.keys/<app_id>.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameKeyFile {
+    pub current: KeyParams,
+    /// Key being rotated away from; only set while a rotation is in progress
+    #[serde(default)]
+    pub previous: Option<KeyParams>,
+}
+
+/// A derived data key, split into independent sub-keys
+#[derive(Clone)]
+pub struct DataKey {
+    id: [u8; KEY_ID_LEN],
+    content_key: [u8; 32],
+    name_key: [u8; 32],
+    siv_key: [u8; 32],
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if !s.len().is_multiple_of(2) {
+        return Err("Invalid hex string".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| "Invalid hex string".to_string()))
+        .collect()
+}
+
+impl DataKey {
+    fn from_master(master: &[u8; 32]) -> Self {
+        let mut id = [0u8; KEY_ID_LEN];
+        id.copy_from_slice(&Sha256::digest(master)[..KEY_ID_LEN]);
+        Self {
+            id,
+            content_key: hmac_sha256(master, b"boilerroom content"),
+            name_key: hmac_sha256(master, b"boilerroom names"),
+            siv_key: hmac_sha256(master, b"boilerroom name nonce"),
+        }
+    }
+
+    pub fn id_hex(&self) -> String {
+        to_hex(&self.id)
+    }
+}
+
+/// Derive the data key described by `params` from a passphrase
+pub fn derive_key(passphrase: &str, params: &KeyParams) -> Result<DataKey, String> {
+    let salt = from_hex(&params.salt)?;
+    let argon_params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| format!("Invalid key parameters: {}", e))?;
+
+    let mut master = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut master)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+
+    Ok(DataKey::from_master(&master))
+}
+
+/// Derive a key and check it against the fingerprint in `params`
+pub fn open_key(passphrase: &str, params: &KeyParams) -> Result<DataKey, String> {
+    let key = derive_key(passphrase, params)?;
+    if key.id_hex() != params.key_id {
+        return Err("Wrong encryption passphrase".to_string());
+    }
+    Ok(key)
+}
+
+/// Generate a fresh salt and derive a new key from it
+pub fn new_key(passphrase: &str) -> Result<(KeyParams, DataKey), String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let (m_cost, t_cost, p_cost) = KDF_COST;
+    let mut params = KeyParams {
+        salt: to_hex(&salt),
+        key_id: String::new(),
+        m_cost,
+        t_cost,
+        p_cost,
+    };
+    let key = derive_key(passphrase, &params)?;
+    params.key_id = key.id_hex();
+
+    Ok((params, key))
+}
+
+// ============================================================================
+// Content and Name Encryption
+// ============================================================================
+
+/// Size of a file once encrypted
+pub fn encrypted_size(plain: u64) -> u64 {
+    HEADER_LEN as u64 + plain + TAG_LEN as u64 * (plain / CHUNK_SIZE as u64 + 1)
+}
+
+/// Size of an encrypted file's contents
+pub fn plain_size(encrypted: u64) -> u64 {
+    let body = encrypted.saturating_sub(HEADER_LEN as u64);
+    let chunks = body.saturating_sub(TAG_LEN as u64) / (CHUNK_SIZE + TAG_LEN) as u64 + 1;
+    body.saturating_sub(TAG_LEN as u64 * chunks)
+}
+
+fn crypto_error(message: &str) -> std::io::Error {
+    std::io::Error::other(message.to_string())
+}
+
+/// Writer encrypting everything written to it: MAGIC | key id | nonce | chunks.
+/// Full chunks are sealed as they fill up; `finish` seals the last, shorter
+/// (possibly empty) one, so a truncated file never decrypts.
+struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: EncryptorBE32<XChaCha20Poly1305>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    fn new(key: &DataKey, mut inner: W) -> std::io::Result<Self> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        inner.write_all(MAGIC)?;
+        inner.write_all(&key.id)?;
+        inner.write_all(&nonce)?;
+
+        Ok(Self {
+            inner,
+            encryptor: EncryptorBE32::new((&key.content_key).into(), nonce[..STREAM_PREFIX_LEN].into()),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        })
+    }
+
+    fn finish(mut self) -> std::io::Result<W> {
+        let chunk = self
+            .encryptor
+            .encrypt_last(&self.buffer[..])
+            .map_err(|_| crypto_error("Encryption failed"))?;
+        self.inner.write_all(&chunk)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let taken = data.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..taken]);
+
+        if self.buffer.len() == CHUNK_SIZE {
+            let chunk = self
+                .encryptor
+                .encrypt_next(&self.buffer[..])
+                .map_err(|_| crypto_error("Encryption failed"))?;
+            self.inner.write_all(&chunk)?;
+            self.buffer.clear();
+        }
+        Ok(taken)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Read until `buf` is full or the reader ends; returns the bytes read
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Read the header of an encrypted file; None if the data isn't encrypted
+fn read_header(reader: &mut impl Read) -> std::io::Result<Option<[u8; HEADER_LEN]>> {
+    let mut header = [0u8; HEADER_LEN];
+    let complete = read_full(reader, &mut header)? == HEADER_LEN;
+    Ok((complete && (header.starts_with(MAGIC) || header.starts_with(LEGACY_MAGIC))).then_some(header))
+}
+
+fn header_key_id(header: &[u8; HEADER_LEN]) -> &[u8] {
+    &header[MAGIC.len()..MAGIC.len() + KEY_ID_LEN]
+}
+
+/// Decrypt an encrypted stream into `writer` with whichever of `keys` it was
+/// encrypted with. Chunks are written out as they are authenticated, so on
+/// error the writer holds a prefix of the contents that must be discarded.
+fn decrypt_stream(keys: &[DataKey], reader: &mut impl Read, writer: &mut impl Write) -> Result<(), String> {
+    const TAMPERED: &str = "Decryption failed - file is corrupted or was tampered with";
+    let read_error = |e: std::io::Error| format!("Failed to read encrypted data: {}", e);
+    let write_error = |e: std::io::Error| format!("Failed to write decrypted data: {}", e);
+
+    let header = read_header(reader)
+        .map_err(read_error)?
+        .ok_or("File is not encrypted")?;
+    let key = keys
+        .iter()
+        .find(|k| k.id == header_key_id(&header))
+        .ok_or("File was encrypted with an unknown key")?;
+    let nonce = &header[MAGIC.len() + KEY_ID_LEN..];
+
+    if header.starts_with(LEGACY_MAGIC) {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(read_error)?;
+        let plain = XChaCha20Poly1305::new((&key.content_key).into())
+            .decrypt(XNonce::from_slice(nonce), &data[..])
+            .map_err(|_| TAMPERED.to_string())?;
+        return writer.write_all(&plain).map_err(write_error);
+    }
+
+    let mut decryptor =
+        DecryptorBE32::<XChaCha20Poly1305>::new((&key.content_key).into(), nonce[..STREAM_PREFIX_LEN].into());
+    let mut buffer = vec![0u8; CHUNK_SIZE + TAG_LEN];
+    loop {
+        let read = read_full(reader, &mut buffer).map_err(read_error)?;
+        if read < buffer.len() {
+            let plain = decryptor
+                .decrypt_last(&buffer[..read])
+                .map_err(|_| TAMPERED.to_string())?;
+            writer.write_all(&plain).map_err(write_error)?;
+            return writer.flush().map_err(write_error);
+        }
+
+        let plain = decryptor
+            .decrypt_next(&buffer[..])
+            .map_err(|_| TAMPERED.to_string())?;
+        writer.write_all(&plain).map_err(write_error)?;
+    }
+}
+
+/// Encrypt file contents in memory
+pub fn encrypt_content(key: &DataKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let out = Vec::with_capacity(encrypted_size(data.len() as u64) as usize);
+    let mut writer = EncryptWriter::new(key, out).map_err(|e| e.to_string())?;
+    writer.write_all(data).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())
+}
+
+/// Decrypt file contents in memory with whichever of `keys` it was encrypted with
+pub fn decrypt_content(keys: &[DataKey], data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    decrypt_stream(keys, &mut &data[..], &mut out)?;
+    Ok(out)
+}
+
+/// Encrypt the local file `source` into `target`
+fn encrypt_file(key: &DataKey, source: &Path, target: &Path) -> Result<(), String> {
+    let mut reader = File::open(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", target.display(), e);
+
+    let file = File::create(target).map_err(write_error)?;
+    let mut writer = EncryptWriter::new(key, BufWriter::new(file)).map_err(write_error)?;
+    std::io::copy(&mut reader, &mut writer)
+        .map_err(|e| format!("Failed to encrypt {}: {}", source.display(), e))?;
+    writer.finish().map_err(write_error)?;
+    Ok(())
+}
+
+/// Decrypt the local file `source` into `target`
+fn decrypt_file(keys: &[DataKey], source: &Path, target: &Path) -> Result<(), String> {
+    let mut reader = File::open(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    let file = File::create(target).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    decrypt_stream(keys, &mut reader, &mut BufWriter::new(file))
+}
+
+/// Re-encrypt the local file `source` (possibly still plaintext) under
+/// `new_key` into `target`. Returns false, writing nothing, if it already
+/// uses that key.
+fn reencrypt_file(new_key: &DataKey, keys: &[DataKey], source: &Path, target: &Path) -> Result<bool, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read {}: {}", source.display(), e);
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", target.display(), e);
+
+    let mut reader = File::open(source).map_err(read_error)?;
+    let header = read_header(&mut reader).map_err(read_error)?;
+    if header.is_some_and(|h| header_key_id(&h) == new_key.id) {
+        return Ok(false);
+    }
+    reader.rewind().map_err(read_error)?;
+
+    let file = File::create(target).map_err(write_error)?;
+    let mut writer = EncryptWriter::new(new_key, BufWriter::new(file)).map_err(write_error)?;
+    if header.is_some() {
+        decrypt_stream(keys, &mut reader, &mut writer)?;
+    } else {
+        std::io::copy(&mut reader, &mut writer).map_err(read_error)?;
+    }
+    writer.finish().map_err(write_error)?;
+    Ok(true)
+}
+
+/// A temporary file for encrypted transfers, removed when dropped. Kept under
+/// ~/.local/share/boilerroom/cloudsync/tmp rather than /tmp, which may be in memory.
+fn scratch_file() -> Result<tempfile::NamedTempFile, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let dir = home.join(".local/share/boilerroom/cloudsync/tmp");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    tempfile::NamedTempFile::new_in(&dir).map_err(|e| format!("Failed to create temporary file: {}", e))
+}
+
+/// Run blocking file encryption off the async runtime
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Encryption task failed: {}", e))?
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Lowercase, unpadded base32 - safe on case-insensitive file systems
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    out
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes() {
+        let value = BASE32_ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+/// Encrypt a single path segment. Deterministic: the nonce is derived from the name.
+pub fn encrypt_name(key: &DataKey, name: &str) -> String {
+    let siv = hmac_sha256(&key.siv_key, name.as_bytes());
+    let nonce = XNonce::from_slice(&siv[..NONCE_LEN]);
+    let ciphertext = XChaCha20Poly1305::new((&key.name_key).into())
+        .encrypt(nonce, name.as_bytes())
+        .expect("encrypting into a Vec cannot fail");
+
+    let mut data = siv[..NONCE_LEN].to_vec();
+    data.extend_from_slice(&ciphertext);
+    base32_encode(&data)
+}
+
+/// Decrypt a path segment with whichever of `keys` it was encrypted with
+pub fn decrypt_name(keys: &[DataKey], encoded: &str) -> Option<String> {
+    let data = base32_decode(encoded)?;
+    if data.len() < NONCE_LEN + TAG_LEN {
+        return None;
+    }
+
+    let nonce = XNonce::from_slice(&data[..NONCE_LEN]);
+    keys.iter().find_map(|key| {
+        XChaCha20Poly1305::new((&key.name_key).into())
+            .decrypt(nonce, &data[NONCE_LEN..])
+            .ok()
+            .and_then(|plain| String::from_utf8(plain).ok())
+    })
+}
+
+// ============================================================================
+// Remote Layout
+// ============================================================================
+
+/// Find the game a storage path belongs to, and the index of the first path
+/// segment whose name is encrypted (usize::MAX if none are).
+///
+/// - boilerroom/<app_id>/<save path...>
+/// - boilerroom/.<area>/<app_id>/<id>/<save path...>   (snapshots)
+/// - boilerroom/.<area>/<app_id>.json                  (per-game metadata)
+/// - boilerroom/.keys/...                              (never encrypted)
+fn classify_path(segments: &[&str]) -> (Option<String>, usize) {
+    if segments.len() < 2 || segments[0] != "boilerroom" || segments[1] == ".keys" {
+        return (None, usize::MAX);
+    }
+
+    if segments[1].starts_with('.') {
+        let app_id = segments
+            .get(2)
+            .map(|s| s.strip_suffix(".json").unwrap_or(s).to_string());
+        (app_id, 4)
+    } else {
+        (Some(segments[1].to_string()), 2)
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Map a plaintext storage path to its encrypted form
+fn encrypt_path(path: &str, key: Option<&DataKey>) -> String {
+    let segments = split_path(path);
+    let (_, first_encrypted) = classify_path(&segments);
+
+    let mut out: Vec<String> = segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| match key {
+            Some(k) if i >= first_encrypted => encrypt_name(k, segment),
+            _ => segment.to_string(),
+        })
+        .collect();
+
+    if path.ends_with('/') {
+        out.push(String::new());
+    }
+    out.join("/")
+}
+
+/// Map encrypted path segments back to plaintext. `offset` is the position of
+/// the first segment within the full path. Segments that don't decrypt (files
+/// uploaded before encryption was enabled) are kept as-is.
+fn decrypt_segments(segments: &[&str], offset: usize, first_encrypted: usize, keys: &[DataKey]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            if i + offset >= first_encrypted {
+                decrypt_name(keys, segment).unwrap_or_else(|| segment.to_string())
+            } else {
+                segment.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn key_file_path(app_id: &str) -> String {
+    format!("boilerroom/.keys/{}.json", app_id)
+}
+
+// ============================================================================
+// Encrypted Storage Wrapper
+// ============================================================================
+
+/// Storage backend wrapper that encrypts everything belonging to a game
+pub struct EncryptedStorage {
+    inner: Box<dyn CloudStorage>,
+    passphrase: String,
+    /// Keys per game, current key first
+    keys: std::sync::Mutex<HashMap<String, Vec<DataKey>>>,
+}
+
+impl EncryptedStorage {
+    pub fn new(inner: Box<dyn CloudStorage>, passphrase: String) -> Self {
+        Self {
+            inner,
+            passphrase,
+            keys: Default::default(),
+        }
+    }
+
+    async fn load_key_file(&self, app_id: &str) -> Result<Option<GameKeyFile>, String> {
+        let path = key_file_path(app_id);
+        if self.inner.stat(&path).await?.is_none() {
+            return Ok(None);
+        }
+
+        let data = self.inner.download(&path).await?;
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| format!("Invalid key file for {}: {}", app_id, e))
+    }
+
+    async fn save_key_file(&self, app_id: &str, key_file: &GameKeyFile) -> Result<(), String> {
+        let data = serde_json::to_vec_pretty(key_file)
+            .map_err(|e| format!("Failed to serialize key file: {}", e))?;
+        self.inner.mkdir("boilerroom/.keys").await?;
+        self.inner.upload(&key_file_path(app_id), data).await
+    }
+
+    /// Keys of a game (current first); empty if the game has no key yet
+    async fn keys_for(&self, app_id: &str) -> Result<Vec<DataKey>, String> {
+        if let Some(keys) = self.keys.lock().unwrap().get(app_id) {
+            return Ok(keys.clone());
+        }
+
+        let keys = match self.load_key_file(app_id).await? {
+            Some(key_file) => {
+                let mut keys = vec![open_key(&self.passphrase, &key_file.current)?];
+                if let Some(previous) = &key_file.previous {
+                    keys.push(open_key(&self.passphrase, previous)?);
+                }
+                keys
+            }
+            None => Vec::new(),
+        };
+
+        if !keys.is_empty() {
+            self.keys.lock().unwrap().insert(app_id.to_string(), keys.clone());
+        }
+        Ok(keys)
+    }
+
+    /// Current key of a game, creating one (and encrypting any existing
+    /// plaintext remote files) if the game doesn't have one yet
+    async fn write_key_for(&self, app_id: &str) -> Result<DataKey, String> {
+        if let Some(key) = self.keys_for(app_id).await?.into_iter().next() {
+            return Ok(key);
+        }
+
+        // Another sync may have created the key while this one waited
+        let _guard = KEY_LOCK.lock().await;
+        if self.keys_for(app_id).await?.is_empty() {
+            self.rotate_locked(app_id).await?;
+        }
+        self.keys_for(app_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("No encryption key for {}", app_id))
+    }
+
+    /// Keys for a path: (game, keys), or (None, []) for paths outside any game
+    async fn path_keys(&self, path: &str) -> Result<(Option<String>, Vec<DataKey>), String> {
+        match classify_path(&split_path(path)).0 {
+            Some(app_id) => {
+                let keys = self.keys_for(&app_id).await?;
+                Ok((Some(app_id), keys))
+            }
+            None => Ok((None, Vec::new())),
+        }
+    }
+
+    /// Convert a raw listing entry (named relative to `raw_parent`) back to plaintext
+    fn decrypt_entry(&self, entry: RemoteEntry, parent: &str, keys: &[DataKey], encrypted: bool) -> RemoteEntry {
+        let parent_segments = split_path(parent);
+        let name_segments: Vec<&str> = entry.name.split('/').collect();
+
+        let mut full = parent_segments.clone();
+        full.extend(&name_segments);
+        let (_, first_encrypted) = classify_path(&full);
+
+        RemoteEntry {
+            name: decrypt_segments(&name_segments, parent_segments.len(), first_encrypted, keys),
+            size: if encrypted && !entry.is_dir {
+                plain_size(entry.size)
+            } else {
+                entry.size
+            },
+            ..entry
+        }
+    }
+
+    /// Re-encrypt a game's remote set (saves, snapshots, trash and index) under a
+    /// fresh key. Files that are still plaintext are encrypted as well.
+    /// Returns the number of files rewritten.
+    pub async fn rotate_key(&self, app_id: &str) -> Result<u32, String> {
+        let _guard = KEY_LOCK.lock().await;
+        self.rotate_locked(app_id).await
+    }
+
+    /// `rotate_key`, with KEY_LOCK held
+    async fn rotate_locked(&self, app_id: &str) -> Result<u32, String> {
+        let mut rewritten = 0;
+        let mut old = self.load_key_file(app_id).await?;
+
+        if let Some(key_file) = old.as_mut() {
+            if let Some(previous) = key_file.previous.take() {
+                // An earlier rotation was interrupted. Finish it first: the
+                // key file only has room for one old key, and files may still
+                // use this one.
+                let current = open_key(&self.passphrase, &key_file.current)?;
+                let keys = vec![current.clone(), open_key(&self.passphrase, &previous)?];
+                rewritten += self.reencrypt_all(app_id, &current, &keys).await?;
+                self.save_key_file(app_id, key_file).await?;
+            }
+        }
+
+        let mut old_keys = Vec::new();
+        if let Some(key_file) = &old {
+            old_keys.push(open_key(&self.passphrase, &key_file.current)?);
+        }
+
+        // Record the new key before touching any file, keeping the old one
+        // readable in case the rotation is interrupted
+        let (new_params, new_key) = new_key(&self.passphrase)?;
+        self.save_key_file(
+            app_id,
+            &GameKeyFile {
+                current: new_params.clone(),
+                previous: old.map(|k| k.current),
+            },
+        )
+        .await?;
+
+        let mut all_keys = vec![new_key.clone()];
+        all_keys.extend(old_keys);
+        self.keys.lock().unwrap().insert(app_id.to_string(), all_keys.clone());
+
+        rewritten += self.reencrypt_all(app_id, &new_key, &all_keys).await?;
+
+        self.save_key_file(
+            app_id,
+            &GameKeyFile {
+                current: new_params,
+                previous: None,
+            },
+        )
+        .await?;
+        self.keys.lock().unwrap().insert(app_id.to_string(), vec![new_key]);
+
+        eprintln!(
+            "[CloudSync] Re-encrypted {} files of app {} with a new key",
+            rewritten, app_id
+        );
+
+        Ok(rewritten)
+    }
+
+    /// Move every file of a game under `new_key`. Passes repeat until one
+    /// finds nothing left to rewrite, so files another sync uploaded with an
+    /// older key in the meantime are caught before that key is dropped.
+    async fn reencrypt_all(&self, app_id: &str, new_key: &DataKey, keys: &[DataKey]) -> Result<u32, String> {
+        let roots = [
+            format!("boilerroom/{}", app_id),
+            format!("boilerroom/.snapshots/{}", app_id),
+            format!("boilerroom/.trash/{}", app_id),
+        ];
+        let index_path = format!("boilerroom/.index/{}.json", app_id);
+        let mut rewritten = 0;
+
+        loop {
+            let mut pass = 0;
+            let mut stale_dirs = Vec::new();
+
+            for root in &roots {
+                for entry in self.inner.list_recursive(root).await? {
+                    let raw_path = format!("{}/{}", root, entry.name);
+                    let segments = split_path(&raw_path);
+                    let (_, first_encrypted) = classify_path(&segments);
+                    let plain_path = decrypt_segments(&segments, 0, first_encrypted, keys);
+                    let new_raw_path = encrypt_path(&plain_path, Some(new_key));
+
+                    // Names under the new key were written with it
+                    if new_raw_path == raw_path {
+                        continue;
+                    }
+                    if entry.is_dir {
+                        stale_dirs.push(raw_path);
+                    } else if self.rewrite_file(&raw_path, &new_raw_path, new_key, keys).await? {
+                        pass += 1;
+                    }
+                }
+            }
+
+            if self.inner.stat(&index_path).await?.is_some()
+                && self.rewrite_file(&index_path, &index_path, new_key, keys).await?
+            {
+                pass += 1;
+            }
+
+            if pass == 0 {
+                // Everything inside has been moved under the new names
+                for dir in stale_dirs {
+                    self.inner.delete(&format!("{}/", dir)).await?;
+                }
+                return Ok(rewritten);
+            }
+            rewritten += pass;
+        }
+    }
+
+    /// Re-encrypt one file under `new_key`, moving it to its new name.
+    /// Returns false if it was already encrypted with that key.
+    async fn rewrite_file(
+        &self,
+        raw_path: &str,
+        new_raw_path: &str,
+        new_key: &DataKey,
+        keys: &[DataKey],
+    ) -> Result<bool, String> {
+        let download = scratch_file()?;
+        let upload = scratch_file()?;
+        self.inner.download_to(raw_path, download.path(), &|_, _| {}).await?;
+
+        let (source, target) = (download.path().to_path_buf(), upload.path().to_path_buf());
+        let (new_key_owned, keys_owned) = (new_key.clone(), keys.to_vec());
+        let changed =
+            run_blocking(move || reencrypt_file(&new_key_owned, &keys_owned, &source, &target)).await?;
+        if !changed {
+            return Ok(false);
+        }
+
+        self.inner.mkdir(parent_path(new_raw_path)).await?;
+        self.inner.upload_from(new_raw_path, upload.path(), &|_, _| {}).await?;
+        if new_raw_path != raw_path {
+            self.inner.delete(raw_path).await?;
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl CloudStorage for EncryptedStorage {
+    async fn test_connection(&self) -> Result<String, String> {
+        self.inner.test_connection().await
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        let (app_id, keys) = self.path_keys(path).await?;
+        let raw = encrypt_path(path, keys.first());
+        let entries = self.inner.list(&raw).await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|e| self.decrypt_entry(e, &raw, &keys, app_id.is_some() && !keys.is_empty()))
+            .collect())
+    }
+
+    async fn list_recursive(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        let (app_id, keys) = self.path_keys(path).await?;
+        let raw = encrypt_path(path, keys.first());
+        let entries = self.inner.list_recursive(&raw).await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|e| self.decrypt_entry(e, &raw, &keys, app_id.is_some() && !keys.is_empty()))
+            .collect())
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String> {
+        let (app_id, keys) = self.path_keys(path).await?;
+        let raw = encrypt_path(path, keys.first());
+
+        Ok(self.inner.stat(&raw).await?.map(|e| RemoteEntry {
+            name: path.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string(),
+            ..self.decrypt_entry(e, "", &keys, app_id.is_some() && !keys.is_empty())
+        }))
+    }
+
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String> {
+        match classify_path(&split_path(path)).0 {
+            Some(app_id) => {
+                let key = self.write_key_for(&app_id).await?;
+                let encrypted = encrypt_content(&key, &content)?;
+                self.inner.upload(&encrypt_path(path, Some(&key)), encrypted).await
+            }
+            None => self.inner.upload(path, content).await,
+        }
+    }
+
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String> {
+        let (app_id, keys) = self.path_keys(path).await?;
+        let data = self.inner.download(&encrypt_path(path, keys.first())).await?;
+
+        if app_id.is_none() || keys.is_empty() {
+            // Nothing of this game has been encrypted yet
+            return Ok(data);
+        }
+        decrypt_content(&keys, &data)
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let (_, keys) = self.path_keys(path).await?;
+        self.inner.delete(&encrypt_path(path, keys.first())).await
+    }
+
+    async fn mkdir(&self, path: &str) -> Result<(), String> {
+        let segments = split_path(path);
+        match classify_path(&segments) {
+            // Only creating a directory with an encrypted name needs the game's key
+            (Some(app_id), first_encrypted) if segments.len() > first_encrypted => {
+                let key = self.write_key_for(&app_id).await?;
+                self.inner.mkdir(&encrypt_path(path, Some(&key))).await
+            }
+            _ => self.inner.mkdir(path).await,
+        }
+    }
+
+    /// Encrypts into a temporary file first, then uploads that
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let Some(app_id) = classify_path(&split_path(path)).0 else {
+            return self.inner.upload_from(path, local, progress).await;
+        };
+
+        let key = self.write_key_for(&app_id).await?;
+        let raw = encrypt_path(path, Some(&key));
+        let encrypted = scratch_file()?;
+        let (source, target) = (local.to_path_buf(), encrypted.path().to_path_buf());
+        run_blocking(move || encrypt_file(&key, &source, &target)).await?;
+
+        self.inner.upload_from(&raw, encrypted.path(), progress).await
+    }
+
+    /// Downloads the encrypted file to `<local>.boilerroom-tmp`, where an
+    /// interrupted download is resumed, then decrypts it into `local`
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let (app_id, keys) = self.path_keys(path).await?;
+        let raw = encrypt_path(path, keys.first());
+        if app_id.is_none() || keys.is_empty() {
+            return self.inner.download_to(&raw, local, progress).await;
+        }
+
+        let mut name = local.as_os_str().to_owned();
+        name.push(".boilerroom-tmp");
+        let encrypted = PathBuf::from(name);
+        self.inner.download_to(&raw, &encrypted, progress).await?;
+
+        let (source, target) = (encrypted.clone(), local.to_path_buf());
+        let result = run_blocking(move || decrypt_file(&keys, &source, &target)).await;
+        // Complete or not, the encrypted copy must not be resumed again
+        let _ = tokio::fs::remove_file(&encrypted).await;
+        result
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        match classify_path(&split_path(to)).0 {
+            Some(app_id) => {
+                let key = self.write_key_for(&app_id).await?;
+                self.inner
+                    .copy(&encrypt_path(from, Some(&key)), &encrypt_path(to, Some(&key)))
+                    .await
+            }
+            None => self.inner.copy(from, to).await,
+        }
+    }
+}
+
+// ============================================================================
+// Passphrase Storage
+// ============================================================================
+
+fn passphrase_file() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home.join(".local/share/boilerroom/cloudsync/passphrase"))
+}
+
+/// Load the encryption passphrase (environment variable first, then the passphrase file)
+pub fn load_passphrase() -> Option<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Some(passphrase);
+        }
+    }
+
+    std::fs::read_to_string(passphrase_file().ok()?)
+        .ok()
+        .map(|p| p.trim_end_matches(['\r', '\n']).to_string())
+        .filter(|p| !p.is_empty())
+}
+
+/// Store the encryption passphrase in a file only the current user can read
+pub fn save_passphrase(passphrase: &str) -> Result<(), String> {
+    use std::io::Write;
+
+    let path = passphrase_file()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("Failed to save passphrase: {}", e))?;
+    file.write_all(passphrase.as_bytes())
+        .map_err(|e| format!("Failed to save passphrase: {}", e))
+}
+
+/// Remove the stored encryption passphrase
+pub fn clear_passphrase() -> Result<(), String> {
+    match std::fs::remove_file(passphrase_file()?) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove passphrase: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsync::remote_file_path;
+    use crate::cloudsync_storage::LocalStorage;
+
+    fn test_key() -> DataKey {
+        new_key("correct horse battery staple").unwrap().1
+    }
+
+    #[test]
+    fn test_content_roundtrip() {
+        let key = test_key();
+        let encrypted = encrypt_content(&key, b"save data").unwrap();
+        assert_eq!(encrypted.len() as u64, encrypted_size(9));
+        assert_eq!(plain_size(encrypted.len() as u64), 9);
+        assert_eq!(decrypt_content(std::slice::from_ref(&key), &encrypted).unwrap(), b"save data");
+
+        // Tampering is detected
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_content(&[key], &tampered).is_err());
+
+        // Other keys can't read it
+        assert!(decrypt_content(&[test_key()], &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_chunked_content() {
+        let key = test_key();
+        for len in [0, CHUNK_SIZE - 1, CHUNK_SIZE, 2 * CHUNK_SIZE + 7] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt_content(&key, &data).unwrap();
+            assert_eq!(encrypted.len() as u64, encrypted_size(len as u64), "{}", len);
+            assert_eq!(plain_size(encrypted.len() as u64), len as u64, "{}", len);
+            assert_eq!(decrypt_content(std::slice::from_ref(&key), &encrypted).unwrap(), data);
+
+            // Dropping the final chunk is detected, even at a chunk boundary
+            let truncated = &encrypted[..encrypted.len() - (len % CHUNK_SIZE) - TAG_LEN];
+            assert!(decrypt_content(std::slice::from_ref(&key), truncated).is_err(), "{}", len);
+        }
+
+        // Chunks can't be reordered
+        let data = vec![7u8; 2 * CHUNK_SIZE];
+        let mut encrypted = encrypt_content(&key, &data).unwrap();
+        let chunk = CHUNK_SIZE + TAG_LEN;
+        let (first, rest) = encrypted[HEADER_LEN..].split_at_mut(chunk);
+        first.swap_with_slice(&mut rest[..chunk]);
+        assert!(decrypt_content(&[key], &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_legacy_content_still_decrypts() {
+        let key = test_key();
+        let nonce = [3u8; NONCE_LEN];
+        let mut legacy = LEGACY_MAGIC.to_vec();
+        legacy.extend_from_slice(&key.id);
+        legacy.extend_from_slice(&nonce);
+        legacy.extend(
+            XChaCha20Poly1305::new((&key.content_key).into())
+                .encrypt(XNonce::from_slice(&nonce), &b"old save"[..])
+                .unwrap(),
+        );
+
+        assert_eq!(plain_size(legacy.len() as u64), 8);
+        assert_eq!(decrypt_content(&[key], &legacy).unwrap(), b"old save");
+    }
+
+    #[test]
+    fn test_name_roundtrip() {
+        let key = test_key();
+        let encrypted = encrypt_name(&key, "slot 1.sav");
+        assert_eq!(encrypted, encrypt_name(&key, "slot 1.sav"));
+        assert!(encrypted.bytes().all(|c| BASE32_ALPHABET.contains(&c)));
+        assert_eq!(decrypt_name(&[key], &encrypted).as_deref(), Some("slot 1.sav"));
+        assert_eq!(decrypt_name(&[test_key()], &encrypted), None);
+    }
+
+    #[test]
+    fn test_open_key_checks_passphrase() {
+        let (params, key) = new_key("secret").unwrap();
+        assert_eq!(open_key("secret", &params).unwrap().id, key.id);
+        assert!(open_key("wrong", &params).is_err());
+    }
+
+    #[test]
+    fn test_classify_path() {
+        let cases = [
+            ("boilerroom/730/saves/slot1.sav", Some("730"), 2),
+            ("boilerroom/.snapshots/730/20260314T150926Z/saves/slot1.sav", Some("730"), 4),
+            ("boilerroom/.index/730.json", Some("730"), 4),
+            ("boilerroom/.keys/730.json", None, usize::MAX),
+            ("boilerroom", None, usize::MAX),
+        ];
+        for (path, app_id, first) in cases {
+            assert_eq!(
+                classify_path(&split_path(path)),
+                (app_id.map(|s| s.to_string()), first),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage_roundtrip_and_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let raw = LocalStorage::new(&root).unwrap();
+
+        // A plaintext upload from before encryption was enabled
+        raw.upload(&remote_file_path("730", "saves/old.sav"), b"old".to_vec()).await.unwrap();
+
+        let storage =
+            EncryptedStorage::new(Box::new(LocalStorage::new(&root).unwrap()), "secret".to_string());
+        let slot1 = remote_file_path("730", "saves/slot1.sav");
+        storage.upload(&slot1, b"save data".to_vec()).await.unwrap();
+
+        // Nothing readable is left on the remote, and the old file was encrypted too
+        let raw_files = raw.list_files("730").await.unwrap();
+        assert_eq!(raw_files.len(), 2);
+        assert!(raw_files.iter().all(|f| !f.contains("slot1") && !f.contains(".sav")));
+
+        let mut files = storage.list_files("730").await.unwrap();
+        files.sort();
+        assert_eq!(files, vec!["saves/old.sav", "saves/slot1.sav"]);
+        let entries = storage.list_file_entries("730").await.unwrap();
+        assert!(entries.iter().any(|e| e.name == "saves/slot1.sav" && e.size == 9));
+        assert_eq!(
+            storage.download(&remote_file_path("730", "saves/old.sav")).await.unwrap(),
+            b"old"
+        );
+
+        // Rotation moves everything to a new key
+        let before = raw.list_files("730").await.unwrap();
+        assert_eq!(storage.rotate_key("730").await.unwrap(), 2);
+        let after = raw.list_files("730").await.unwrap();
+        assert!(before.iter().all(|f| !after.contains(f)));
+        assert_eq!(storage.download(&slot1).await.unwrap(), b"save data");
+
+        // Files are streamed through disk, in either direction
+        let dir_local = tempfile::tempdir().unwrap();
+        let source = dir_local.path().join("big.sav");
+        let data: Vec<u8> = (0..CHUNK_SIZE * 2 + 5).map(|i| (i % 13) as u8).collect();
+        std::fs::write(&source, &data).unwrap();
+        let big = remote_file_path("730", "saves/big.sav");
+        storage.upload_from(&big, &source, &|_, _| {}).await.unwrap();
+        let target = dir_local.path().join("big.sav.boilerroom-part");
+        storage.download_to(&big, &target, &|_, _| {}).await.unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), data);
+        assert!(!dir_local.path().join("big.sav.boilerroom-part.boilerroom-tmp").exists());
+        let entry = storage.stat(&big).await.unwrap().unwrap();
+        assert_eq!(entry.size, data.len() as u64);
+
+        // A fresh client with the wrong passphrase is refused
+        let wrong =
+            EncryptedStorage::new(Box::new(LocalStorage::new(&root).unwrap()), "wrong".to_string());
+        assert!(wrong.download(&slot1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_rotation_keeps_previous_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_string_lossy().to_string();
+        let storage =
+            EncryptedStorage::new(Box::new(LocalStorage::new(&root).unwrap()), "secret".to_string());
+        let slot1 = remote_file_path("730", "saves/slot1.sav");
+        storage.upload(&slot1, b"save data".to_vec()).await.unwrap();
+
+        // A rotation that stopped right after recording its new key
+        let old = storage.load_key_file("730").await.unwrap().unwrap();
+        let (params, _) = new_key("secret").unwrap();
+        storage
+            .save_key_file(
+                "730",
+                &GameKeyFile {
+                    current: params,
+                    previous: Some(old.current),
+                },
+            )
+            .await
+            .unwrap();
+
+        // A new rotation must not forget the key slot1 is still encrypted with
+        let fresh =
+            EncryptedStorage::new(Box::new(LocalStorage::new(&root).unwrap()), "secret".to_string());
+        fresh.rotate_key("730").await.unwrap();
+        assert!(fresh.load_key_file("730").await.unwrap().unwrap().previous.is_none());
+
+        let reader =
+            EncryptedStorage::new(Box::new(LocalStorage::new(&root).unwrap()), "secret".to_string());
+        assert_eq!(reader.download(&slot1).await.unwrap(), b"save data");
+    }
+}
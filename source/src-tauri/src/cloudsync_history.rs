@@ -0,0 +1,257 @@
+//! CloudSync History - Persisted record of sync runs
+//!
+//! Every sync of a game appends a run (what moved, how much, what failed) to
+//! the game's history. The history backs `GameCloudStatus.last_sync` and the
+//! global status counts.
+//!
+//! Local layout (~/.local/share/boilerroom/cloudsync/history):
+//! - <account_id>/<app_id>.json   (runs, newest first)
+
+use crate::cloudsync::SyncMode;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Runs kept per game; older ones are dropped
+const HISTORY_KEEP: usize = 100;
+
+/// One sync of a game
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SyncRun {
+    pub started_at: String,
+    pub finished_at: String,
+    pub mode: SyncMode,
+    /// "upload", "download", "both" or "none"
+    pub direction: String,
+    pub files_uploaded: u32,
+    pub files_downloaded: u32,
+    #[serde(default)]
+    pub files_deleted: u32,
+    pub bytes_uploaded: u64,
+    pub bytes_downloaded: u64,
+    pub conflicts: u32,
+    /// Files left out of sync (failed, or uploads held while the game ran)
+    pub files_pending: u32,
+    pub errors: Vec<String>,
+    pub success: bool,
+    pub message: String,
+}
+
+impl SyncRun {
+    /// Start recording a run
+    pub fn start(mode: SyncMode) -> Self {
+        Self {
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: String::new(),
+            mode,
+            direction: "none".to_string(),
+            files_uploaded: 0,
+            files_downloaded: 0,
+            files_deleted: 0,
+            bytes_uploaded: 0,
+            bytes_downloaded: 0,
+            conflicts: 0,
+            files_pending: 0,
+            errors: Vec::new(),
+            success: false,
+            message: String::new(),
+        }
+    }
+
+    /// Stamp the end of the run
+    pub fn finish(&mut self, success: bool, message: &str) {
+        self.finished_at = Utc::now().to_rfc3339();
+        self.success = success;
+        self.message = message.to_string();
+        self.direction = match (self.files_uploaded > 0, self.files_downloaded > 0) {
+            (true, true) => "both",
+            (true, false) => "upload",
+            (false, true) => "download",
+            (false, false) => "none",
+        }
+        .to_string();
+    }
+}
+
+fn history_root() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home.join(".local/share/boilerroom/cloudsync/history"))
+}
+
+fn history_file(root: &Path, account_id: &str, app_id: &str) -> PathBuf {
+    root.join(account_id).join(format!("{}.json", app_id))
+}
+
+fn load_history_in(root: &Path, account_id: &str, app_id: &str) -> Vec<SyncRun> {
+    std::fs::read_to_string(history_file(root, account_id, app_id))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn record_run_in(root: &Path, account_id: &str, app_id: &str, run: SyncRun) -> Result<(), String> {
+    let mut runs = load_history_in(root, account_id, app_id);
+    runs.insert(0, run);
+    runs.truncate(HISTORY_KEEP);
+
+    let path = history_file(root, account_id, app_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create sync history dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&runs)
+        .map_err(|e| format!("Failed to serialize sync history: {}", e))?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write sync history: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save sync history: {}", e))
+}
+
+fn games_in(root: &Path, account_id: &str) -> Vec<String> {
+    std::fs::read_dir(root.join(account_id))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter_map(|name| name.strip_suffix(".json").map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Sync runs of a game, newest first
+pub fn load_history(account_id: &str, app_id: &str) -> Vec<SyncRun> {
+    match history_root() {
+        Ok(root) => load_history_in(&root, account_id, app_id),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Add a finished run to a game's history
+pub fn record_run(account_id: &str, app_id: &str, run: SyncRun) -> Result<(), String> {
+    record_run_in(&history_root()?, account_id, app_id, run)
+}
+
+/// Games of an account that have a history
+pub fn history_games(account_id: &str) -> Vec<String> {
+    match history_root() {
+        Ok(root) => games_in(&root, account_id),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// When a game last finished a sync
+pub fn last_sync(account_id: &str, app_id: &str) -> Option<String> {
+    load_history(account_id, app_id)
+        .into_iter()
+        .next()
+        .map(|run| run.finished_at)
+}
+
+// ============================================================================
+// Status
+// ============================================================================
+
+/// Where a game stands, as far as its history and sync state tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameSyncStanding {
+    Synced,
+    Pending,
+    Conflict,
+}
+
+/// Classify a game from its last run and its unresolved conflicts.
+/// A game without runs but with a sync state was synced before history was kept.
+pub fn game_standing(last_run: Option<&SyncRun>, conflicts: usize) -> GameSyncStanding {
+    if conflicts > 0 {
+        return GameSyncStanding::Conflict;
+    }
+    match last_run {
+        Some(run) if !run.success || run.files_pending > 0 || !run.errors.is_empty() => {
+            GameSyncStanding::Pending
+        }
+        _ => GameSyncStanding::Synced,
+    }
+}
+
+/// App IDs with a sync in progress
+static ACTIVE_SYNCS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Marks a game as syncing until dropped
+pub struct ActiveSync {
+    app_id: String,
+}
+
+impl ActiveSync {
+    pub fn begin(app_id: &str) -> Self {
+        ACTIVE_SYNCS.lock().unwrap().push(app_id.to_string());
+        Self {
+            app_id: app_id.to_string(),
+        }
+    }
+}
+
+impl Drop for ActiveSync {
+    fn drop(&mut self) {
+        let mut active = ACTIVE_SYNCS.lock().unwrap();
+        if let Some(i) = active.iter().position(|id| id == &self.app_id) {
+            active.remove(i);
+        }
+    }
+}
+
+/// Whether any sync is running right now
+pub fn is_syncing() -> bool {
+    !ACTIVE_SYNCS.lock().unwrap().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(uploaded: u32, pending: u32) -> SyncRun {
+        let mut run = SyncRun::start(SyncMode::Full);
+        run.files_uploaded = uploaded;
+        run.files_pending = pending;
+        run.finish(true, "done");
+        run
+    }
+
+    #[test]
+    fn test_record_and_load_history() {
+        let root = tempfile::tempdir().unwrap();
+        record_run_in(root.path(), "42", "730", run(1, 0)).unwrap();
+        record_run_in(root.path(), "42", "730", run(2, 0)).unwrap();
+
+        let runs = load_history_in(root.path(), "42", "730");
+        assert_eq!(runs.len(), 2);
+        // Newest first
+        assert_eq!(runs[0].files_uploaded, 2);
+        assert_eq!(runs[0].direction, "upload");
+        assert!(load_history_in(root.path(), "43", "730").is_empty());
+        assert_eq!(games_in(root.path(), "42"), vec!["730".to_string()]);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let root = tempfile::tempdir().unwrap();
+        for _ in 0..HISTORY_KEEP + 5 {
+            record_run_in(root.path(), "42", "730", run(0, 0)).unwrap();
+        }
+        assert_eq!(load_history_in(root.path(), "42", "730").len(), HISTORY_KEEP);
+    }
+
+    #[test]
+    fn test_game_standing() {
+        assert_eq!(game_standing(Some(&run(1, 0)), 0), GameSyncStanding::Synced);
+        assert_eq!(game_standing(Some(&run(1, 2)), 0), GameSyncStanding::Pending);
+        assert_eq!(game_standing(Some(&run(1, 0)), 1), GameSyncStanding::Conflict);
+        assert_eq!(game_standing(None, 0), GameSyncStanding::Synced);
+
+        let mut failed = run(0, 0);
+        failed.finish(false, "Failed to list files");
+        assert_eq!(game_standing(Some(&failed), 0), GameSyncStanding::Pending);
+    }
+}
@@ -0,0 +1,316 @@
+//! CloudSync Registry - Sync Windows registry keys of Proton games as files
+//!
+//! Some games keep their saves in the registry (PCGamingWiki lists them as
+//! `{{p|hkcu}}\Software\...`). Under Proton the registry is plain text in the
+//! prefix: `user.reg` (HKEY_CURRENT_USER) and `system.reg`
+//! (HKEY_LOCAL_MACHINE), one `[Key\\Path] <timestamp>` section per key.
+//!
+//! Each save key (with its subkeys) is exported to a file that is synced like
+//! any other save. Syncs export the keys right before comparing them; finding
+//! save locations never writes. After a sync, the exports it downloaded are
+//! written back into the `.reg` file, unless Wine is running in the prefix.
+//!
+//! Local layout (~/.local/share/boilerroom/cloudsync/registry):
+//! - <app_id>/HKCU/<key path>.reg
+//! - <app_id>/HKLM/<key path>.reg
+
+use crate::cloudsync_rollback::RollbackSession;
+use std::path::{Path, PathBuf};
+
+/// Registry hive a save key lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hive {
+    CurrentUser,
+    LocalMachine,
+}
+
+impl Hive {
+    /// Registry file of the hive inside a prefix
+    fn file_name(&self) -> &'static str {
+        match self {
+            Hive::CurrentUser => "user.reg",
+            Hive::LocalMachine => "system.reg",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Hive::CurrentUser => "HKCU",
+            Hive::LocalMachine => "HKLM",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "HKCU" => Some(Hive::CurrentUser),
+            "HKLM" => Some(Hive::LocalMachine),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// .reg Files
+// ============================================================================
+
+/// A `[Key] <timestamp>` section: its unescaped key name and its lines
+struct Section<'a> {
+    key: String,
+    text: &'a str,
+}
+
+/// Split a Wine .reg file into its preamble and key sections
+fn split_sections(content: &str) -> (&str, Vec<Section<'_>>) {
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .filter(|&i| content[i..].starts_with('['))
+        .collect();
+
+    let preamble = &content[..starts.first().copied().unwrap_or(content.len())];
+    let sections = starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(content.len());
+            let text = &content[start..end];
+            let header = text.lines().next().unwrap_or_default();
+            let name = &header[1..header.rfind(']').unwrap_or(header.len())];
+            Section {
+                key: name.replace("\\\\", "\\"),
+                text,
+            }
+        })
+        .collect();
+    (preamble, sections)
+}
+
+/// Whether a section belongs to `key` (the key itself or one of its subkeys)
+fn in_key(section_key: &str, key: &str) -> bool {
+    let section_key = section_key.to_lowercase();
+    let key = key.trim_matches('\\').to_lowercase();
+    section_key == key || section_key.starts_with(&format!("{}\\", key))
+}
+
+/// The sections of a key and its subkeys, or None if the key doesn't exist
+pub fn export_key(content: &str, key: &str) -> Option<String> {
+    let (_, sections) = split_sections(content);
+    let exported: String = sections
+        .iter()
+        .filter(|s| in_key(&s.key, key))
+        .map(|s| s.text)
+        .collect();
+    (!exported.is_empty()).then_some(exported)
+}
+
+/// Replace a key and its subkeys with exported sections
+pub fn import_key(content: &str, key: &str, exported: &str) -> String {
+    let (preamble, sections) = split_sections(content);
+    let mut result = preamble.to_string();
+    for section in sections.iter().filter(|s| !in_key(&s.key, key)) {
+        result.push_str(section.text);
+    }
+    if !result.is_empty() && !result.ends_with("\n\n") {
+        result.push('\n');
+    }
+    result.push_str(exported);
+    result
+}
+
+// ============================================================================
+// Exports
+// ============================================================================
+
+/// Directory a game's registry exports are kept in
+pub fn registry_root(app_id: &str) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home
+        .join(".local/share/boilerroom/cloudsync/registry")
+        .join(app_id))
+}
+
+fn export_path(root: &Path, hive: Hive, key: &str) -> PathBuf {
+    let segments: Vec<&str> = key
+        .split('\\')
+        .filter(|s| !s.is_empty() && *s != "." && *s != "..")
+        .collect();
+    let mut path = root.join(hive.label());
+    if let Some((name, parents)) = segments.split_last() {
+        path.extend(parents);
+        // Not set_extension: key names can contain dots
+        path.push(format!("{}.reg", name));
+    }
+    path
+}
+
+/// Whether a key exists in a prefix
+pub fn key_exists(prefix: &Path, hive: Hive, key: &str) -> bool {
+    std::fs::read_to_string(prefix.join(hive.file_name()))
+        .ok()
+        .and_then(|content| export_key(&content, key))
+        .is_some()
+}
+
+/// Export a key from a prefix into the game's registry directory. The export
+/// is only rewritten when the key changed, so its modification time stays
+/// meaningful.
+pub fn export_to_file(app_id: &str, prefix: &Path, hive: Hive, key: &str) -> Result<(), String> {
+    let root = registry_root(app_id)?;
+    let reg_file = prefix.join(hive.file_name());
+    let content = std::fs::read_to_string(&reg_file)
+        .map_err(|e| format!("Failed to read {}: {}", reg_file.display(), e))?;
+
+    if let Some(exported) = export_key(&content, key) {
+        let path = export_path(&root, hive, key);
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(exported.as_str()) {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            std::fs::write(&path, exported)
+                .map_err(|e| format!("Failed to write registry export: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a local save file is a registry export of the game
+pub fn is_export(app_id: &str, path: &Path) -> bool {
+    registry_root(app_id).is_ok_and(|root| path.starts_with(root))
+}
+
+/// Hive and key an export file holds
+fn export_key_of(root: &Path, path: &Path) -> Option<(Hive, String)> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut segments = relative.iter().map(|s| s.to_string_lossy().to_string());
+    let hive = Hive::from_label(&segments.next()?)?;
+    let key = segments
+        .collect::<Vec<_>>()
+        .join("\\")
+        .strip_suffix(".reg")?
+        .to_string();
+    (!key.is_empty()).then_some((hive, key))
+}
+
+/// Write exports a sync downloaded back into the prefix's .reg files, where
+/// they differ from the prefix as it is now. Other exports are left alone.
+/// Must not run while Wine has the prefix open, since it rewrites the .reg
+/// files on exit. The .reg files are backed up in the rollback session first.
+/// Returns how many keys were imported.
+pub fn import_exports(
+    app_id: &str,
+    prefix: &Path,
+    exports: &[PathBuf],
+    rollback: &RollbackSession,
+) -> Result<u32, String> {
+    import_exports_in(&registry_root(app_id)?, prefix, exports, rollback)
+}
+
+fn import_exports_in(
+    root: &Path,
+    prefix: &Path,
+    exports: &[PathBuf],
+    rollback: &RollbackSession,
+) -> Result<u32, String> {
+    let mut imported = 0;
+
+    for export in exports {
+        let Some((hive, key)) = export_key_of(root, export) else {
+            continue;
+        };
+
+        let exported = std::fs::read_to_string(export)
+            .map_err(|e| format!("Failed to read registry export: {}", e))?;
+        let reg_file = prefix.join(hive.file_name());
+        let content = std::fs::read_to_string(&reg_file)
+            .map_err(|e| format!("Failed to read {}: {}", reg_file.display(), e))?;
+        if export_key(&content, &key).as_deref() == Some(exported.as_str()) {
+            continue;
+        }
+
+        rollback.backup(&format!("registry/{}", hive.file_name()), &reg_file)?;
+        let tmp_path = reg_file.with_extension("reg.boilerroom-tmp");
+        std::fs::write(&tmp_path, import_key(&content, &key, &exported))
+            .map_err(|e| format!("Failed to write {}: {}", reg_file.display(), e))?;
+        std::fs::rename(&tmp_path, &reg_file)
+            .map_err(|e| format!("Failed to save {}: {}", reg_file.display(), e))?;
+        eprintln!("[CloudSync] Imported registry key {}\\{}", hive.label(), key);
+        imported += 1;
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_REG: &str = "WINE REGISTRY Version 2\n\
+        ;; All keys relative to \\\\User\\\\S-1-5-21-0-0-0-1000\n\
+        \n\
+        #arch=win64\n\
+        \n\
+        [Software\\\\Team Cherry\\\\Hollow Knight] 1700000000\n\
+        #time=1da0000000000000\n\
+        \"slot\"=dword:00000001\n\
+        \n\
+        [Software\\\\Team Cherry\\\\Hollow Knight\\\\Settings] 1700000000\n\
+        \"vsync\"=dword:00000000\n\
+        \n\
+        [Software\\\\Wine] 1700000000\n\
+        \"Version\"=\"win10\"\n\
+        \n";
+
+    #[test]
+    fn test_export_key() {
+        let exported = export_key(USER_REG, "Software\\Team Cherry\\Hollow Knight").unwrap();
+        assert!(exported.starts_with("[Software\\\\Team Cherry\\\\Hollow Knight] "));
+        assert!(exported.contains("\"vsync\""));
+        assert!(!exported.contains("Wine"));
+        // Case-insensitive, like Windows
+        assert_eq!(
+            export_key(USER_REG, "software\\team cherry\\hollow knight"),
+            Some(exported)
+        );
+        assert_eq!(export_key(USER_REG, "Software\\Team Cherry\\Hollow"), None);
+    }
+
+    #[test]
+    fn test_import_key() {
+        let key = "Software\\Team Cherry\\Hollow Knight";
+        let newer = "[Software\\\\Team Cherry\\\\Hollow Knight] 1800000000\n\"slot\"=dword:00000002\n\n";
+        let imported = import_key(USER_REG, key, newer);
+
+        assert!(imported.starts_with("WINE REGISTRY Version 2\n"));
+        assert_eq!(export_key(&imported, key).as_deref(), Some(newer));
+        assert!(export_key(&imported, "Software\\Wine").is_some());
+    }
+
+    #[test]
+    fn test_import_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("registry");
+        let prefix = dir.path().join("pfx");
+        std::fs::create_dir_all(&prefix).unwrap();
+        std::fs::write(prefix.join("user.reg"), USER_REG).unwrap();
+        let rollback = RollbackSession::with_root("367520", dir.path().join("rollback"));
+
+        let key = "Software\\Team Cherry\\Hollow Knight";
+        let export = export_path(&root, Hive::CurrentUser, key);
+        assert!(export.ends_with("HKCU/Software/Team Cherry/Hollow Knight.reg"));
+        std::fs::create_dir_all(export.parent().unwrap()).unwrap();
+        std::fs::write(&export, export_key(USER_REG, key).unwrap()).unwrap();
+
+        // Unchanged exports leave the prefix alone
+        let exports = [export.clone()];
+        assert_eq!(import_exports_in(&root, &prefix, &exports, &rollback).unwrap(), 0);
+
+        // Only the exports the sync downloaded are imported
+        let newer = "[Software\\\\Team Cherry\\\\Hollow Knight] 1800000000\n\"slot\"=dword:00000002\n\n";
+        std::fs::write(&export, newer).unwrap();
+        assert_eq!(import_exports_in(&root, &prefix, &[], &rollback).unwrap(), 0);
+        assert_eq!(import_exports_in(&root, &prefix, &exports, &rollback).unwrap(), 1);
+        let content = std::fs::read_to_string(prefix.join("user.reg")).unwrap();
+        assert_eq!(export_key(&content, key).as_deref(), Some(newer));
+        assert!(rollback.finish().is_some());
+    }
+}
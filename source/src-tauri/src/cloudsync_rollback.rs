@@ -0,0 +1,375 @@
+//! CloudSync Rollback - Local backups of saves overwritten by downloads
+//!
+//! Before a download replaces a local save, the current file is copied into a
+//! rollback point so it can be put back even if the remote copy turns out to
+//! be bad. One rollback point is created per sync (or restore) that actually
+//! overwrites something.
+//!
+//! Local layout (~/.local/share/boilerroom/cloudsync/rollback):
+//! - <app_id>/<rollback_id>/files/...        (backed-up files)
+//! - <app_id>/<rollback_id>/manifest.json
+
+use crate::cloudsync_snapshots::split_snapshot_id;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Rollback points kept per game; older ones are pruned
+const ROLLBACK_KEEP: usize = 10;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// A single backed-up save file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackFile {
+    /// Path of the file relative to its cloud root
+    pub path: String,
+    /// Where the file lives on disk
+    pub local_path: PathBuf,
+    pub size: u64,
+}
+
+/// Metadata for a rollback point (stored as manifest.json inside it)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackInfo {
+    pub id: String,
+    pub app_id: String,
+    pub created_at: String,
+    pub files: Vec<RollbackFile>,
+}
+
+/// Result of restoring a rollback point
+#[derive(Debug, Default)]
+pub struct RollbackRestore {
+    pub files_restored: u32,
+    pub errors: Vec<String>,
+}
+
+/// Directory holding the rollback points of a game
+fn rollback_root(app_id: &str) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home
+        .join(".local/share/boilerroom/cloudsync/rollback")
+        .join(app_id))
+}
+
+/// Map a save's relative path into the rollback point, dropping anything
+/// that could escape it (`..`, absolute prefixes)
+fn backup_path(dir: &Path, relative_path: &str) -> PathBuf {
+    let normalized = relative_path.replace('\\', "/");
+    let mut path = dir.join("files");
+    for component in Path::new(&normalized).components() {
+        if let Component::Normal(segment) = component {
+            path.push(segment);
+        }
+    }
+    path
+}
+
+// ============================================================================
+// Atomic Writes
+// ============================================================================
+
+/// Atomically replace `target` with the fully written file at `source`:
+/// flush `source` to disk, then rename it over `target`
+pub fn replace_file(source: &Path, target: &Path) -> Result<(), String> {
+    std::fs::File::open(source)
+        .and_then(|f| f.sync_all())
+        .map_err(|e| format!("Failed to flush {}: {}", source.display(), e))?;
+    std::fs::rename(source, target).map_err(|e| format!("Write failed: {}", e))?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = target.parent() {
+        if let Ok(dir) = std::fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy a file to `target` through a temp file next to it, so `target` is
+/// never left half-written
+pub fn copy_atomic(source: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let mut tmp_name = target.as_os_str().to_owned();
+    tmp_name.push(".boilerroom-tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    if let Err(e) = std::fs::copy(source, &tmp_path) {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(format!("Failed to copy {}: {}", source.display(), e));
+    }
+    replace_file(&tmp_path, target)
+}
+
+// ============================================================================
+// Rollback Sessions
+// ============================================================================
+
+/// Collects the files overwritten during one sync into a single rollback point.
+/// Nothing is written to disk until the first backup.
+pub struct RollbackSession {
+    app_id: String,
+    root: PathBuf,
+    created: Mutex<Option<RollbackInfo>>,
+}
+
+impl RollbackSession {
+    pub fn new(app_id: &str) -> Result<Self, String> {
+        Ok(Self::with_root(app_id, rollback_root(app_id)?))
+    }
+
+    pub(crate) fn with_root(app_id: &str, root: PathBuf) -> Self {
+        Self {
+            app_id: app_id.to_string(),
+            root,
+            created: Mutex::new(None),
+        }
+    }
+
+    /// Back up the current content of a local save before it is overwritten.
+    /// Missing files have nothing to back up and are skipped.
+    pub fn backup(&self, relative_path: &str, local_path: &Path) -> Result<(), String> {
+        let size = match std::fs::metadata(local_path) {
+            Ok(m) if m.is_file() => m.len(),
+            _ => return Ok(()),
+        };
+
+        let mut created = self.created.lock().unwrap();
+        let info = match created.as_mut() {
+            Some(info) => info,
+            None => created.insert(RollbackInfo {
+                id: self.next_id(),
+                app_id: self.app_id.clone(),
+                created_at: Utc::now().to_rfc3339(),
+                files: Vec::new(),
+            }),
+        };
+
+        // A file replaced twice in one session keeps its original content
+        if info.files.iter().any(|f| f.path == relative_path) {
+            return Ok(());
+        }
+
+        let dir = self.root.join(&info.id);
+        copy_atomic(local_path, &backup_path(&dir, relative_path))?;
+        info.files.push(RollbackFile {
+            path: relative_path.to_string(),
+            local_path: local_path.to_path_buf(),
+            size,
+        });
+
+        // Keep the manifest current, so a crash mid-sync still leaves a usable rollback point
+        let content = serde_json::to_string_pretty(&*info)
+            .map_err(|e| format!("Failed to serialize rollback manifest: {}", e))?;
+        std::fs::write(dir.join(MANIFEST_FILE), content)
+            .map_err(|e| format!("Failed to write rollback manifest: {}", e))
+    }
+
+    /// Finish the session, pruning old rollback points if a new one was created.
+    /// Returns the ID of the new rollback point.
+    pub fn finish(self) -> Option<String> {
+        let id = self.created.into_inner().unwrap().map(|info| info.id)?;
+        if let Err(e) = prune_rollbacks_in(&self.root, ROLLBACK_KEEP) {
+            eprintln!("[CloudSync] Rollback pruning failed for {}: {}", self.app_id, e);
+        }
+        Some(id)
+    }
+
+    /// Timestamp ID, suffixed if a rollback point with that ID already exists
+    fn next_id(&self) -> String {
+        let base = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.root.join(&id).exists() {
+            id = format!("{}-{}", base, n);
+            n += 1;
+        }
+        id
+    }
+}
+
+// ============================================================================
+// Listing & Restoring
+// ============================================================================
+
+fn list_rollbacks_in(root: &Path) -> Vec<RollbackInfo> {
+    let mut rollbacks: Vec<RollbackInfo> = std::fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| std::fs::read_to_string(e.path().join(MANIFEST_FILE)).ok())
+                .filter_map(|content| serde_json::from_str(&content).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // IDs are timestamps (suffixed within a second), so they sort chronologically
+    rollbacks.sort_by_key(|r| std::cmp::Reverse(split_snapshot_id(&r.id)));
+    rollbacks
+}
+
+fn prune_rollbacks_in(root: &Path, keep: usize) -> Result<(), String> {
+    let mut ids: Vec<String> = std::fs::read_dir(root)
+        .map_err(|e| format!("Failed to read {}: {}", root.display(), e))?
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|id| split_snapshot_id(id).is_some())
+        .collect();
+    ids.sort_by_key(|id| std::cmp::Reverse(split_snapshot_id(id)));
+
+    for id in ids.iter().skip(keep) {
+        std::fs::remove_dir_all(root.join(id))
+            .map_err(|e| format!("Failed to remove rollback {}: {}", id, e))?;
+    }
+    Ok(())
+}
+
+fn restore_rollback_in(root: &Path, rollback_id: &str) -> Result<RollbackRestore, String> {
+    // IDs come from the frontend; anything but a plain ID could point outside the game's rollbacks
+    if split_snapshot_id(rollback_id).is_none() {
+        return Err(format!("Invalid rollback ID: {}", rollback_id));
+    }
+    let dir = root.join(rollback_id);
+    let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|_| format!("Rollback {} not found", rollback_id))?;
+    let info: RollbackInfo = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid rollback manifest: {}", e))?;
+
+    let mut result = RollbackRestore::default();
+    for file in &info.files {
+        match copy_atomic(&backup_path(&dir, &file.path), &file.local_path) {
+            Ok(_) => {
+                result.files_restored += 1;
+                eprintln!("[CloudSync] Rolled back: {}", file.path);
+            }
+            Err(e) => result.errors.push(format!("{}: {}", file.path, e)),
+        }
+    }
+
+    Ok(result)
+}
+
+/// List the local rollback points of a game, newest first
+pub fn list_rollbacks(app_id: &str) -> Result<Vec<RollbackInfo>, String> {
+    Ok(list_rollbacks_in(&rollback_root(app_id)?))
+}
+
+/// Put the files of a rollback point back in place
+pub fn restore_rollback(app_id: &str, rollback_id: &str) -> Result<RollbackRestore, String> {
+    restore_rollback_in(&rollback_root(app_id)?, rollback_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_path() {
+        let dir = Path::new("/rollback/730/id");
+        assert_eq!(
+            backup_path(dir, "saves\\slot1.sav"),
+            PathBuf::from("/rollback/730/id/files/saves/slot1.sav")
+        );
+        assert_eq!(
+            backup_path(dir, "../../etc/passwd"),
+            PathBuf::from("/rollback/730/id/files/etc/passwd")
+        );
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let root = tempfile::tempdir().unwrap();
+        let saves = tempfile::tempdir().unwrap();
+        let slot1 = saves.path().join("slot1.sav");
+        std::fs::write(&slot1, b"good save").unwrap();
+
+        let session = RollbackSession::with_root("730", root.path().to_path_buf());
+        session.backup("slot1.sav", &slot1).unwrap();
+        // Missing files are skipped
+        session.backup("slot2.sav", &saves.path().join("slot2.sav")).unwrap();
+        let id = session.finish().unwrap();
+
+        std::fs::write(&slot1, b"corrupt").unwrap();
+
+        let rollbacks = list_rollbacks_in(root.path());
+        assert_eq!(rollbacks.len(), 1);
+        assert_eq!(rollbacks[0].id, id);
+        assert_eq!(rollbacks[0].files.len(), 1);
+        assert_eq!(rollbacks[0].files[0].size, 9);
+
+        let result = restore_rollback_in(root.path(), &id).unwrap();
+        assert_eq!(result.files_restored, 1);
+        assert!(result.errors.is_empty());
+        assert_eq!(std::fs::read(&slot1).unwrap(), b"good save");
+    }
+
+    #[test]
+    fn test_empty_session_creates_nothing() {
+        let root = tempfile::tempdir().unwrap();
+        let session = RollbackSession::with_root("730", root.path().to_path_buf());
+        assert_eq!(session.finish(), None);
+        assert!(list_rollbacks_in(root.path()).is_empty());
+    }
+
+    #[test]
+    fn test_prune_rollbacks() {
+        let root = tempfile::tempdir().unwrap();
+        for id in ["20260101T000000Z", "20260102T000000Z", "20260103T000000Z"] {
+            std::fs::create_dir_all(root.path().join(id)).unwrap();
+        }
+
+        prune_rollbacks_in(root.path(), 2).unwrap();
+        assert!(!root.path().join("20260101T000000Z").exists());
+        assert!(root.path().join("20260102T000000Z").exists());
+        assert!(root.path().join("20260103T000000Z").exists());
+    }
+
+    #[test]
+    fn test_rollbacks_within_a_second() {
+        let root = tempfile::tempdir().unwrap();
+        let ids: Vec<String> = std::iter::once("20260101T000000Z".to_string())
+            .chain((2..=10).map(|n| format!("20260101T000000Z-{}", n)))
+            .collect();
+        for id in &ids {
+            let info = RollbackInfo {
+                id: id.clone(),
+                app_id: "730".to_string(),
+                created_at: String::new(),
+                files: Vec::new(),
+            };
+            std::fs::create_dir_all(root.path().join(id)).unwrap();
+            std::fs::write(
+                root.path().join(id).join(MANIFEST_FILE),
+                serde_json::to_string(&info).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let listed: Vec<String> = list_rollbacks_in(root.path()).into_iter().map(|r| r.id).collect();
+        let newest_first: Vec<String> = ids.iter().rev().cloned().collect();
+        assert_eq!(listed, newest_first);
+
+        // -10 is the newest and survives, the unsuffixed one goes first
+        prune_rollbacks_in(root.path(), 9).unwrap();
+        assert!(root.path().join("20260101T000000Z-10").exists());
+        assert!(!root.path().join("20260101T000000Z").exists());
+    }
+
+    #[test]
+    fn test_restore_rejects_paths() {
+        let root = tempfile::tempdir().unwrap();
+        for id in ["../440/20260101T000000Z", "/etc", "20260101T000000Z/..", ""] {
+            let err = restore_rollback_in(&root.path().join("730"), id).unwrap_err();
+            assert!(err.starts_with("Invalid rollback ID"), "{}", id);
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! CloudSync Roots - Map Steam Cloud root IDs to directories on this machine
+//!
+//! remotecache.vdf stores every file as a root ID plus a path relative to it.
+//! Windows roots (Documents, AppData, Saved Games, ...) of games running under
+//! Proton live inside the game's Wine prefix:
+//! `<library>/steamapps/compatdata/<appid>/pfx/drive_c/users/steamuser/...`
+//!
+//! Whether a game uses Proton is decided from its compat tool mapping in
+//! Steam's config.vdf, falling back to the presence of a prefix in the
+//! library the game is installed in.
+
+use crate::vdf::{self, AppManifest, LibraryFolders};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Steam Cloud root IDs (ERemoteStorageFileRoot)
+pub mod root {
+    pub const DEFAULT: i32 = 0;
+    pub const GAME_INSTALL: i32 = 1;
+    pub const WIN_MY_DOCUMENTS: i32 = 2;
+    pub const WIN_APP_DATA_LOCAL: i32 = 3;
+    pub const WIN_APP_DATA_ROAMING: i32 = 4;
+    pub const STEAM_USER_BASE_STORAGE: i32 = 5;
+    pub const MAC_HOME: i32 = 6;
+    pub const MAC_APP_SUPPORT: i32 = 7;
+    pub const MAC_DOCUMENTS: i32 = 8;
+    pub const WIN_SAVED_GAMES: i32 = 9;
+    pub const WIN_PROGRAM_DATA: i32 = 10;
+    pub const STEAM_CLOUD_DOCUMENTS: i32 = 11;
+    pub const WIN_APP_DATA_LOCAL_LOW: i32 = 12;
+    pub const MAC_CACHES: i32 = 13;
+    pub const LINUX_HOME: i32 = 14;
+    pub const LINUX_XDG_DATA_HOME: i32 = 15;
+    pub const LINUX_XDG_CONFIG_HOME: i32 = 16;
+}
+
+/// Where a game is installed and which Wine prefix (if any) it runs in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameLocation {
+    /// Game installation directory (steamapps/common/<installdir>)
+    pub install_dir: Option<PathBuf>,
+    /// Proton prefix (steamapps/compatdata/<appid>/pfx) when the game runs under Proton
+    pub proton_prefix: Option<PathBuf>,
+}
+
+/// Steam installation directory
+pub fn steam_root() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library/Application Support/Steam"))
+    } else {
+        Some(home.join(".local/share/Steam"))
+    }
+}
+
+// ============================================================================
+// Game Location
+// ============================================================================
+
+/// Name of the compat tool Steam is set to run a game with, from config.vdf
+pub fn compat_tool_for(config_vdf: &str, app_id: &str) -> Option<String> {
+    let config = vdf::parse(config_vdf).ok()?;
+    let store = config.value.get_obj()?;
+    let path = ["Software", "Valve", "Steam", "CompatToolMapping", app_id, "name"];
+    vdf::lookup_str(store, &path)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+}
+
+/// Whether a compat tool is a Wine/Proton build (as opposed to the Steam
+/// Linux Runtime containers native games can be forced into)
+fn is_proton_tool(name: &str) -> bool {
+    !name.starts_with("steamlinuxruntime")
+}
+
+/// All steamapps directories: the main one plus those in libraryfolders.vdf
+fn steamapps_dirs(steam_root: &Path) -> Vec<PathBuf> {
+    let main = steam_root.join("steamapps");
+    let mut dirs = vec![main.clone()];
+
+    let library = std::fs::read_to_string(main.join("libraryfolders.vdf"))
+        .ok()
+        .and_then(|content| LibraryFolders::parse(&content).ok());
+    for path in library.map(|library| library.paths()).unwrap_or_default() {
+        let steamapps = PathBuf::from(path).join("steamapps");
+        if !dirs.contains(&steamapps) {
+            dirs.push(steamapps);
+        }
+    }
+
+    dirs
+}
+
+/// Find where a game is installed and whether it runs under Proton
+pub fn locate_game(app_id: &str) -> GameLocation {
+    match steam_root() {
+        Some(steam_root) => locate_game_in(&steam_root, app_id),
+        None => GameLocation::default(),
+    }
+}
+
+/// App IDs of every game installed in any Steam library
+pub fn installed_app_ids() -> Vec<String> {
+    match steam_root() {
+        Some(steam_root) => installed_app_ids_in(&steam_root),
+        None => Vec::new(),
+    }
+}
+
+fn installed_app_ids_in(steam_root: &Path) -> Vec<String> {
+    let mut app_ids: Vec<String> = steamapps_dirs(steam_root)
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("appmanifest_") && name.ends_with(".acf")
+        })
+        .filter_map(|e| {
+            let content = std::fs::read_to_string(e.path()).ok()?;
+            AppManifest::parse(&content).ok().map(|manifest| manifest.appid)
+        })
+        .collect();
+
+    app_ids.sort();
+    app_ids.dedup();
+    app_ids
+}
+
+fn locate_game_in(steam_root: &Path, app_id: &str) -> GameLocation {
+    let libraries = steamapps_dirs(steam_root);
+    let manifest_name = format!("appmanifest_{}.acf", app_id);
+
+    // The library holding the app manifest is the one the game is installed in
+    let library = libraries.iter().find(|dir| dir.join(&manifest_name).exists());
+    let install_dir = library.and_then(|dir| {
+        let content = std::fs::read_to_string(dir.join(&manifest_name)).ok()?;
+        let manifest = AppManifest::parse(&content).ok()?;
+        (!manifest.installdir.is_empty()).then(|| dir.join("common").join(manifest.installdir))
+    });
+
+    let compat_tool = std::fs::read_to_string(steam_root.join("config/config.vdf"))
+        .ok()
+        .and_then(|content| compat_tool_for(&content, app_id));
+
+    // Prefixes normally live next to the game, but Steam sometimes keeps them
+    // in the main library
+    let prefix_in = |dir: &PathBuf| dir.join("compatdata").join(app_id).join("pfx");
+    let existing_prefix = library
+        .into_iter()
+        .chain(libraries.iter())
+        .map(prefix_in)
+        .find(|p| p.is_dir());
+
+    let proton_prefix = match compat_tool {
+        Some(tool) if is_proton_tool(&tool) => {
+            existing_prefix.or_else(|| library.or(libraries.first()).map(prefix_in))
+        }
+        Some(_) => None,
+        None => existing_prefix,
+    };
+
+    GameLocation {
+        install_dir,
+        proton_prefix,
+    }
+}
+
+// ============================================================================
+// Root Resolution
+// ============================================================================
+
+/// First of several alternative directories that exists (Wine renamed some
+/// profile folders over time), or the first one if none exist yet
+fn first_existing(base: &Path, candidates: &[&str]) -> PathBuf {
+    candidates
+        .iter()
+        .map(|c| base.join(c))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| base.join(candidates[0]))
+}
+
+/// Resolve a Windows root inside a Proton prefix
+pub(crate) fn resolve_proton_root(root: i32, prefix: &Path) -> Option<PathBuf> {
+    let drive_c = prefix.join("drive_c");
+    let profile = drive_c.join("users/steamuser");
+
+    match root {
+        root::WIN_MY_DOCUMENTS => Some(first_existing(&profile, &["Documents", "My Documents"])),
+        root::WIN_APP_DATA_LOCAL => Some(first_existing(
+            &profile,
+            &["AppData/Local", "Local Settings/Application Data"],
+        )),
+        root::WIN_APP_DATA_ROAMING => {
+            Some(first_existing(&profile, &["AppData/Roaming", "Application Data"]))
+        }
+        root::WIN_APP_DATA_LOCAL_LOW => Some(profile.join("AppData/LocalLow")),
+        root::WIN_SAVED_GAMES => Some(profile.join("Saved Games")),
+        root::WIN_PROGRAM_DATA => Some(drive_c.join("ProgramData")),
+        _ => None,
+    }
+}
+
+/// Resolve the base directory of a Steam Cloud root
+pub fn resolve_root(root: i32, app_id: &str, user_id: &str, location: &GameLocation) -> Option<PathBuf> {
+    if let Some(prefix) = &location.proton_prefix {
+        if let Some(path) = resolve_proton_root(root, prefix) {
+            return Some(path);
+        }
+    }
+
+    let home = dirs::home_dir()?;
+    let is_mac = cfg!(target_os = "macos");
+
+    match root {
+        root::DEFAULT => Some(steam_root()?.join("userdata").join(user_id).join(app_id).join("remote")),
+        root::GAME_INSTALL => location.install_dir.clone(),
+        root::STEAM_USER_BASE_STORAGE => Some(steam_root()?.join("userdata").join(user_id)),
+        // Windows roots for native games
+        root::WIN_MY_DOCUMENTS => dirs::document_dir(),
+        root::WIN_APP_DATA_LOCAL | root::WIN_APP_DATA_LOCAL_LOW if is_mac => {
+            Some(home.join("Library/Application Support"))
+        }
+        root::WIN_APP_DATA_LOCAL | root::WIN_APP_DATA_LOCAL_LOW => dirs::data_local_dir(),
+        root::WIN_APP_DATA_ROAMING if is_mac => Some(home.join("Library/Application Support")),
+        root::WIN_APP_DATA_ROAMING => dirs::config_dir(),
+        // macOS roots
+        root::MAC_HOME if is_mac => Some(home),
+        root::MAC_APP_SUPPORT if is_mac => Some(home.join("Library/Application Support")),
+        root::MAC_DOCUMENTS if is_mac => Some(home.join("Documents")),
+        root::MAC_CACHES if is_mac => Some(home.join("Library/Caches")),
+        // Linux roots
+        root::LINUX_HOME if !is_mac => Some(home),
+        root::LINUX_XDG_DATA_HOME if !is_mac => dirs::data_dir(),
+        root::LINUX_XDG_CONFIG_HOME if !is_mac => dirs::config_dir(),
+        // Only exist on Windows (or in a Proton prefix)
+        root::WIN_SAVED_GAMES | root::WIN_PROGRAM_DATA | root::STEAM_CLOUD_DOCUMENTS => None,
+        // Other platforms' roots, and IDs Steam may add later
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG_VDF: &str = r#"
+"InstallConfigStore"
+{
+	"Software"
+	{
+		"Valve"
+		{
+			"Steam"
+			{
+				"CompatToolMapping"
+				{
+					"0"
+					{
+						"name"		"proton_experimental"
+						"config"		""
+						"priority"		"75"
+					}
+					"1245620"
+					{
+						"name"		"proton_9"
+						"config"		""
+						"priority"		"250"
+					}
+					"570"
+					{
+						"name"		"steamlinuxruntime_sniper"
+						"config"		""
+						"priority"		"250"
+					}
+				}
+			}
+		}
+	}
+}
+"#;
+
+    #[test]
+    fn test_compat_tool_for() {
+        assert_eq!(compat_tool_for(CONFIG_VDF, "1245620").as_deref(), Some("proton_9"));
+        assert_eq!(
+            compat_tool_for(CONFIG_VDF, "570").as_deref(),
+            Some("steamlinuxruntime_sniper")
+        );
+        assert_eq!(compat_tool_for(CONFIG_VDF, "730"), None);
+        assert!(is_proton_tool("proton_9"));
+        assert!(is_proton_tool("GE-Proton9-20"));
+        assert!(!is_proton_tool("steamlinuxruntime_sniper"));
+    }
+
+    #[test]
+    fn test_locate_game() {
+        let steam = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+        let write = |path: PathBuf, content: &str| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+
+        write(
+            steam.path().join("steamapps/libraryfolders.vdf"),
+            &format!(
+                "\"libraryfolders\"\n{{\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                library.path().display()
+            ),
+        );
+        write(steam.path().join("config/config.vdf"), CONFIG_VDF);
+        write(
+            library.path().join("steamapps/appmanifest_1245620.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"1245620\"\n\t\"installdir\"\t\t\"ELDEN RING\"\n}\n",
+        );
+        write(
+            library.path().join("steamapps/appmanifest_570.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"570\"\n\t\"installdir\"\t\t\"dota 2 beta\"\n}\n",
+        );
+
+        // Proton game: prefix in its own library, even before the first launch
+        let location = locate_game_in(steam.path(), "1245620");
+        assert_eq!(
+            location.install_dir,
+            Some(library.path().join("steamapps/common/ELDEN RING"))
+        );
+        assert_eq!(
+            location.proton_prefix,
+            Some(library.path().join("steamapps/compatdata/1245620/pfx"))
+        );
+
+        assert_eq!(
+            installed_app_ids_in(steam.path()),
+            vec!["1245620".to_string(), "570".to_string()]
+        );
+
+        // Native game forced into the Linux runtime
+        let location = locate_game_in(steam.path(), "570");
+        assert_eq!(location.proton_prefix, None);
+
+        // No mapping, but an existing prefix in the main library
+        std::fs::create_dir_all(steam.path().join("steamapps/compatdata/999/pfx")).unwrap();
+        let location = locate_game_in(steam.path(), "999");
+        assert_eq!(location.install_dir, None);
+        assert_eq!(
+            location.proton_prefix,
+            Some(steam.path().join("steamapps/compatdata/999/pfx"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_proton_roots() {
+        let prefix = tempfile::tempdir().unwrap();
+        let location = GameLocation {
+            install_dir: Some(PathBuf::from("/games/common/Foo")),
+            proton_prefix: Some(prefix.path().to_path_buf()),
+        };
+        let profile = prefix.path().join("drive_c/users/steamuser");
+        let resolve = |root| resolve_root(root, "1245620", "12345", &location);
+
+        assert_eq!(resolve(root::WIN_MY_DOCUMENTS), Some(profile.join("Documents")));
+        assert_eq!(resolve(root::WIN_APP_DATA_LOCAL), Some(profile.join("AppData/Local")));
+        assert_eq!(resolve(root::WIN_APP_DATA_ROAMING), Some(profile.join("AppData/Roaming")));
+        assert_eq!(resolve(root::WIN_APP_DATA_LOCAL_LOW), Some(profile.join("AppData/LocalLow")));
+        assert_eq!(resolve(root::WIN_SAVED_GAMES), Some(profile.join("Saved Games")));
+        assert_eq!(
+            resolve(root::WIN_PROGRAM_DATA),
+            Some(prefix.path().join("drive_c/ProgramData"))
+        );
+        assert_eq!(resolve(root::GAME_INSTALL), Some(PathBuf::from("/games/common/Foo")));
+        assert_eq!(resolve(99), None);
+
+        // Prefixes created by old Wine versions use the XP-era folder names
+        std::fs::create_dir_all(profile.join("My Documents")).unwrap();
+        assert_eq!(resolve(root::WIN_MY_DOCUMENTS), Some(profile.join("My Documents")));
+    }
+
+    #[test]
+    fn test_resolve_native_roots() {
+        let location = GameLocation::default();
+        let resolve = |root| resolve_root(root, "730", "12345", &location);
+
+        assert!(resolve(root::DEFAULT).unwrap().ends_with("userdata/12345/730/remote"));
+        assert_eq!(resolve(root::GAME_INSTALL), None);
+        assert_eq!(resolve(root::WIN_SAVED_GAMES), None);
+        if cfg!(target_os = "linux") {
+            assert_eq!(resolve(root::LINUX_HOME), dirs::home_dir());
+            assert_eq!(resolve(root::LINUX_XDG_CONFIG_HOME), dirs::config_dir());
+            assert_eq!(resolve(root::MAC_APP_SUPPORT), None);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! CloudSync Scan - Discover save files on disk that remotecache.vdf doesn't list
+//!
+//! remotecache.vdf only lists files Steam has already recorded. When the scan
+//! is enabled for a game, the directories its saves live in are walked and
+//! every file found is synced as well:
+//! - Root 0: the whole `userdata/<uid>/<appid>/remote` folder
+//! - Other roots: the deepest directory holding all of the game's known files
+//!   on that root (never the root itself, which would be e.g. all of ~/Documents)
+//!
+//! Per-game include/exclude globs filter the scanned files; the VDF listing is
+//! always kept as is. Files are keyed by root and path (`cloudsync::file_key`),
+//! so the same path found on two roots is synced as two files, and a file
+//! only the remote has goes back to the root it was uploaded from.
+
+use crate::cloudsync::{file_key, split_file_key, CloudFile, CloudSyncConfig, GameFileRules};
+use crate::cloudsync_roots::{resolve_root, GameLocation};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Compiled include/exclude rules. Each pattern remembers whether it
+/// contains a `/` (and so matches the whole path, not just the file name).
+pub struct FileFilter {
+    include: Vec<(Regex, bool)>,
+    exclude: Vec<(Regex, bool)>,
+}
+
+/// Convert a glob to an anchored regex.
+///
+/// `*` and `?` stay within one path segment, `**` spans directories and a
+/// leading or inner `**/` also matches no directory at all.
+pub fn glob_to_regex(pattern: &str) -> Result<Regex, String> {
+    let mut regex = String::from("^");
+    let chars: Vec<char> = pattern.trim_start_matches('/').chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+impl FileFilter {
+    pub fn new(rules: &GameFileRules) -> Result<Self, String> {
+        let compile = |patterns: &[String]| -> Result<Vec<(Regex, bool)>, String> {
+            patterns
+                .iter()
+                .map(|p| p.trim())
+                .filter(|p| !p.is_empty())
+                .map(|p| Ok((glob_to_regex(p)?, p.contains('/'))))
+                .collect()
+        };
+
+        Ok(Self {
+            include: compile(&rules.include)?,
+            exclude: compile(&rules.exclude)?,
+        })
+    }
+
+    /// Whether a file (path relative to its cloud root) should be synced.
+    /// Patterns without a `/` are matched against the file name only.
+    pub fn matches(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let hit = |(re, whole_path): &(Regex, bool)| {
+            if *whole_path {
+                re.is_match(path)
+            } else {
+                re.is_match(name)
+            }
+        };
+
+        (self.include.is_empty() || self.include.iter().any(hit)) && !self.exclude.iter().any(hit)
+    }
+}
+
+/// Whether the scan is enabled for a game
+pub fn scan_enabled(config: &CloudSyncConfig, app_id: &str) -> bool {
+    config
+        .game_rules
+        .get(app_id)
+        .and_then(|r| r.scan_local_tree)
+        .unwrap_or(config.scan_local_tree)
+}
+
+/// Files CloudSync itself leaves next to saves (partial downloads, temp
+/// files, kept conflict copies), which must never be synced
+pub fn is_sync_artifact(name: &str) -> bool {
+    if name.ends_with(".boilerroom-part") || name.ends_with(".boilerroom-tmp") {
+        return true;
+    }
+    if !name.contains(".conflict-") {
+        return false;
+    }
+
+    Regex::new(r"\.conflict-\d{8}T\d{6}Z(\.|$)")
+        .map(|re| re.is_match(name))
+        .unwrap_or(false)
+}
+
+/// Deepest directory (relative to the root) containing all of the given files,
+/// or None if they only share the root itself
+fn common_directory<'a>(paths: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut common: Option<Vec<&str>> = None;
+
+    for path in paths {
+        let mut segments: Vec<&str> = path.split(['/', '\\']).filter(|s| !s.is_empty()).collect();
+        segments.pop(); // file name
+        common = Some(match common {
+            None => segments,
+            Some(prev) => prev
+                .iter()
+                .zip(&segments)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| *a)
+                .collect(),
+        });
+    }
+
+    common.filter(|c| !c.is_empty()).map(|c| c.join("/"))
+}
+
+/// Walk `dir` (inside the root `base`) and add every matching file not
+/// already in `files`
+fn scan_directory(
+    base: &Path,
+    dir: &Path,
+    root: i32,
+    filter: &FileFilter,
+    files: &mut HashMap<String, CloudFile>,
+) -> u32 {
+    let mut added = 0;
+
+    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() || is_sync_artifact(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let relative = match entry.path().strip_prefix(base) {
+            Ok(r) => r.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        let key = file_key(root, &relative);
+        if files.contains_key(&key) || !filter.matches(&relative) {
+            continue;
+        }
+
+        let metadata = entry.metadata().ok();
+        files.insert(
+            key,
+            CloudFile {
+                path: relative,
+                root,
+                size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+                localtime: metadata
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0),
+                remotetime: 0,
+                sha: String::new(),
+                syncstate: 0,
+                resolved_path: Some(entry.path().to_path_buf()),
+            },
+        );
+        added += 1;
+    }
+
+    added
+}
+
+/// Add the files found on disk under a game's cloud roots to its VDF listing
+pub fn union_local_tree(
+    files: &mut HashMap<String, CloudFile>,
+    app_id: &str,
+    user_id: &str,
+    location: &GameLocation,
+    rules: &GameFileRules,
+) -> Result<(), String> {
+    let filter = FileFilter::new(rules)?;
+
+    // Which directory to walk on each root
+    let mut by_root: BTreeMap<i32, Vec<&str>> = BTreeMap::new();
+    by_root.insert(0, Vec::new());
+    for file in files.values().filter(|f| f.root >= 0) {
+        by_root.entry(file.root).or_default().push(&file.path);
+    }
+    let mut targets: Vec<(i32, PathBuf, PathBuf)> = Vec::new();
+    for (root, paths) in by_root {
+        let base = match resolve_root(root, app_id, user_id, location) {
+            Some(b) => b,
+            None => continue,
+        };
+        let dir = if root == 0 {
+            base.clone()
+        } else {
+            match common_directory(paths.into_iter()) {
+                Some(d) => base.join(d),
+                None => continue,
+            }
+        };
+        targets.push((root, base, dir));
+    }
+
+    for (root, base, dir) in targets {
+        if !dir.is_dir() {
+            continue;
+        }
+        let added = scan_directory(&base, &dir, root, &filter, files);
+        if added > 0 {
+            eprintln!(
+                "[CloudSync] Found {} files for {} not in remotecache.vdf under {}",
+                added,
+                app_id,
+                dir.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Add remote files no local listing knows about (uploaded by another device's
+/// scan) so they get downloaded, on the root their key names
+pub fn union_remote_files<'a>(
+    files: &mut HashMap<String, CloudFile>,
+    remote_keys: impl Iterator<Item = &'a str>,
+    rules: &GameFileRules,
+) -> Result<(), String> {
+    let filter = FileFilter::new(rules)?;
+
+    for key in remote_keys {
+        let (root, path) = split_file_key(key);
+        let escapes = path.split('/').any(|s| s == ".." || s == ".");
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if files.contains_key(key) || escapes || is_sync_artifact(name) || !filter.matches(path) {
+            continue;
+        }
+
+        files.insert(
+            key.to_string(),
+            CloudFile {
+                path: path.to_string(),
+                root,
+                size: 0,
+                localtime: 0,
+                remotetime: 0,
+                sha: String::new(),
+                syncstate: 0,
+                resolved_path: None,
+            },
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(include: &[&str], exclude: &[&str]) -> GameFileRules {
+        GameFileRules {
+            scan_local_tree: Some(true),
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let re = glob_to_regex("saves/*.sav").unwrap();
+        assert!(re.is_match("saves/slot1.sav"));
+        assert!(!re.is_match("saves/old/slot1.sav"));
+
+        let re = glob_to_regex("**/*.sav").unwrap();
+        assert!(re.is_match("slot1.sav"));
+        assert!(re.is_match("saves/old/slot1.sav"));
+
+        let re = glob_to_regex("cache/**").unwrap();
+        assert!(re.is_match("cache/shaders/a.bin"));
+        assert!(!re.is_match("saves/cache.bin"));
+
+        let re = glob_to_regex("slot?.sav").unwrap();
+        assert!(re.is_match("slot1.sav"));
+        assert!(!re.is_match("slot10.sav"));
+    }
+
+    #[test]
+    fn test_file_filter() {
+        let filter = FileFilter::new(&rules(&[], &["*.log", "cache/**"])).unwrap();
+        assert!(filter.matches("saves/slot1.sav"));
+        assert!(!filter.matches("logs/output.log"));
+        assert!(!filter.matches("cache/shaders/a.bin"));
+
+        let filter = FileFilter::new(&rules(&["saves/**"], &["*.bak"])).unwrap();
+        assert!(filter.matches("saves/slot1.sav"));
+        assert!(!filter.matches("saves/slot1.bak"));
+        assert!(!filter.matches("settings.ini"));
+    }
+
+    #[test]
+    fn test_is_sync_artifact() {
+        assert!(is_sync_artifact("slot1.sav.boilerroom-part"));
+        assert!(is_sync_artifact("slot1.sav.boilerroom-tmp"));
+        assert!(is_sync_artifact("slot1.conflict-20260314T150926Z.sav"));
+        assert!(is_sync_artifact("profile.conflict-20260314T150926Z"));
+        assert!(!is_sync_artifact("slot1.sav"));
+        assert!(!is_sync_artifact("conflict-resolution.cfg"));
+    }
+
+    #[test]
+    fn test_common_directory() {
+        let paths = ["My Games/Foo/Saves/a.sav", "My Games/Foo/config.ini"];
+        assert_eq!(common_directory(paths.into_iter()), Some("My Games/Foo".to_string()));
+        assert_eq!(common_directory(["a.sav", "Foo/b.sav"].into_iter()), None);
+        assert_eq!(common_directory(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn test_scan_directory() {
+        let base = tempfile::tempdir().unwrap();
+        let write = |path: &str| {
+            let full = base.path().join(path);
+            std::fs::create_dir_all(full.parent().unwrap()).unwrap();
+            std::fs::write(full, b"data").unwrap();
+        };
+        write("saves/slot1.sav");
+        write("saves/slot2.sav");
+        write("saves/slot2.sav.boilerroom-part");
+        write("logs/game.log");
+
+        let mut files = HashMap::new();
+        files.insert(
+            "saves/slot1.sav".to_string(),
+            CloudFile {
+                path: "saves/slot1.sav".to_string(),
+                root: 0,
+                size: 4,
+                localtime: 0,
+                remotetime: 0,
+                sha: "known".to_string(),
+                syncstate: 0,
+                resolved_path: None,
+            },
+        );
+
+        let filter = FileFilter::new(&rules(&[], &["*.log"])).unwrap();
+        let added = scan_directory(base.path(), base.path(), 0, &filter, &mut files);
+
+        assert_eq!(added, 1);
+        let mut keys: Vec<&String> = files.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["saves/slot1.sav", "saves/slot2.sav"]);
+        // VDF entries are kept as they were
+        assert_eq!(files["saves/slot1.sav"].sha, "known");
+        assert_eq!(files["saves/slot2.sav"].size, 4);
+
+        // The same path on another root is a file of its own
+        let added = scan_directory(base.path(), base.path(), 12, &filter, &mut files);
+        assert_eq!(added, 2);
+        assert_eq!(files[".root12/saves/slot1.sav"].root, 12);
+        assert_eq!(files[".root12/saves/slot1.sav"].path, "saves/slot1.sav");
+
+        let remote = [
+            "saves/slot2.sav",
+            "saves/slot3.sav",
+            ".root12/saves/slot4.sav",
+            "../escape.sav",
+            "debug.log",
+        ];
+        union_remote_files(&mut files, remote.into_iter(), &rules(&[], &["*.log"])).unwrap();
+        assert_eq!(files.len(), 6);
+        assert_eq!(files["saves/slot3.sav"].root, 0);
+        assert_eq!(files[".root12/saves/slot4.sav"].root, 12);
+        assert_eq!(files[".root12/saves/slot4.sav"].path, "saves/slot4.sav");
+    }
+}
@@ -0,0 +1,341 @@
+//! CloudSync Sessions - Tracks whether a game is running
+//!
+//! Games often write their saves continuously while they run, so uploads are
+//! held back until the game exits and a sync is queued the moment it does.
+//! A game counts as running while any process of the user lives inside its
+//! install directory (see `cloudsync_roots::locate_game`) or, for Proton
+//! games, inside its Wine prefix.
+
+use crate::cloudsync_roots::{self, GameLocation};
+use crate::cloudsync_worker::CloudSyncWorker;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const SESSION_EVENT: &str = "cloudsync-session";
+
+/// How often tracked games are checked
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Payload of `cloudsync-session` events
+#[derive(Debug, Clone, Serialize)]
+pub struct GameSessionEvent {
+    pub app_id: String,
+    pub running: bool,
+}
+
+// ============================================================================
+// Process Matching
+// ============================================================================
+
+/// What we know about a running process
+#[derive(Debug, Default)]
+struct ProcessInfo {
+    exe: Option<PathBuf>,
+    args: Vec<String>,
+    /// WINEPREFIX / STEAM_COMPAT_DATA_PATH from its environment
+    wine_prefix: Option<PathBuf>,
+}
+
+/// Turn a Wine path of the host filesystem (`Z:\home\...`) into a Unix path
+fn unix_path_of(arg: &str) -> String {
+    let bytes = arg.as_bytes();
+    if bytes.len() > 2
+        && bytes[0].eq_ignore_ascii_case(&b'z')
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+    {
+        arg[2..].replace('\\', "/")
+    } else {
+        arg.to_string()
+    }
+}
+
+/// True if `arg` mentions a path inside `dir`
+fn mentions_dir(arg: &str, dir: &str) -> bool {
+    let arg = unix_path_of(arg);
+    let dir = dir.trim_end_matches('/');
+    arg == dir || arg.contains(&format!("{}/", dir))
+}
+
+/// True if the process belongs to the game
+fn process_matches(process: &ProcessInfo, dirs: &[String], prefix: Option<&Path>) -> bool {
+    if let (Some(prefix), Some(process_prefix)) = (prefix, &process.wine_prefix) {
+        // STEAM_COMPAT_DATA_PATH is the compatdata dir, the prefix is its pfx/
+        if process_prefix == prefix || prefix.parent() == Some(process_prefix.as_path()) {
+            return true;
+        }
+    }
+
+    dirs.iter().any(|dir| {
+        process
+            .exe
+            .as_ref()
+            .is_some_and(|exe| mentions_dir(&exe.to_string_lossy(), dir))
+            || process.args.iter().any(|arg| mentions_dir(arg, dir))
+    })
+}
+
+/// The install directory as written and as resolved, since libraries are
+/// often reached through symlinks
+fn install_dirs(location: &GameLocation) -> Vec<String> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = &location.install_dir {
+        dirs.push(dir.to_string_lossy().to_string());
+        if let Ok(canonical) = std::fs::canonicalize(dir) {
+            let canonical = canonical.to_string_lossy().to_string();
+            if !dirs.contains(&canonical) {
+                dirs.push(canonical);
+            }
+        }
+    }
+    dirs
+}
+
+#[cfg(target_os = "linux")]
+fn list_processes() -> Vec<ProcessInfo> {
+    let own_pid = std::process::id().to_string();
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            name.chars().all(|c| c.is_ascii_digit()) && name != own_pid
+        })
+        .filter_map(|e| {
+            let dir = e.path();
+            // Other users' processes can't be read, and don't matter
+            let cmdline = std::fs::read(dir.join("cmdline")).ok()?;
+            let args = cmdline
+                .split(|b| *b == 0)
+                .filter(|a| !a.is_empty())
+                .map(|a| String::from_utf8_lossy(a).to_string())
+                .collect();
+            let wine_prefix = std::fs::read(dir.join("environ")).ok().and_then(|environ| {
+                environ.split(|b| *b == 0).find_map(|var| {
+                    let var = String::from_utf8_lossy(var);
+                    var.strip_prefix("WINEPREFIX=")
+                        .or_else(|| var.strip_prefix("STEAM_COMPAT_DATA_PATH="))
+                        .map(|p| PathBuf::from(p.trim_end_matches('/')))
+                })
+            });
+
+            Some(ProcessInfo {
+                exe: std::fs::read_link(dir.join("exe")).ok(),
+                args,
+                wine_prefix,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_processes() -> Vec<ProcessInfo> {
+    // No /proc: the full command line is the best we have
+    let output = match std::process::Command::new("ps")
+        .args(["-axww", "-o", "command="])
+        .output()
+    {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| ProcessInfo {
+            args: vec![line.trim().to_string()],
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Which of the given games have a process running
+fn running_games(games: &HashMap<String, GameLocation>) -> HashSet<String> {
+    let processes = list_processes();
+
+    games
+        .iter()
+        .filter(|(_, location)| {
+            let dirs = install_dirs(location);
+            let prefix = location.proton_prefix.as_deref();
+            (!dirs.is_empty() || prefix.is_some())
+                && processes.iter().any(|p| process_matches(p, &dirs, prefix))
+        })
+        .map(|(app_id, _)| app_id.clone())
+        .collect()
+}
+
+/// Check whether a game is running right now
+pub fn is_game_running(app_id: &str, location: &GameLocation) -> bool {
+    let games = HashMap::from([(app_id.to_string(), location.clone())]);
+    !running_games(&games).is_empty()
+}
+
+// ============================================================================
+// Session Tracker
+// ============================================================================
+
+#[derive(Default)]
+struct TrackerState {
+    tracked: HashMap<String, GameLocation>,
+    running: HashSet<String>,
+}
+
+/// Polls the tracked games and syncs each one when it exits (managed by Tauri state)
+#[derive(Clone)]
+pub struct GameSessionTracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl GameSessionTracker {
+    /// Start the polling task
+    pub fn spawn(app_handle: AppHandle) -> Self {
+        let tracker = Self {
+            state: Arc::new(Mutex::new(TrackerState::default())),
+        };
+        tauri::async_runtime::spawn(tracker.clone().run(app_handle));
+        tracker
+    }
+
+    /// Start tracking games (already tracked ones are left as they are)
+    pub fn track(&self, app_ids: &[String]) {
+        let mut state = self.state.lock().unwrap();
+        for app_id in app_ids {
+            if !state.tracked.contains_key(app_id) {
+                state
+                    .tracked
+                    .insert(app_id.clone(), cloudsync_roots::locate_game(app_id));
+            }
+        }
+    }
+
+    /// Stop tracking all games
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tracked.clear();
+        state.running.clear();
+    }
+
+    /// Whether a tracked game was running at the last check
+    pub fn is_running(&self, app_id: &str) -> bool {
+        self.state.lock().unwrap().running.contains(app_id)
+    }
+
+    async fn run(self, app_handle: AppHandle) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let tracked = self.state.lock().unwrap().tracked.clone();
+            if tracked.is_empty() {
+                continue;
+            }
+
+            let now_running = match tokio::task::spawn_blocking(move || running_games(&tracked)).await {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("[CloudSync] Process scan failed: {}", e);
+                    continue;
+                }
+            };
+
+            let (started, exited) = {
+                let mut state = self.state.lock().unwrap();
+                // Games untracked during the scan are dropped
+                let now_running: HashSet<String> = now_running
+                    .into_iter()
+                    .filter(|id| state.tracked.contains_key(id))
+                    .collect();
+                let started: Vec<String> = now_running.difference(&state.running).cloned().collect();
+                let exited: Vec<String> = state.running.difference(&now_running).cloned().collect();
+                state.running = now_running;
+                (started, exited)
+            };
+
+            for app_id in started {
+                eprintln!("[CloudSync] Game {} started, holding uploads until it exits", app_id);
+                let _ = app_handle.emit(
+                    SESSION_EVENT,
+                    GameSessionEvent {
+                        app_id,
+                        running: true,
+                    },
+                );
+            }
+
+            for app_id in exited {
+                eprintln!("[CloudSync] Game {} exited, syncing saves", app_id);
+                if let Some(worker) = app_handle.try_state::<CloudSyncWorker>() {
+                    worker.request_sync(&app_id);
+                }
+                let _ = app_handle.emit(
+                    SESSION_EVENT,
+                    GameSessionEvent {
+                        app_id,
+                        running: false,
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(exe: &str, args: &[&str]) -> ProcessInfo {
+        ProcessInfo {
+            exe: Some(PathBuf::from(exe)),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            wine_prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_unix_path_of() {
+        assert_eq!(
+            unix_path_of("Z:\\home\\deck\\Games\\Hades\\Hades.exe"),
+            "/home/deck/Games/Hades/Hades.exe"
+        );
+        assert_eq!(unix_path_of("C:\\windows\\system32"), "C:\\windows\\system32");
+        assert_eq!(unix_path_of("-windowed"), "-windowed");
+    }
+
+    #[test]
+    fn test_process_matches_install_dir() {
+        let dirs = vec!["/lib/steamapps/common/Hades".to_string()];
+
+        let native = process("/lib/steamapps/common/Hades/Hades.x86_64", &[]);
+        assert!(process_matches(&native, &dirs, None));
+
+        let wine = process(
+            "/lib/steamapps/common/Proton 9.0/files/bin/wine64-preloader",
+            &["Z:\\lib\\steamapps\\common\\Hades\\x64\\Hades.exe"],
+        );
+        assert!(process_matches(&wine, &dirs, None));
+
+        // A sibling install sharing the name prefix isn't the game
+        let sibling = process("/lib/steamapps/common/Hades II/Hades2.x86_64", &[]);
+        assert!(!process_matches(&sibling, &dirs, None));
+    }
+
+    #[test]
+    fn test_process_matches_wine_prefix() {
+        let prefix = Path::new("/lib/steamapps/compatdata/1145360/pfx");
+        let mut launcher = process("/usr/bin/wineserver", &[]);
+
+        launcher.wine_prefix = Some(PathBuf::from("/lib/steamapps/compatdata/1145360"));
+        assert!(process_matches(&launcher, &[], Some(prefix)));
+
+        launcher.wine_prefix = Some(PathBuf::from("/lib/steamapps/compatdata/730/pfx"));
+        assert!(!process_matches(&launcher, &[], Some(prefix)));
+    }
+}
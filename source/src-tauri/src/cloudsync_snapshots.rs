@@ -0,0 +1,382 @@
+//! CloudSync Snapshots - Versioned save history on the remote
+//!
+//! Before a sync overwrites remote saves, the current remote set of a game is
+//! copied into a timestamped snapshot so a bad upload can always be rolled back.
+//!
+//! Remote layout:
+//! - boilerroom/<app_id>/...                              (live files)
+//! - boilerroom/.snapshots/<app_id>/<snapshot_id>/...     (snapshot files)
+//! - boilerroom/.snapshots/<app_id>/<snapshot_id>/manifest.json
+
+use crate::cloudsync::{
+    encode_webdav_path, file_key, normalize_remote_path, remote_file_path, split_file_key, CloudFile,
+};
+use crate::cloudsync_state::{self, FileDigest};
+use crate::cloudsync_storage::{parent_path, CloudStorage, Progress};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Snapshot IDs are UTC timestamps, suffixed (`-2`, `-3`, ...) when several
+/// snapshots are taken within a second
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Metadata for a single snapshot (stored as manifest.json inside the snapshot)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub app_id: String,
+    pub created_at: String,
+    pub files: Vec<CloudFile>,
+    /// Size (and, where the remote index knew it, SHA1) of each file's
+    /// content when the snapshot was taken, to verify restores against.
+    /// Empty for snapshots taken before this was recorded.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub digests: HashMap<String, FileDigest>,
+}
+
+/// Remote collection holding all snapshots of a game
+fn snapshots_root(app_id: &str) -> String {
+    format!("boilerroom/.snapshots/{}", app_id)
+}
+
+/// Format a snapshot ID from a timestamp
+pub fn snapshot_id_from_time(time: DateTime<Utc>) -> String {
+    time.format(SNAPSHOT_ID_FORMAT).to_string()
+}
+
+/// Snapshot ID for a timestamp, suffixed if a snapshot with that ID exists
+fn next_snapshot_id(time: DateTime<Utc>, existing: &[String]) -> String {
+    let base = snapshot_id_from_time(time);
+    let mut id = base.clone();
+    let mut n = 2;
+    while existing.contains(&id) {
+        id = format!("{}-{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+/// Split a snapshot ID into its timestamp and sequence number (1 unsuffixed).
+/// Local rollback points use the same IDs.
+pub fn split_snapshot_id(id: &str) -> Option<(DateTime<Utc>, u32)> {
+    let (time, n) = match id.split_once('-') {
+        Some((time, n)) => (time, n.parse().ok().filter(|n| *n >= 2)?),
+        None => (id, 1),
+    };
+    let time = NaiveDateTime::parse_from_str(time, SNAPSHOT_ID_FORMAT).ok()?;
+    Some((time.and_utc(), n))
+}
+
+/// Parse a snapshot ID back into its timestamp
+pub fn parse_snapshot_id(id: &str) -> Option<DateTime<Utc>> {
+    split_snapshot_id(id).map(|(time, _)| time)
+}
+
+/// Snapshot the current remote set of a game.
+///
+/// `files` provides root/path metadata for the manifest; remote files that
+/// aren't in it are still copied, with the root their key names.
+/// Returns the new snapshot ID, or None if there was nothing to snapshot.
+pub async fn create_snapshot(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    files: &HashMap<String, CloudFile>,
+) -> Result<Option<String>, String> {
+    let remote_files = client.list_file_entries(app_id).await?;
+    if remote_files.is_empty() {
+        return Ok(None);
+    }
+    let index = cloudsync_state::load_remote_index(client, app_id).await;
+
+    let now = Utc::now();
+    let id = next_snapshot_id(now, &list_snapshot_ids(client, app_id).await?);
+    let snapshot_dir = format!("{}/{}", snapshots_root(app_id), id);
+    client.mkdir(&snapshot_dir).await?;
+
+    let mut manifest_files = Vec::new();
+    let mut digests = HashMap::new();
+    for entry in &remote_files {
+        let remote_path = &entry.name;
+        let target = format!("{}/{}", snapshot_dir, normalize_remote_path(remote_path));
+        client.mkdir(parent_path(&target)).await?;
+        client
+            .copy(&remote_file_path(app_id, remote_path), &target)
+            .await?;
+
+        let file = files.get(remote_path).cloned().unwrap_or_else(|| {
+            let (root, path) = split_file_key(remote_path);
+            CloudFile {
+                path: path.to_string(),
+                root,
+                size: 0,
+                localtime: 0,
+                remotetime: 0,
+                sha: String::new(),
+                syncstate: 0,
+                resolved_path: None,
+            }
+        });
+        manifest_files.push(file);
+
+        // The index only vouches for a file it has the listed size of
+        let digest = index
+            .files
+            .get(remote_path)
+            .filter(|d| d.size == entry.size)
+            .cloned()
+            .unwrap_or(FileDigest {
+                sha: String::new(),
+                size: entry.size,
+            });
+        digests.insert(remote_path.clone(), digest);
+    }
+
+    let info = SnapshotInfo {
+        id: id.clone(),
+        app_id: app_id.to_string(),
+        created_at: now.to_rfc3339(),
+        files: manifest_files,
+        digests,
+    };
+    let manifest = serde_json::to_vec_pretty(&info)
+        .map_err(|e| format!("Failed to serialize snapshot manifest: {}", e))?;
+    client
+        .upload(&format!("{}/{}", snapshot_dir, MANIFEST_FILE), manifest)
+        .await?;
+
+    eprintln!(
+        "[CloudSync] Created snapshot {} for app {} ({} files)",
+        id,
+        app_id,
+        remote_files.len()
+    );
+
+    Ok(Some(id))
+}
+
+/// List snapshot IDs for a game, newest first
+pub async fn list_snapshot_ids(client: &dyn CloudStorage, app_id: &str) -> Result<Vec<String>, String> {
+    let entries = client.list(&snapshots_root(app_id)).await?;
+
+    let mut ids: Vec<(DateTime<Utc>, u32, String)> = entries
+        .into_iter()
+        .filter(|e| e.is_dir)
+        .filter_map(|e| split_snapshot_id(&e.name).map(|(time, n)| (time, n, e.name)))
+        .collect();
+    ids.sort_by(|a, b| b.cmp(a));
+    let ids = ids.into_iter().map(|(_, _, id)| id).collect();
+
+    Ok(ids)
+}
+
+/// Read the manifest of a single snapshot
+pub async fn get_snapshot(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    snapshot_id: &str,
+) -> Result<SnapshotInfo, String> {
+    if split_snapshot_id(snapshot_id).is_none() {
+        return Err(format!("Invalid snapshot ID: {}", snapshot_id));
+    }
+    let data = client
+        .download(&format!(
+            "{}/{}/{}",
+            snapshots_root(app_id),
+            snapshot_id,
+            MANIFEST_FILE
+        ))
+        .await?;
+
+    serde_json::from_slice(&data).map_err(|e| format!("Invalid snapshot manifest: {}", e))
+}
+
+/// Keys the files of a snapshot are stored under, in manifest order.
+/// Snapshots taken before files on roots other than 0 were kept apart hold
+/// those under their bare path.
+pub async fn snapshot_file_keys(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    snapshot: &SnapshotInfo,
+) -> Result<Vec<String>, String> {
+    let stored: HashSet<String> = client
+        .list_recursive(&format!("{}/{}", snapshots_root(app_id), snapshot.id))
+        .await?
+        .into_iter()
+        .filter(|e| !e.is_dir)
+        .map(|e| e.name)
+        .collect();
+
+    Ok(snapshot
+        .files
+        .iter()
+        .map(|file| {
+            let key = file_key(file.root, &file.path);
+            if stored.contains(&normalize_remote_path(&key)) {
+                key
+            } else {
+                file.path.clone()
+            }
+        })
+        .collect())
+}
+
+/// List all snapshots for a game, newest first
+pub async fn list_snapshots(client: &dyn CloudStorage, app_id: &str) -> Result<Vec<SnapshotInfo>, String> {
+    let mut snapshots = Vec::new();
+
+    for id in list_snapshot_ids(client, app_id).await? {
+        match get_snapshot(client, app_id, &id).await {
+            Ok(info) => snapshots.push(info),
+            Err(e) => eprintln!("[CloudSync] Skipping snapshot {}: {}", id, e),
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// Download a single file from a snapshot
+pub async fn download_snapshot_file(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    snapshot_id: &str,
+    relative_path: &str,
+    local: &Path,
+    progress: Progress<'_>,
+) -> Result<(), String> {
+    let snapshot_dir = format!("{}/{}", snapshots_root(app_id), snapshot_id);
+
+    match client
+        .download_to(
+            &format!("{}/{}", snapshot_dir, normalize_remote_path(relative_path)),
+            local,
+            progress,
+        )
+        .await
+    {
+        Ok(()) => Ok(()),
+        // Snapshots taken before the remote tree mirrored local directories
+        // store files under their flattened names
+        Err(e) if e == "File not found on server" => client
+            .download_to(
+                &format!("{}/{}", snapshot_dir, encode_webdav_path(relative_path)),
+                local,
+                progress,
+            )
+            .await
+            .map_err(|_| e),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decide which snapshots fall outside the retention policy.
+///
+/// `ids` must be sorted newest first. The newest snapshot is always kept.
+/// A `keep_count` or `max_age_days` of 0 disables that limit.
+pub fn snapshots_to_prune(
+    ids: &[String],
+    keep_count: u32,
+    max_age_days: u32,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    ids.iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(index, id)| {
+            let over_count = keep_count > 0 && *index >= keep_count as usize;
+            let too_old = max_age_days > 0
+                && parse_snapshot_id(id)
+                    .map(|t| now.signed_duration_since(t).num_days() >= max_age_days as i64)
+                    .unwrap_or(false);
+            over_count || too_old
+        })
+        .map(|(_, id)| id.clone())
+        .collect()
+}
+
+/// Delete snapshots that fall outside the retention policy.
+/// Returns the number of snapshots removed.
+pub async fn prune_snapshots(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    keep_count: u32,
+    max_age_days: u32,
+) -> Result<u32, String> {
+    let ids = list_snapshot_ids(client, app_id).await?;
+    let mut removed = 0;
+
+    for id in snapshots_to_prune(&ids, keep_count, max_age_days, Utc::now()) {
+        client
+            .delete(&format!("{}/{}/", snapshots_root(app_id), id))
+            .await?;
+        removed += 1;
+    }
+
+    if removed > 0 {
+        eprintln!("[CloudSync] Pruned {} old snapshots for app {}", removed, app_id);
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_snapshot_id_roundtrip() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 14, 15, 9, 26).unwrap();
+        let id = snapshot_id_from_time(time);
+        assert_eq!(id, "20260314T150926Z");
+        assert_eq!(parse_snapshot_id(&id), Some(time));
+        assert_eq!(parse_snapshot_id("not-a-snapshot"), None);
+    }
+
+    #[test]
+    fn test_snapshot_ids_within_a_second() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 14, 15, 9, 26).unwrap();
+        let mut existing = Vec::new();
+        for expected in ["20260314T150926Z", "20260314T150926Z-2", "20260314T150926Z-3"] {
+            let id = next_snapshot_id(time, &existing);
+            assert_eq!(id, expected);
+            assert_eq!(parse_snapshot_id(&id), Some(time));
+            existing.push(id);
+        }
+
+        assert_eq!(split_snapshot_id("20260314T150926Z-10"), Some((time, 10)));
+        assert_eq!(split_snapshot_id("20260314T150926Z-1"), None);
+        assert_eq!(split_snapshot_id("20260314T150926Z-x"), None);
+    }
+
+    #[test]
+    fn test_snapshots_to_prune() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 31, 0, 0, 0).unwrap();
+        let ids: Vec<String> = vec![
+            "20260330T000000Z".into(),
+            "20260320T000000Z".into(),
+            "20260310T000000Z".into(),
+            "20260101T000000Z".into(),
+        ];
+
+        // Count limit only
+        assert_eq!(
+            snapshots_to_prune(&ids, 2, 0, now),
+            vec!["20260310T000000Z".to_string(), "20260101T000000Z".to_string()]
+        );
+
+        // Age limit only
+        assert_eq!(
+            snapshots_to_prune(&ids, 0, 15, now),
+            vec!["20260310T000000Z".to_string(), "20260101T000000Z".to_string()]
+        );
+
+        // No limits
+        assert!(snapshots_to_prune(&ids, 0, 0, now).is_empty());
+
+        // Newest snapshot always survives
+        assert!(snapshots_to_prune(&ids[..1], 0, 1, now).is_empty());
+    }
+}
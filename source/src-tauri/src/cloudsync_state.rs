@@ -0,0 +1,639 @@
+//! CloudSync State - Persisted sync manifests for three-way conflict detection
+//!
+//! Two manifests are kept per game:
+//! - Local sync state (~/.local/share/boilerroom/cloudsync/state/<account_id>/<app_id>.json):
+//!   the SHA1 and size of every file as of the last successful sync (the "base").
+//! - Remote index (boilerroom/.index/<app_id>.json on the server): the SHA1 and
+//!   size of every remote file, updated by whichever device uploads.
+//!
+//! Comparing local and remote against the base tells us which side changed.
+//! Digests use SHA1 so they're directly comparable with the `sha` values Steam
+//! records in remotecache.vdf.
+
+use crate::cloudsync::SyncMode;
+use crate::cloudsync_storage::{CloudStorage, RemoteEntry};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Content fingerprint of a save file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileDigest {
+    pub sha: String,
+    pub size: u64,
+}
+
+/// Per-game local sync state
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GameSyncState {
+    pub app_id: String,
+    /// Steam account the saves belong to
+    #[serde(default)]
+    pub account_id: String,
+    /// Digest of each file (by relative path) as of the last sync
+    #[serde(default)]
+    pub files: HashMap<String, FileDigest>,
+    /// Files that changed on both sides and are waiting for the user
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    /// Files deleted by a sync (on either side)
+    #[serde(default)]
+    pub deleted: HashMap<String, Tombstone>,
+    /// Listed version of each remote file as of the last sync, where the
+    /// backend reports one (not known right after an upload)
+    #[serde(default)]
+    pub remote_versions: HashMap<String, RemoteVersion>,
+}
+
+/// Version of a remote file as the backend listed it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemoteVersion {
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<u64>,
+}
+
+impl RemoteVersion {
+    pub fn of(entry: &RemoteEntry) -> Self {
+        Self {
+            size: entry.size,
+            etag: entry.etag.clone(),
+            modified: entry.modified,
+        }
+    }
+}
+
+/// Per-game remote index, stored next to the saves on the server
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RemoteIndex {
+    #[serde(default)]
+    pub files: HashMap<String, FileDigest>,
+    /// Files deleted from the remote, so devices that never synced them
+    /// don't upload their stale copies again
+    #[serde(default)]
+    pub deleted: HashMap<String, Tombstone>,
+}
+
+/// Record of a deleted save file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Tombstone {
+    /// Content the file had when it was deleted
+    pub digest: FileDigest,
+    pub deleted_at: String,
+}
+
+/// What to do with a single file during sync
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    None,
+    Upload,
+    Download,
+    Conflict,
+    /// Deleted remotely since the last sync: delete the local file
+    DeleteLocal,
+    /// Deleted locally since the last sync: delete the remote file
+    DeleteRemote,
+}
+
+/// How the user wants a conflict resolved
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum ConflictResolution {
+    /// Overwrite the remote file with the local one
+    KeepLocal,
+    /// Overwrite the local file with the remote one
+    KeepRemote,
+    /// Keep the local file, and store the remote version as a conflict copy next to it
+    KeepBoth,
+}
+
+// ============================================================================
+// Hashing
+// ============================================================================
+
+/// Compute the digest of a file on disk without loading it fully into memory
+pub fn hash_file(path: &Path) -> Result<FileDigest, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha1::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok(FileDigest {
+        sha: format!("{:x}", hasher.finalize()),
+        size,
+    })
+}
+
+// ============================================================================
+// Three-way Decision
+// ============================================================================
+
+/// Decide what to do with a file given its local, remote and last-synced
+/// digests. A file missing on one side is a deletion if the other side still
+/// has the last-synced content; if that changed meanwhile, the change wins.
+pub fn decide_sync_action(
+    local: Option<&FileDigest>,
+    remote: Option<&FileDigest>,
+    base: Option<&FileDigest>,
+) -> SyncAction {
+    match (local, remote) {
+        (None, None) => SyncAction::None,
+        (None, Some(r)) if base == Some(r) => SyncAction::DeleteRemote,
+        (Some(l), None) if base == Some(l) => SyncAction::DeleteLocal,
+        (Some(_), None) => SyncAction::Upload,
+        (None, Some(_)) => SyncAction::Download,
+        (Some(l), Some(r)) if l == r => SyncAction::None,
+        (Some(l), Some(r)) => {
+            let base = match base {
+                Some(b) => b,
+                // Both sides exist with different content and were never synced
+                None => return SyncAction::Conflict,
+            };
+            match (l != base, r != base) {
+                (true, false) => SyncAction::Upload,
+                (false, true) => SyncAction::Download,
+                _ => SyncAction::Conflict,
+            }
+        }
+    }
+}
+
+/// A file and what a sync would do with it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlannedFile {
+    pub path: String,
+    pub action: SyncAction,
+}
+
+/// Narrow the action for a file down to what the sync mode allows.
+/// `None` means the file is skipped this time and stays out of sync.
+/// Forced modes never delete: a file missing on the winning side is skipped.
+pub fn action_for_mode(
+    action: SyncAction,
+    mode: SyncMode,
+    has_local: bool,
+    has_remote: bool,
+) -> Option<SyncAction> {
+    match (mode, action) {
+        (_, SyncAction::None) => Some(SyncAction::None),
+        (SyncMode::Full | SyncMode::DryRun, _) => Some(action),
+        (SyncMode::UploadOnly, SyncAction::Download | SyncAction::DeleteLocal) => None,
+        (SyncMode::DownloadOnly, SyncAction::Upload | SyncAction::DeleteRemote) => None,
+        (SyncMode::UploadOnly | SyncMode::DownloadOnly, _) => Some(action),
+        (SyncMode::ForceUpload, _) => has_local.then_some(SyncAction::Upload),
+        (SyncMode::ForceDownload, _) => has_remote.then_some(SyncAction::Download),
+    }
+}
+
+/// Deletions are only passed on while the game still has saves on the side
+/// they came from. If every save is gone there (reinstall, fresh Proton
+/// prefix, empty bucket), that side was wiped rather than cleaned up, and is
+/// restored from the other one instead.
+pub fn guard_wiped_side(action: SyncAction, local_empty: bool, remote_empty: bool) -> SyncAction {
+    match action {
+        SyncAction::DeleteRemote if local_empty => SyncAction::Download,
+        SyncAction::DeleteLocal if remote_empty => SyncAction::Upload,
+        action => action,
+    }
+}
+
+/// The digest a file was last synced with: the sync state's, or for a file
+/// this device never synced, the one it had when another device deleted it
+pub fn base_digest<'a>(
+    state: &'a GameSyncState,
+    index: &'a RemoteIndex,
+    file_path: &str,
+) -> Option<&'a FileDigest> {
+    state
+        .files
+        .get(file_path)
+        .or_else(|| index.deleted.get(file_path).map(|t| &t.digest))
+}
+
+/// Whether a listed remote file is still the version of the last sync, by
+/// its ETag or else its modification time. None when the listing can't tell.
+fn unchanged_since_sync(listed: &RemoteEntry, synced: Option<&RemoteVersion>) -> Option<bool> {
+    let synced = synced?;
+    if synced.size != listed.size {
+        return Some(false);
+    }
+    if let (Some(synced), Some(listed)) = (&synced.etag, &listed.etag) {
+        return Some(synced == listed);
+    }
+    match (synced.modified, listed.modified) {
+        (Some(synced), Some(listed)) => Some(synced == listed),
+        _ => None,
+    }
+}
+
+/// The index digest of a remote file, if it can be trusted: it has to match
+/// the listed size, and can't be the synced digest of a file the listing
+/// shows was replaced since (behind the index's back, e.g. by an older
+/// version or another tool)
+pub fn indexed_digest<'a>(
+    listed: &RemoteEntry,
+    synced: Option<&RemoteVersion>,
+    indexed: Option<&'a FileDigest>,
+    base: Option<&FileDigest>,
+) -> Option<&'a FileDigest> {
+    let indexed = indexed.filter(|d| d.size == listed.size)?;
+    let replaced = unchanged_since_sync(listed, synced) == Some(false);
+    (!(replaced && Some(indexed) == base)).then_some(indexed)
+}
+
+/// Best estimate of a remote file's digest without downloading it. A
+/// trusted index digest is used first. Otherwise a file the listing shows
+/// unchanged since the last sync has its synced digest; without an ETag or
+/// modification time, a file still the size it had then is assumed
+/// unchanged. Anything else changed (with unknown content).
+pub fn estimate_remote_digest(
+    listed: &RemoteEntry,
+    synced: Option<&RemoteVersion>,
+    indexed: Option<&FileDigest>,
+    base: Option<&FileDigest>,
+) -> FileDigest {
+    if let Some(d) = indexed_digest(listed, synced, indexed, base) {
+        return d.clone();
+    }
+    if unchanged_since_sync(listed, synced) != Some(false) {
+        if let Some(b) = base.filter(|b| b.size == listed.size) {
+            return b.clone();
+        }
+    }
+    FileDigest {
+        sha: String::new(),
+        size: listed.size,
+    }
+}
+
+// ============================================================================
+// Local State Persistence
+// ============================================================================
+
+/// Directory holding per-game sync state files
+fn get_state_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let dir = home.join(".local/share/boilerroom/cloudsync/state");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create sync state dir: {}", e))?;
+    Ok(dir)
+}
+
+fn state_file(dir: &Path, account_id: &str, app_id: &str) -> PathBuf {
+    dir.join(account_id).join(format!("{}.json", app_id))
+}
+
+/// Load the sync state of a game for an account (empty state if none was
+/// saved yet)
+pub fn load_sync_state(account_id: &str, app_id: &str) -> GameSyncState {
+    let state = get_state_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_to_string(state_file(&dir, account_id, app_id)).ok())
+        .and_then(|content| serde_json::from_str::<GameSyncState>(&content).ok());
+
+    let mut state = state.unwrap_or_else(|| GameSyncState {
+        app_id: app_id.to_string(),
+        ..Default::default()
+    });
+    state.account_id = account_id.to_string();
+    state
+}
+
+/// Persist the sync state for a game
+pub fn save_sync_state(state: &GameSyncState) -> Result<(), String> {
+    let dir = get_state_dir()?;
+    let path = state_file(&dir, &state.account_id, &state.app_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create sync state dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize sync state: {}", e))?;
+
+    // Write to a temp file first so a crash never leaves a truncated state file
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write sync state: {}", e))?;
+    std::fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save sync state: {}", e))?;
+    Ok(())
+}
+
+/// Games of an account that have a saved sync state
+pub fn state_games(account_id: &str) -> Vec<String> {
+    get_state_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_dir(dir.join(account_id)).ok())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter_map(|name| name.strip_suffix(".json").map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Games with a sync state from before accounts were separated
+/// (~/.local/share/boilerroom/cloudsync/state/<app_id>.json)
+pub fn legacy_state_games() -> Vec<String> {
+    get_state_dir()
+        .ok()
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.path().is_file())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter_map(|name| name.strip_suffix(".json").map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Hand a game's sync state from before accounts were separated to an
+/// account. Returns false if there is none, or the account already has its own.
+pub fn adopt_legacy_state(account_id: &str, app_id: &str) -> Result<bool, String> {
+    adopt_legacy_state_in(&get_state_dir()?, account_id, app_id)
+}
+
+fn adopt_legacy_state_in(dir: &Path, account_id: &str, app_id: &str) -> Result<bool, String> {
+    let legacy = dir.join(format!("{}.json", app_id));
+    let path = state_file(dir, account_id, app_id);
+    if !legacy.is_file() || path.exists() {
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create sync state dir: {}", e))?;
+    }
+    std::fs::rename(&legacy, &path).map_err(|e| format!("Failed to move sync state: {}", e))?;
+    Ok(true)
+}
+
+// ============================================================================
+// Remote Index
+// ============================================================================
+
+fn remote_index_path(app_id: &str) -> String {
+    format!("boilerroom/.index/{}.json", app_id)
+}
+
+/// Load the remote index for a game (empty if missing or unreadable)
+pub async fn load_remote_index(client: &dyn CloudStorage, app_id: &str) -> RemoteIndex {
+    match client.download(&remote_index_path(app_id)).await {
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_default(),
+        Err(_) => RemoteIndex::default(),
+    }
+}
+
+/// Upload the remote index for a game
+pub async fn save_remote_index(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    index: &RemoteIndex,
+) -> Result<(), String> {
+    let data = serde_json::to_vec_pretty(index)
+        .map_err(|e| format!("Failed to serialize remote index: {}", e))?;
+    client.mkdir("boilerroom/.index").await?;
+    client.upload(&remote_index_path(app_id), data).await
+}
+
+/// Build the local path used to keep the remote side of a conflict,
+/// e.g. `slot1.sav` -> `slot1.conflict-20260314T150926Z.sav`
+pub fn conflict_copy_path(path: &Path, timestamp: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.conflict-{}.{}", stem, timestamp, ext.to_string_lossy()),
+        None => format!("{}.conflict-{}", stem, timestamp),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(sha: &str) -> FileDigest {
+        FileDigest {
+            sha: sha.to_string(),
+            size: 1,
+        }
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("slot1.sav");
+        std::fs::write(&path, b"hello").unwrap();
+        let d = hash_file(&path).unwrap();
+        assert_eq!(d.sha, "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d");
+        assert_eq!(d.size, 5);
+    }
+
+    #[test]
+    fn test_adopt_legacy_state() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("730.json"), r#"{"app_id":"730"}"#).unwrap();
+
+        assert!(adopt_legacy_state_in(dir.path(), "42", "730").unwrap());
+        assert!(state_file(dir.path(), "42", "730").is_file());
+        assert!(!dir.path().join("730.json").exists());
+
+        // An account's own state is never replaced
+        std::fs::write(dir.path().join("730.json"), r#"{"app_id":"730"}"#).unwrap();
+        assert!(!adopt_legacy_state_in(dir.path(), "42", "730").unwrap());
+        assert!(dir.path().join("730.json").exists());
+        assert!(!adopt_legacy_state_in(dir.path(), "42", "440").unwrap());
+    }
+
+    #[test]
+    fn test_decide_sync_action() {
+        let a = digest("a");
+        let b = digest("b");
+        let c = digest("c");
+
+        assert_eq!(decide_sync_action(None, None, None), SyncAction::None);
+        assert_eq!(decide_sync_action(Some(&a), None, None), SyncAction::Upload);
+        assert_eq!(decide_sync_action(None, Some(&a), None), SyncAction::Download);
+        assert_eq!(decide_sync_action(Some(&a), Some(&a), None), SyncAction::None);
+
+        // Only local changed since last sync
+        assert_eq!(decide_sync_action(Some(&b), Some(&a), Some(&a)), SyncAction::Upload);
+        // Only remote changed since last sync
+        assert_eq!(decide_sync_action(Some(&a), Some(&b), Some(&a)), SyncAction::Download);
+        // Both changed
+        assert_eq!(decide_sync_action(Some(&b), Some(&c), Some(&a)), SyncAction::Conflict);
+        // Differ but never synced
+        assert_eq!(decide_sync_action(Some(&a), Some(&b), None), SyncAction::Conflict);
+    }
+
+    #[test]
+    fn test_decide_deletions() {
+        let a = digest("a");
+        let b = digest("b");
+
+        // Deleted on one side, untouched on the other
+        assert_eq!(decide_sync_action(None, Some(&a), Some(&a)), SyncAction::DeleteRemote);
+        assert_eq!(decide_sync_action(Some(&a), None, Some(&a)), SyncAction::DeleteLocal);
+        // Deleted on one side, changed on the other: the change wins
+        assert_eq!(decide_sync_action(None, Some(&b), Some(&a)), SyncAction::Download);
+        assert_eq!(decide_sync_action(Some(&b), None, Some(&a)), SyncAction::Upload);
+
+        // A device that never synced the file learns of the deletion from the index
+        let state = GameSyncState::default();
+        let mut index = RemoteIndex::default();
+        index.deleted.insert(
+            "slot1.sav".to_string(),
+            Tombstone {
+                digest: a.clone(),
+                deleted_at: "2026-03-14T15:09:26Z".to_string(),
+            },
+        );
+        let base = base_digest(&state, &index, "slot1.sav");
+        assert_eq!(decide_sync_action(Some(&a), None, base), SyncAction::DeleteLocal);
+        assert_eq!(decide_sync_action(Some(&b), None, base), SyncAction::Upload);
+
+        // Nothing is deleted from a side that was wiped
+        assert_eq!(
+            guard_wiped_side(SyncAction::DeleteRemote, true, false),
+            SyncAction::Download
+        );
+        assert_eq!(guard_wiped_side(SyncAction::DeleteLocal, false, true), SyncAction::Upload);
+        assert_eq!(
+            guard_wiped_side(SyncAction::DeleteLocal, false, false),
+            SyncAction::DeleteLocal
+        );
+    }
+
+    #[test]
+    fn test_action_for_mode() {
+        use SyncAction::{Conflict, DeleteLocal, DeleteRemote, Download, Upload};
+
+        assert_eq!(action_for_mode(Download, SyncMode::Full, true, true), Some(Download));
+        assert_eq!(action_for_mode(Download, SyncMode::UploadOnly, true, true), None);
+        assert_eq!(action_for_mode(Upload, SyncMode::DownloadOnly, true, false), None);
+        assert_eq!(action_for_mode(Conflict, SyncMode::UploadOnly, true, true), Some(Conflict));
+        assert_eq!(action_for_mode(DeleteLocal, SyncMode::UploadOnly, true, true), None);
+        assert_eq!(
+            action_for_mode(DeleteRemote, SyncMode::UploadOnly, false, true),
+            Some(DeleteRemote)
+        );
+
+        // Forcing a direction overrides conflicts and the other side's changes
+        assert_eq!(action_for_mode(Conflict, SyncMode::ForceUpload, true, true), Some(Upload));
+        assert_eq!(action_for_mode(Upload, SyncMode::ForceDownload, true, true), Some(Download));
+        assert_eq!(
+            action_for_mode(SyncAction::None, SyncMode::ForceUpload, true, true),
+            Some(SyncAction::None)
+        );
+
+        // ...but never deletes what only exists on the losing side
+        assert_eq!(action_for_mode(Download, SyncMode::ForceUpload, false, true), None);
+        assert_eq!(action_for_mode(Upload, SyncMode::ForceDownload, true, false), None);
+    }
+
+    fn listed(size: u64, etag: Option<&str>, modified: Option<u64>) -> RemoteEntry {
+        RemoteEntry {
+            name: "slot1.sav".to_string(),
+            is_dir: false,
+            size,
+            modified,
+            etag: etag.map(|e| e.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_estimate_remote_digest() {
+        let indexed = FileDigest { sha: "i".to_string(), size: 10 };
+        let base = FileDigest { sha: "b".to_string(), size: 20 };
+
+        assert_eq!(
+            estimate_remote_digest(&listed(10, None, None), None, Some(&indexed), Some(&base)),
+            indexed
+        );
+        // Index out of date, but the size is what it was at the last sync
+        assert_eq!(
+            estimate_remote_digest(&listed(20, None, None), None, Some(&indexed), Some(&base)),
+            base
+        );
+
+        // Neither matches: changed, content unknown, so it never equals a local file
+        let unknown =
+            estimate_remote_digest(&listed(30, None, None), None, Some(&indexed), Some(&base));
+        assert_eq!(unknown.size, 30);
+        assert_eq!(
+            decide_sync_action(Some(&base), Some(&unknown), Some(&base)),
+            SyncAction::Download
+        );
+    }
+
+    #[test]
+    fn test_estimate_remote_digest_same_size_change() {
+        let base = FileDigest { sha: "b".to_string(), size: 20 };
+        let synced = RemoteVersion::of(&listed(20, Some("\"v1\""), Some(1000)));
+
+        // Same ETag: unchanged
+        let same = listed(20, Some("\"v1\""), Some(1000));
+        assert_eq!(
+            estimate_remote_digest(&same, Some(&synced), Some(&base), Some(&base)),
+            base
+        );
+
+        // Rewritten with the same size behind the index's back: a new ETag
+        // (or, without ETags, a new modification time) gives it away
+        for changed in [
+            listed(20, Some("\"v2\""), Some(1000)),
+            listed(20, None, Some(2000)),
+        ] {
+            assert_eq!(indexed_digest(&changed, Some(&synced), Some(&base), Some(&base)), None);
+            let estimate = estimate_remote_digest(&changed, Some(&synced), Some(&base), Some(&base));
+            assert_ne!(estimate, base);
+            assert_eq!(
+                decide_sync_action(Some(&base), Some(&estimate), Some(&base)),
+                SyncAction::Download
+            );
+        }
+
+        // An index updated by the device that uploaded it is still trusted
+        let uploaded = FileDigest { sha: "u".to_string(), size: 20 };
+        let changed = listed(20, Some("\"v2\""), Some(2000));
+        assert_eq!(
+            estimate_remote_digest(&changed, Some(&synced), Some(&uploaded), Some(&base)),
+            uploaded
+        );
+    }
+
+    #[test]
+    fn test_conflict_copy_path() {
+        assert_eq!(
+            conflict_copy_path(Path::new("/saves/slot1.sav"), "20260314T150926Z"),
+            PathBuf::from("/saves/slot1.conflict-20260314T150926Z.sav")
+        );
+        assert_eq!(
+            conflict_copy_path(Path::new("/saves/profile"), "20260314T150926Z"),
+            PathBuf::from("/saves/profile.conflict-20260314T150926Z")
+        );
+    }
+}
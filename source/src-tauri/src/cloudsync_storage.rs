@@ -0,0 +1,1759 @@
+//! CloudSync Storage - Pluggable remote storage backends
+//!
+//! All sync logic talks to a `CloudStorage` trait object, selected from
+//! `CloudSyncConfig.provider`:
+//! - "webdav" (and "other"): `WebDavClient` in cloudsync.rs
+//! - "local": a plain directory, e.g. a NAS mount or a folder synced by a
+//!   provider's desktop client
+//! - "s3": any S3-compatible object store (AWS, MinIO, Garage, ...)
+//!
+//! Paths are relative to the storage root and use '/' separators.
+//! A path ending in '/' denotes a collection (directory/prefix).
+
+use crate::cloudsync::{
+    decode_webdav_path, encode_webdav_path, normalize_remote_path, remote_file_path,
+    CloudFile, CloudSyncConfig, WebDavClient,
+};
+use crate::cloudsync_crypto::{load_passphrase, EncryptedStorage};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// A file or collection on the remote storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Modification time (unix seconds), if the backend reports one
+    pub modified: Option<u64>,
+    /// Opaque version tag (HTTP ETag), if the backend reports one
+    pub etag: Option<String>,
+}
+
+/// Progress callback for streamed transfers: (bytes transferred, total bytes)
+pub type Progress<'a> = &'a (dyn Fn(u64, u64) + Send + Sync);
+
+/// Operations every storage backend must support
+#[async_trait]
+pub trait CloudStorage: Send + Sync {
+    /// Check that the backend is reachable and writable
+    async fn test_connection(&self) -> Result<String, String>;
+
+    /// List the direct children of a collection (empty if it doesn't exist)
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String>;
+
+    /// List everything below a collection. Entry names are paths relative to
+    /// `path`. Backends that can list a whole tree in one request should override this.
+    async fn list_recursive(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        walk_collection(self, path).await
+    }
+
+    /// Get metadata for a single file (None if it doesn't exist)
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String>;
+
+    /// Upload content to a file, replacing it if it exists
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String>;
+
+    /// Download the content of a file
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String>;
+
+    /// Delete a file, or a collection and everything in it (missing paths are not an error)
+    async fn delete(&self, path: &str) -> Result<(), String>;
+
+    /// Create a collection and all missing parents
+    async fn mkdir(&self, path: &str) -> Result<(), String>;
+
+    /// Copy a file. Backends with server-side copy should override this.
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        let data = self.download(from).await?;
+        self.upload(to, data).await
+    }
+
+    /// Upload a local file, streaming it from disk. Backends that can't
+    /// stream fall back to reading the whole file into memory.
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let data = tokio::fs::read(local)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", local.display(), e))?;
+        let total = data.len() as u64;
+        self.upload(path, data).await?;
+        progress(total, total);
+        Ok(())
+    }
+
+    /// Download a file into `local`, streaming it to disk. If `local` already
+    /// holds the start of the file (an interrupted download), backends that
+    /// support ranged reads continue where it stopped.
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let data = self.download(path).await?;
+        let total = data.len() as u64;
+        tokio::fs::write(local, data)
+            .await
+            .map_err(|e| format!("Write failed: {}", e))?;
+        progress(total, total);
+        Ok(())
+    }
+
+    /// List the save files of a game with their metadata (names are relative paths)
+    async fn list_file_entries(&self, app_id: &str) -> Result<Vec<RemoteEntry>, String> {
+        let entries = self.list_recursive(&format!("boilerroom/{}", app_id)).await?;
+        Ok(entries.into_iter().filter(|e| !e.is_dir).collect())
+    }
+
+    /// List the save files of a game (relative paths; sync itself needs the
+    /// metadata of `list_file_entries`)
+    #[cfg(test)]
+    async fn list_files(&self, app_id: &str) -> Result<Vec<String>, String> {
+        let entries = self.list_file_entries(app_id).await?;
+        Ok(entries.into_iter().map(|e| e.name).collect())
+    }
+
+    /// Upload a save file of a game, streaming it from disk
+    async fn upload_file_from(
+        &self,
+        app_id: &str,
+        relative_path: &str,
+        local: &Path,
+        progress: Progress<'_>,
+    ) -> Result<(), String> {
+        let remote_path = remote_file_path(app_id, relative_path);
+        self.mkdir(parent_path(&remote_path)).await?;
+        self.upload_from(&remote_path, local, progress).await
+    }
+
+    /// Download a save file of a game, streaming it to disk
+    async fn download_file_to(
+        &self,
+        app_id: &str,
+        relative_path: &str,
+        local: &Path,
+        progress: Progress<'_>,
+    ) -> Result<(), String> {
+        self.download_to(&remote_file_path(app_id, relative_path), local, progress)
+            .await
+    }
+}
+
+/// Parent collection of a storage path ("" for top-level entries)
+pub fn parent_path(path: &str) -> &str {
+    path.trim_end_matches('/')
+        .rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("")
+}
+
+/// List a tree by listing one collection at a time
+pub async fn walk_collection<S: CloudStorage + ?Sized>(
+    storage: &S,
+    path: &str,
+) -> Result<Vec<RemoteEntry>, String> {
+    let root = path.trim_matches('/');
+    let mut entries = Vec::new();
+    let mut pending = vec![String::new()];
+
+    while let Some(relative) = pending.pop() {
+        let dir = if relative.is_empty() {
+            root.to_string()
+        } else {
+            format!("{}/{}", root, relative)
+        };
+
+        for mut entry in storage.list(&dir).await? {
+            if !relative.is_empty() {
+                entry.name = format!("{}/{}", relative, entry.name);
+            }
+            if entry.is_dir {
+                pending.push(entry.name.clone());
+            }
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+// ============================================================================
+// Streaming Helpers
+// ============================================================================
+
+/// Size of the chunks read from disk when streaming
+const STREAM_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Build an HTTP client for transfers. There is no overall timeout, so large
+/// saves can take as long as they need; only stalled connections time out.
+pub fn transfer_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .read_timeout(std::time::Duration::from_secs(60))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Open a local file as a streamed request body. Returns the body, the file
+/// size and a channel reporting how many bytes have been sent so far.
+pub async fn file_body(
+    local: &Path,
+) -> Result<(reqwest::Body, u64, UnboundedReceiver<u64>), String> {
+    let file = tokio::fs::File::open(local)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", local.display(), e))?;
+    let total = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", local.display(), e))?
+        .len();
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let stream = futures::stream::unfold((file, 0u64, tx), |(mut file, sent, tx)| async move {
+        let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buffer.truncate(n);
+                let sent = sent + n as u64;
+                let _ = tx.send(sent);
+                Some((Ok(buffer), (file, sent, tx)))
+            }
+            Err(e) => Some((Err(e), (file, sent, tx))),
+        }
+    });
+
+    Ok((reqwest::Body::wrap_stream(stream), total, rx))
+}
+
+/// Send a request with a streamed body, forwarding upload progress
+pub async fn send_with_progress(
+    request: reqwest::RequestBuilder,
+    mut sent: UnboundedReceiver<u64>,
+    total: u64,
+    progress: Progress<'_>,
+) -> reqwest::Result<reqwest::Response> {
+    let response = request.send();
+    tokio::pin!(response);
+
+    loop {
+        tokio::select! {
+            result = &mut response => return result,
+            Some(done) = sent.recv() => progress(done, total),
+        }
+    }
+}
+
+/// Size of a partial download already on disk (0 if there is none)
+pub async fn partial_length(local: &Path) -> u64 {
+    tokio::fs::metadata(local)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0)
+}
+
+/// Where the ETag of a partial download is kept, so that resuming it only
+/// continues the same version of the file (If-Range)
+fn partial_etag_path(local: &Path) -> PathBuf {
+    let mut name = local.as_os_str().to_owned();
+    name.push(".etag.boilerroom-tmp");
+    PathBuf::from(name)
+}
+
+/// Where to resume a partial download, and the ETag to send as If-Range
+/// with the Range request. A partial file without a recorded ETag can't be
+/// matched to a version of the remote file, so it starts over.
+pub async fn resume_point(local: &Path) -> (u64, Option<String>) {
+    let offset = partial_length(local).await;
+    if offset == 0 {
+        return (0, None);
+    }
+    match tokio::fs::read_to_string(partial_etag_path(local)).await {
+        Ok(etag) if !etag.is_empty() => (offset, Some(etag)),
+        _ => (0, None),
+    }
+}
+
+/// Remove a partial download and its recorded ETag
+pub async fn discard_partial(local: &Path) {
+    let _ = tokio::fs::remove_file(local).await;
+    let _ = tokio::fs::remove_file(partial_etag_path(local)).await;
+}
+
+/// Stream a response body into a local file. `offset` is where the body
+/// starts within the file: non-zero when resuming with a Range request.
+/// A fresh download records the response's ETag until it completes, for
+/// `resume_point`.
+pub async fn write_response_to_file(
+    response: reqwest::Response,
+    local: &Path,
+    offset: u64,
+    progress: Progress<'_>,
+) -> Result<(), String> {
+    let total = offset + response.content_length().unwrap_or(0);
+    let etag_path = partial_etag_path(local);
+    let mut file = if offset > 0 {
+        tokio::fs::OpenOptions::new().append(true).open(local).await
+    } else {
+        // If-Range only takes strong ETags
+        match response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .filter(|etag| !etag.starts_with("W/"))
+        {
+            Some(etag) => tokio::fs::write(&etag_path, etag).await,
+            None => match tokio::fs::remove_file(&etag_path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+        .map_err(|e| format!("Write failed: {}", e))?;
+        tokio::fs::File::create(local).await
+    }
+    .map_err(|e| format!("Write failed: {}", e))?;
+
+    let mut done = offset;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Download failed: {}", e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Write failed: {}", e))?;
+        done += chunk.len() as u64;
+        progress(done, total.max(done));
+    }
+
+    file.flush().await.map_err(|e| format!("Write failed: {}", e))?;
+    let _ = tokio::fs::remove_file(&etag_path).await;
+    Ok(())
+}
+
+/// Copy a local file in chunks, reporting progress
+async fn copy_with_progress(from: &Path, to: &Path, progress: Progress<'_>) -> Result<(), String> {
+    let mut source = tokio::fs::File::open(from).await.map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            "File not found on server".to_string()
+        } else {
+            format!("Failed to open {}: {}", from.display(), e)
+        }
+    })?;
+    let total = source
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?
+        .len();
+    let mut target = tokio::fs::File::create(to)
+        .await
+        .map_err(|e| format!("Write failed: {}", e))?;
+
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut done = 0u64;
+    loop {
+        let read = source
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        target
+            .write_all(&buffer[..read])
+            .await
+            .map_err(|e| format!("Write failed: {}", e))?;
+        done += read as u64;
+        progress(done, total);
+    }
+
+    target.flush().await.map_err(|e| format!("Write failed: {}", e))
+}
+
+/// Create the storage used for sync: the configured backend, wrapped in
+/// client-side encryption if enabled
+pub fn create_storage(config: &CloudSyncConfig) -> Result<Box<dyn CloudStorage>, String> {
+    let backend = create_backend(config)?;
+    if !config.encryption_enabled {
+        return Ok(backend);
+    }
+
+    let passphrase = load_passphrase().ok_or("Encryption is enabled but no passphrase is set")?;
+    Ok(Box::new(EncryptedStorage::new(backend, passphrase)))
+}
+
+/// Create the storage backend selected by the configuration
+pub fn create_backend(config: &CloudSyncConfig) -> Result<Box<dyn CloudStorage>, String> {
+    match config.provider.as_str() {
+        "" | "webdav" | "other" => {
+            if config.webdav_url.is_empty() {
+                return Err("WebDAV URL is required".to_string());
+            }
+            Ok(Box::new(WebDavClient::new(config)?))
+        }
+        "local" => {
+            if config.local_path.is_empty() {
+                return Err("Local folder path is required".to_string());
+            }
+            Ok(Box::new(LocalStorage::new(&config.local_path)?))
+        }
+        "s3" => Ok(Box::new(S3Storage::new(config)?)),
+        other => Err(format!(
+            "Provider '{}' is not supported yet - use WebDAV, a local/NAS folder or S3",
+            other
+        )),
+    }
+}
+
+// ============================================================================
+// Local Directory Backend
+// ============================================================================
+
+/// Storage backend writing to a local directory (NAS mount, synced folder, ...)
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: &str) -> Result<Self, String> {
+        let expanded = shellexpand::tilde(root).to_string();
+        Ok(Self {
+            root: PathBuf::from(expanded),
+        })
+    }
+
+    /// Map a storage path to a local path, refusing anything that escapes the root
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(path.trim_matches('/'));
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("Invalid storage path: {}", path));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+/// Convert file metadata into a remote entry
+fn entry_from_metadata(name: String, metadata: &std::fs::Metadata) -> RemoteEntry {
+    RemoteEntry {
+        name,
+        is_dir: metadata.is_dir(),
+        size: if metadata.is_dir() { 0 } else { metadata.len() },
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        etag: None,
+    }
+}
+
+#[async_trait]
+impl CloudStorage for LocalStorage {
+    async fn test_connection(&self) -> Result<String, String> {
+        if !self.root.is_dir() {
+            return Err(format!("Folder {} does not exist", self.root.display()));
+        }
+        self.mkdir("boilerroom").await?;
+        Ok("Connection successful".to_string())
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        let dir = self.resolve(path)?;
+        let mut read_dir = match tokio::fs::read_dir(&dir).await {
+            Ok(r) => r,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to list files: {}", e)),
+        };
+
+        let mut entries = Vec::new();
+        while let Some(entry) = read_dir
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to list files: {}", e))?
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Skip temp files from interrupted uploads
+            if name.ends_with(".boilerroom-tmp") {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata().await {
+                entries.push(entry_from_metadata(name, &metadata));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String> {
+        let local = self.resolve(path)?;
+        match tokio::fs::metadata(&local).await {
+            Ok(metadata) => {
+                let name = local
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Ok(Some(entry_from_metadata(name, &metadata)))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to check file: {}", e)),
+        }
+    }
+
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String> {
+        let local = self.resolve(path)?;
+        if let Some(parent) = local.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Upload failed: {}", e))?;
+        }
+
+        // Write next to the target and rename, so readers never see a partial file
+        let mut tmp_name = local.as_os_str().to_owned();
+        tmp_name.push(".boilerroom-tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        tokio::fs::write(&tmp_path, content)
+            .await
+            .map_err(|e| format!("Upload failed: {}", e))?;
+        tokio::fs::rename(&tmp_path, &local)
+            .await
+            .map_err(|e| format!("Upload failed: {}", e))
+    }
+
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String> {
+        match tokio::fs::read(self.resolve(path)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err("File not found on server".to_string())
+            }
+            Err(e) => Err(format!("Download failed: {}", e)),
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let local = self.resolve(path)?;
+        let result = match tokio::fs::metadata(&local).await {
+            Ok(m) if m.is_dir() => tokio::fs::remove_dir_all(&local).await,
+            Ok(_) => tokio::fs::remove_file(&local).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Delete failed: {}", e)),
+        }
+    }
+
+    async fn mkdir(&self, path: &str) -> Result<(), String> {
+        tokio::fs::create_dir_all(self.resolve(path)?)
+            .await
+            .map_err(|e| format!("Failed to create directory: {}", e))
+    }
+
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let target = self.resolve(path)?;
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Upload failed: {}", e))?;
+        }
+
+        let mut tmp_name = target.as_os_str().to_owned();
+        tmp_name.push(".boilerroom-tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        copy_with_progress(local, &tmp_path, progress).await?;
+        tokio::fs::rename(&tmp_path, &target)
+            .await
+            .map_err(|e| format!("Upload failed: {}", e))
+    }
+
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        copy_with_progress(&self.resolve(path)?, local, progress).await
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        let target = self.resolve(to)?;
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Copy failed: {}", e))?;
+        }
+        tokio::fs::copy(self.resolve(from)?, &target)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Copy failed: {}", e))
+    }
+}
+
+// ============================================================================
+// S3-compatible Backend
+// ============================================================================
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Storage backend for S3-compatible object stores (path-style addressing)
+pub struct S3Storage {
+    client: reqwest::Client,
+    endpoint: reqwest::Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+/// One page of a ListObjectsV2 response
+#[derive(Debug, Default, PartialEq)]
+pub struct ListObjectsPage {
+    pub objects: Vec<RemoteEntry>,
+    /// Full keys of the objects, in the same order as `objects`
+    pub keys: Vec<String>,
+    pub common_prefixes: Vec<String>,
+    pub next_continuation_token: Option<String>,
+}
+
+impl S3Storage {
+    pub fn new(config: &CloudSyncConfig) -> Result<Self, String> {
+        if config.s3_endpoint.is_empty() || config.s3_bucket.is_empty() {
+            return Err("S3 endpoint and bucket are required".to_string());
+        }
+
+        let endpoint = reqwest::Url::parse(config.s3_endpoint.trim_end_matches('/'))
+            .map_err(|e| format!("Invalid S3 endpoint: {}", e))?;
+        Ok(Self {
+            client: transfer_client()?,
+            endpoint,
+            bucket: config.s3_bucket.clone(),
+            region: if config.s3_region.is_empty() {
+                "us-east-1".to_string()
+            } else {
+                config.s3_region.clone()
+            },
+            access_key: config.s3_access_key.clone(),
+            secret_key: config.s3_secret_key.clone(),
+        })
+    }
+
+    /// Canonical (URI-encoded) path of an object below the endpoint's path
+    fn canonical_uri(&self, key: &str) -> String {
+        format!("{}{}", self.endpoint.path().trim_end_matches('/'), self.object_path(key))
+    }
+
+    /// URI-encoded /<bucket>/<key> of an object, as x-amz-copy-source names it
+    fn object_path(&self, key: &str) -> String {
+        let mut path = format!("/{}", uri_encode(&self.bucket));
+        for segment in key.split('/').filter(|s| !s.is_empty()) {
+            path.push('/');
+            path.push_str(&uri_encode(segment));
+        }
+        path
+    }
+
+    /// Send a signed request for an object key (empty key = bucket)
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        extra_headers: &[(&str, String)],
+    ) -> Result<reqwest::Response, String> {
+        let payload_hash = hex_sha256(&body);
+        self.signed_request(method, key, query, &payload_hash, extra_headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("S3 request failed: {}", e))
+    }
+
+    /// Build a signed request without a body. Streamed bodies can't be hashed
+    /// up front, so they are signed with `UNSIGNED-PAYLOAD`.
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(&str, &str)],
+        payload_hash: &str,
+        extra_headers: &[(&str, String)],
+    ) -> reqwest::RequestBuilder {
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let canonical_uri = self.canonical_uri(key);
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers: Vec<(String, String)> = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        for (name, value) in extra_headers {
+            headers.push((name.to_lowercase(), value.clone()));
+        }
+
+        let query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let authorization = sigv4_authorization(
+            &self.access_key,
+            &self.secret_key,
+            &self.region,
+            method.as_str(),
+            &canonical_uri,
+            &query,
+            &headers,
+            payload_hash,
+            &amz_date,
+        );
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&canonical_uri);
+        if !query.is_empty() {
+            url.set_query(Some(&canonical_query_string(&query)));
+        }
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("Authorization", authorization);
+        for (name, value) in &headers {
+            if name != "host" {
+                request = request.header(name.as_str(), value.as_str());
+            }
+        }
+
+        request
+    }
+
+    /// Fetch one page of a ListObjectsV2 listing
+    async fn list_page(
+        &self,
+        prefix: &str,
+        delimiter: Option<&str>,
+        continuation_token: Option<&str>,
+    ) -> Result<ListObjectsPage, String> {
+        let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+        if let Some(d) = delimiter {
+            query.push(("delimiter", d));
+        }
+        if let Some(token) = continuation_token {
+            query.push(("continuation-token", token));
+        }
+
+        let response = self
+            .send(reqwest::Method::GET, "", &query, Vec::new(), &[])
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("Failed to list files: status {}", response.status()));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        parse_list_objects(&body, prefix)
+    }
+
+    /// Collect every key under a prefix (recursively)
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>, String> {
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let page = self.list_page(prefix, None, token.as_deref()).await?;
+            keys.extend(page.keys);
+            match page.next_continuation_token {
+                Some(t) => token = Some(t),
+                None => break,
+            }
+        }
+
+        Ok(keys)
+    }
+
+    /// Upload a large file in parts. Finished parts are recorded on disk, so
+    /// an interrupted upload continues with the next missing part.
+    async fn upload_multipart(
+        &self,
+        key: &str,
+        local: &Path,
+        total: u64,
+        progress: Progress<'_>,
+    ) -> Result<(), String> {
+        let modified = std::fs::metadata(local)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let state_path = multipart_state_path(&self.bucket, key)?;
+
+        // Only resume if the file is unchanged since the upload started
+        let saved = std::fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<MultipartUpload>(&content).ok())
+            .filter(|u| {
+                u.key == key
+                    && u.size == total
+                    && u.modified == modified
+                    && u.part_size == MULTIPART_PART_SIZE
+            });
+        let mut upload = match saved {
+            Some(upload) => {
+                eprintln!(
+                    "[CloudSync] Resuming upload of {} ({} parts done)",
+                    key,
+                    upload.parts.len()
+                );
+                upload
+            }
+            None => MultipartUpload {
+                key: key.to_string(),
+                upload_id: self.create_multipart_upload(key).await?,
+                size: total,
+                modified,
+                part_size: MULTIPART_PART_SIZE,
+                parts: Vec::new(),
+            },
+        };
+        save_multipart_state(&state_path, &upload)?;
+
+        let mut file = tokio::fs::File::open(local)
+            .await
+            .map_err(|e| format!("Failed to open {}: {}", local.display(), e))?;
+        let part_count = total.div_ceil(MULTIPART_PART_SIZE);
+        let mut done: u64 = upload
+            .parts
+            .iter()
+            .map(|p| MULTIPART_PART_SIZE.min(total - (p.number - 1) * MULTIPART_PART_SIZE))
+            .sum();
+        progress(done, total);
+
+        for number in 1..=part_count {
+            if upload.parts.iter().any(|p| p.number == number) {
+                continue;
+            }
+
+            let offset = (number - 1) * MULTIPART_PART_SIZE;
+            let length = MULTIPART_PART_SIZE.min(total - offset);
+            let mut buffer = vec![0u8; length as usize];
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", local.display(), e))?;
+            file.read_exact(&mut buffer)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", local.display(), e))?;
+
+            let part_number = number.to_string();
+            let response = self
+                .send(
+                    reqwest::Method::PUT,
+                    key,
+                    &[("partNumber", &part_number), ("uploadId", &upload.upload_id)],
+                    buffer,
+                    &[],
+                )
+                .await?;
+            if response.status().as_u16() == 404 {
+                // The upload was aborted or expired on the server; start over next time
+                let _ = std::fs::remove_file(&state_path);
+                return Err("Upload failed: multipart upload no longer exists".to_string());
+            }
+            if !response.status().is_success() {
+                return Err(format!("Upload failed: status {}", response.status()));
+            }
+            let etag = response
+                .headers()
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .ok_or("Upload failed: part response has no ETag")?
+                .to_string();
+
+            upload.parts.push(UploadedPart { number, etag });
+            save_multipart_state(&state_path, &upload)?;
+            done += length;
+            progress(done, total);
+        }
+
+        self.complete_multipart_upload(&upload).await?;
+        let _ = std::fs::remove_file(&state_path);
+        Ok(())
+    }
+
+    /// Start a multipart upload and return its upload ID
+    async fn create_multipart_upload(&self, key: &str) -> Result<String, String> {
+        let response = self
+            .send(reqwest::Method::POST, key, &[("uploads", "")], Vec::new(), &[])
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("Upload failed: status {}", response.status()));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+        xml_element_text(&body, "UploadId")
+            .ok_or_else(|| "Upload failed: no upload ID in response".to_string())
+    }
+
+    /// Assemble the uploaded parts into the final object
+    async fn complete_multipart_upload(&self, upload: &MultipartUpload) -> Result<(), String> {
+        let mut parts = upload.parts.clone();
+        parts.sort_by_key(|p| p.number);
+
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for part in &parts {
+            body.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                part.number,
+                quick_xml::escape::escape(part.etag.as_str())
+            ));
+        }
+        body.push_str("</CompleteMultipartUpload>");
+
+        let response = self
+            .send(
+                reqwest::Method::POST,
+                &upload.key,
+                &[("uploadId", &upload.upload_id)],
+                body.into_bytes(),
+                &[],
+            )
+            .await?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?;
+
+        // S3 can report a failed completion inside a 200 response
+        if !status.is_success() || text.contains("<Error>") {
+            let code = xml_element_text(&text, "Code").unwrap_or_else(|| status.to_string());
+            return Err(format!("Upload failed: {}", code));
+        }
+        Ok(())
+    }
+}
+
+/// Files at least this large are uploaded to S3 in parts
+const MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Size of each part of a multipart upload
+const MULTIPART_PART_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Progress of a multipart upload, persisted so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MultipartUpload {
+    key: String,
+    upload_id: String,
+    size: u64,
+    modified: u64,
+    part_size: u64,
+    parts: Vec<UploadedPart>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UploadedPart {
+    number: u64,
+    etag: String,
+}
+
+/// File recording a multipart upload in progress
+/// (~/.local/share/boilerroom/cloudsync/uploads/<hash>.json)
+fn multipart_state_path(bucket: &str, key: &str) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let dir = home.join(".local/share/boilerroom/cloudsync/uploads");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create upload state dir: {}", e))?;
+    let id = hex_sha256(format!("{}/{}", bucket, key).as_bytes());
+    Ok(dir.join(format!("{}.json", &id[..16])))
+}
+
+fn save_multipart_state(path: &Path, upload: &MultipartUpload) -> Result<(), String> {
+    let content = serde_json::to_string(upload)
+        .map_err(|e| format!("Failed to serialize upload state: {}", e))?;
+    std::fs::write(path, content).map_err(|e| format!("Failed to save upload state: {}", e))
+}
+
+/// Text of the first element with the given local name
+fn xml_element_text(xml: &str, element: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut inside = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => inside = e.local_name().as_ref() == element.as_bytes(),
+            Ok(Event::Text(t)) if inside => {
+                return t.unescape().ok().map(|s| s.trim().to_string());
+            }
+            Ok(Event::End(_)) => inside = false,
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl CloudStorage for S3Storage {
+    async fn test_connection(&self) -> Result<String, String> {
+        let response = self
+            .send(reqwest::Method::HEAD, "", &[], Vec::new(), &[])
+            .await
+            .map_err(|e| format!("Connection failed: {}", e))?;
+
+        match response.status().as_u16() {
+            200..=299 => Ok("Connection successful".to_string()),
+            403 => Err("Access forbidden - check access key/secret".to_string()),
+            404 => Err(format!("Bucket '{}' not found", self.bucket)),
+            status => Err(format!("Server returned status {}", status)),
+        }
+    }
+
+    async fn list(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        let prefix = format!("{}/", path.trim_matches('/'));
+        let mut entries = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let page = self.list_page(&prefix, Some("/"), token.as_deref()).await?;
+            entries.extend(page.objects);
+            entries.extend(page.common_prefixes.iter().map(|p| RemoteEntry {
+                name: p[prefix.len().min(p.len())..].trim_end_matches('/').to_string(),
+                is_dir: true,
+                size: 0,
+                modified: None,
+                etag: None,
+            }));
+            match page.next_continuation_token {
+                Some(t) => token = Some(t),
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn list_recursive(&self, path: &str) -> Result<Vec<RemoteEntry>, String> {
+        // Without a delimiter S3 returns every key under the prefix; names are
+        // already relative to it. Prefixes aren't objects, so no directory entries.
+        let prefix = format!("{}/", path.trim_matches('/'));
+        let mut entries = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let page = self.list_page(&prefix, None, token.as_deref()).await?;
+            entries.extend(page.objects);
+            match page.next_continuation_token {
+                Some(t) => token = Some(t),
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<RemoteEntry>, String> {
+        let response = self
+            .send(reqwest::Method::HEAD, path, &[], Vec::new(), &[])
+            .await?;
+
+        if response.status().as_u16() == 404 {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("Failed to check file: status {}", response.status()));
+        }
+
+        let modified = response
+            .headers()
+            .get("last-modified")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| chrono::DateTime::parse_from_rfc2822(s).ok())
+            .map(|dt| dt.timestamp() as u64);
+
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.trim_matches('"').to_string());
+
+        Ok(Some(RemoteEntry {
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            is_dir: false,
+            size: response.content_length().unwrap_or(0),
+            modified,
+            etag,
+        }))
+    }
+
+    async fn upload(&self, path: &str, content: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .send(reqwest::Method::PUT, path, &[], content, &[])
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Upload failed: status {}", response.status()))
+        }
+    }
+
+    async fn upload_from(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let total = tokio::fs::metadata(local)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", local.display(), e))?
+            .len();
+        if total >= MULTIPART_THRESHOLD {
+            return self.upload_multipart(path, local, total, progress).await;
+        }
+
+        let (body, total, sent) = file_body(local).await?;
+        let request = self
+            .signed_request(reqwest::Method::PUT, path, &[], "UNSIGNED-PAYLOAD", &[])
+            .header("Content-Length", total)
+            .body(body);
+        let response = send_with_progress(request, sent, total, progress)
+            .await
+            .map_err(|e| format!("Upload failed: {}", e))?;
+
+        if response.status().is_success() {
+            progress(total, total);
+            Ok(())
+        } else {
+            Err(format!("Upload failed: status {}", response.status()))
+        }
+    }
+
+    async fn download_to(&self, path: &str, local: &Path, progress: Progress<'_>) -> Result<(), String> {
+        let (offset, etag) = resume_point(local).await;
+        let mut headers = Vec::new();
+        if let Some(etag) = etag {
+            headers.push(("Range", format!("bytes={}-", offset)));
+            headers.push(("If-Range", etag));
+        }
+        let response = self
+            .send(reqwest::Method::GET, path, &[], Vec::new(), &headers)
+            .await?;
+
+        match response.status().as_u16() {
+            206 => write_response_to_file(response, local, offset, progress).await,
+            // The file changed since the partial download (or the Range
+            // header was ignored): the whole file is sent again
+            200..=299 => write_response_to_file(response, local, 0, progress).await,
+            404 => Err("File not found on server".to_string()),
+            416 => {
+                // The partial file doesn't fit the remote one; start over
+                discard_partial(local).await;
+                self.download_to(path, local, progress).await
+            }
+            status => Err(format!("Download failed: status {}", status)),
+        }
+    }
+
+    async fn download(&self, path: &str) -> Result<Vec<u8>, String> {
+        let response = self
+            .send(reqwest::Method::GET, path, &[], Vec::new(), &[])
+            .await?;
+
+        if response.status().is_success() {
+            response
+                .bytes()
+                .await
+                .map(|b| b.to_vec())
+                .map_err(|e| format!("Failed to read response: {}", e))
+        } else if response.status().as_u16() == 404 {
+            Err("File not found on server".to_string())
+        } else {
+            Err(format!("Download failed: status {}", response.status()))
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        // S3 has no directories: deleting a collection means deleting every key under it
+        let keys = if path.ends_with('/') {
+            self.list_keys(path.trim_start_matches('/')).await?
+        } else {
+            vec![path.to_string()]
+        };
+
+        for key in keys {
+            let response = self
+                .send(reqwest::Method::DELETE, &key, &[], Vec::new(), &[])
+                .await?;
+            match response.status().as_u16() {
+                200..=299 | 404 => {}
+                status => return Err(format!("Delete failed: status {}", status)),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn mkdir(&self, _path: &str) -> Result<(), String> {
+        // Prefixes exist implicitly
+        Ok(())
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), String> {
+        let source = self.object_path(from);
+        let response = self
+            .send(
+                reqwest::Method::PUT,
+                to,
+                &[],
+                Vec::new(),
+                &[("x-amz-copy-source", source)],
+            )
+            .await?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Copy failed: status {}", response.status()))
+        }
+    }
+}
+
+/// URI-encode a string per SigV4 rules (everything except unreserved characters)
+fn uri_encode(s: &str) -> String {
+    urlencoding::encode(s).into_owned()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Build the canonical query string: sorted by key, keys and values URI-encoded
+fn canonical_query_string(query: &[(String, String)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k), uri_encode(v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Derive the SigV4 signing key for a date (YYYYMMDD), region and service
+pub fn sigv4_signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// Build the AWS Signature Version 4 Authorization header for an S3 request.
+/// `headers` must use lowercase names.
+#[allow(clippy::too_many_arguments)]
+pub fn sigv4_authorization(
+    access_key: &str,
+    secret_key: &str,
+    region: &str,
+    method: &str,
+    canonical_uri: &str,
+    query: &[(String, String)],
+    headers: &[(String, String)],
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    let mut sorted_headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), v.trim().to_string()))
+        .collect();
+    sorted_headers.sort();
+
+    let canonical_headers: String = sorted_headers
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect();
+    let signed_headers = sorted_headers
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_query_string(query),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex_sha256(canonical_request.as_bytes())
+    );
+
+    let signing_key = sigv4_signing_key(secret_key, date, region, "s3");
+    let signature = hmac_sha256(&signing_key, string_to_sign.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    )
+}
+
+/// Parse a ListObjectsV2 XML response. Object names are relative to `prefix`.
+pub fn parse_list_objects(xml: &str, prefix: &str) -> Result<ListObjectsPage, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut page = ListObjectsPage::default();
+    let mut path: Vec<String> = Vec::new();
+    let mut is_truncated = false;
+
+    let mut key = String::new();
+    let mut size = 0u64;
+    let mut modified: Option<u64> = None;
+    let mut etag: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if name == "Contents" {
+                    key.clear();
+                    size = 0;
+                    modified = None;
+                    etag = None;
+                }
+                path.push(name);
+            }
+            Ok(Event::End(_)) => {
+                let closed = path.pop();
+                if closed.as_deref() == Some("Contents") && !key.is_empty() {
+                    let name = key.strip_prefix(prefix).unwrap_or(&key).to_string();
+                    page.objects.push(RemoteEntry {
+                        name,
+                        is_dir: false,
+                        size,
+                        modified,
+                        etag: etag.clone(),
+                    });
+                    page.keys.push(key.clone());
+                }
+            }
+            Ok(Event::Text(t)) => {
+                let text = t
+                    .unescape()
+                    .map_err(|e| format!("Invalid S3 listing: {}", e))?
+                    .to_string();
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match (parent, path.last().map(|s| s.as_str())) {
+                    (Some("Contents"), Some("Key")) => key = text,
+                    (Some("Contents"), Some("Size")) => size = text.parse().unwrap_or(0),
+                    (Some("Contents"), Some("LastModified")) => {
+                        modified = DateTime::parse_from_rfc3339(&text)
+                            .ok()
+                            .map(|dt| dt.timestamp() as u64);
+                    }
+                    (Some("Contents"), Some("ETag")) => {
+                        etag = Some(text.trim_matches('"').to_string())
+                    }
+                    (Some("CommonPrefixes"), Some("Prefix")) => page.common_prefixes.push(text),
+                    (_, Some("IsTruncated")) => is_truncated = text == "true",
+                    (_, Some("NextContinuationToken")) => {
+                        page.next_continuation_token = Some(text)
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Invalid S3 listing: {}", e)),
+            _ => {}
+        }
+    }
+
+    if !is_truncated {
+        page.next_continuation_token = None;
+    }
+
+    Ok(page)
+}
+
+// ============================================================================
+// Layout Migration
+// ============================================================================
+
+/// Outcome of migrating flattened uploads to the directory layout
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayoutMigrationResult {
+    pub games_checked: u32,
+    pub files_migrated: u32,
+    /// Local sync states from before accounts were separated handed to the account
+    #[serde(default)]
+    pub states_adopted: u32,
+    pub errors: Vec<String>,
+}
+
+/// Work out the real path of a file uploaded under the old flattened layout.
+///
+/// `known_paths` maps flattened names to the game's real save paths, so names
+/// that genuinely contain `__` are kept intact; anything else is decoded.
+/// Returns None if the file doesn't need to move.
+pub fn unflatten_remote_name(name: &str, known_paths: &HashMap<String, String>) -> Option<String> {
+    let path = match known_paths.get(name) {
+        Some(path) => normalize_remote_path(path),
+        None => decode_webdav_path(name),
+    };
+
+    if path == name {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Move a game's flattened uploads (`saves__slot1.sav`) to the directory layout
+/// (`saves/slot1.sav`). `known_paths` are the game's save paths, used to tell
+/// real `__` in file names apart from encoded separators.
+/// Returns the number of files moved.
+pub async fn migrate_flat_layout(
+    storage: &dyn CloudStorage,
+    app_id: &str,
+    known_paths: &[String],
+) -> Result<u32, String> {
+    let known: HashMap<String, String> = known_paths
+        .iter()
+        .map(|p| (encode_webdav_path(p), p.clone()))
+        .collect();
+
+    // The flat layout only ever stored files directly in the game's collection
+    let app_dir = format!("boilerroom/{}", app_id);
+    let mut moved = 0;
+
+    for entry in storage.list(&app_dir).await? {
+        if entry.is_dir {
+            continue;
+        }
+        let target = match unflatten_remote_name(&entry.name, &known) {
+            Some(t) => t,
+            None => continue,
+        };
+
+        let source = format!("{}/{}", app_dir, entry.name);
+        let target = remote_file_path(app_id, &target);
+
+        // A file already at the new location was uploaded later, so it wins
+        if storage.stat(&target).await?.is_none() {
+            storage.mkdir(parent_path(&target)).await?;
+            storage.copy(&source, &target).await?;
+        }
+        storage.delete(&source).await?;
+        moved += 1;
+    }
+
+    if moved > 0 {
+        eprintln!("[CloudSync] Migrated {} files of app {} to the directory layout", moved, app_id);
+    }
+
+    Ok(moved)
+}
+
+/// Move a game's files on roots other than 0 from their bare path, where
+/// they were uploaded before roots were kept apart, to their key
+/// (`.root<N>/<path>`). `files` are the game's save files by key; a bare path
+/// that a root 0 file has too is left to that file.
+/// Returns the files moved, as (old key, new key).
+pub async fn migrate_root_layout(
+    storage: &dyn CloudStorage,
+    app_id: &str,
+    files: &HashMap<String, CloudFile>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut moved = Vec::new();
+
+    for (key, file) in files {
+        if file.root <= 0 || files.contains_key(&file.path) {
+            continue;
+        }
+        let source = remote_file_path(app_id, &file.path);
+        if storage.stat(&source).await?.is_none() {
+            continue;
+        }
+
+        // A file already at the new location was uploaded later, so it wins
+        let target = remote_file_path(app_id, key);
+        if storage.stat(&target).await?.is_none() {
+            storage.mkdir(parent_path(&target)).await?;
+            storage.copy(&source, &target).await?;
+        }
+        storage.delete(&source).await?;
+        moved.push((file.path.clone(), key.clone()));
+    }
+
+    if !moved.is_empty() {
+        eprintln!(
+            "[CloudSync] Moved {} files of app {} into their root's directory",
+            moved.len(),
+            app_id
+        );
+    }
+
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sigv4_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = sigv4_signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_s3_object_paths() {
+        let config = CloudSyncConfig {
+            s3_endpoint: "https://nas.example:9000/s3/".to_string(),
+            s3_bucket: "saves".to_string(),
+            ..Default::default()
+        };
+        let storage = S3Storage::new(&config).unwrap();
+        let key = "boilerroom/730/saves/slot 1+2.sav";
+
+        // The copy source never carries the endpoint's path
+        assert_eq!(storage.object_path(key), "/saves/boilerroom/730/saves/slot%201%2B2.sav");
+        assert_eq!(
+            storage.canonical_uri(key),
+            "/s3/saves/boilerroom/730/saves/slot%201%2B2.sav"
+        );
+        assert_eq!(storage.canonical_uri(""), "/s3/saves");
+    }
+
+    #[test]
+    fn test_canonical_query_string() {
+        let query = vec![
+            ("prefix".to_string(), "boilerroom/730/".to_string()),
+            ("list-type".to_string(), "2".to_string()),
+        ];
+        assert_eq!(
+            canonical_query_string(&query),
+            "list-type=2&prefix=boilerroom%2F730%2F"
+        );
+    }
+
+    #[test]
+    fn test_parse_list_objects() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>saves</Name>
+  <Prefix>boilerroom/730/</Prefix>
+  <IsTruncated>true</IsTruncated>
+  <NextContinuationToken>abc</NextContinuationToken>
+  <Contents>
+    <Key>boilerroom/730/slot1.sav</Key>
+    <LastModified>2026-03-14T15:09:26.000Z</LastModified>
+    <ETag>&quot;9b2cf535f27731c974343645a3985328&quot;</ETag>
+    <Size>1024</Size>
+  </Contents>
+  <CommonPrefixes><Prefix>boilerroom/730/profiles/</Prefix></CommonPrefixes>
+</ListBucketResult>"#;
+
+        let page = parse_list_objects(xml, "boilerroom/730/").unwrap();
+        assert_eq!(page.objects.len(), 1);
+        assert_eq!(page.objects[0].name, "slot1.sav");
+        assert_eq!(page.objects[0].size, 1024);
+        assert_eq!(page.objects[0].modified, Some(1773500966));
+        assert_eq!(
+            page.objects[0].etag.as_deref(),
+            Some("9b2cf535f27731c974343645a3985328")
+        );
+        assert_eq!(page.keys, vec!["boilerroom/730/slot1.sav".to_string()]);
+        assert_eq!(page.common_prefixes, vec!["boilerroom/730/profiles/".to_string()]);
+        assert_eq!(page.next_continuation_token.as_deref(), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(&dir.path().to_string_lossy()).unwrap();
+
+        let path = remote_file_path("730", "saves/slot1.sav");
+        storage.mkdir(parent_path(&path)).await.unwrap();
+        storage.upload(&path, b"save data".to_vec()).await.unwrap();
+        assert_eq!(storage.list_files("730").await.unwrap(), vec!["saves/slot1.sav"]);
+        assert_eq!(storage.download(&path).await.unwrap(), b"save data");
+
+        let stat = storage
+            .stat(&remote_file_path("730", "saves/slot1.sav"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stat.size, 9);
+
+        storage.delete("boilerroom/730/").await.unwrap();
+        assert!(storage.list_files("730").await.unwrap().is_empty());
+        assert!(storage.resolve("../outside").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resume_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("slot1.sav.boilerroom-part");
+        assert_eq!(resume_point(&local).await, (0, None));
+
+        // Without the ETag of the version it came from, a partial file starts over
+        std::fs::write(&local, b"save").unwrap();
+        assert_eq!(resume_point(&local).await, (0, None));
+
+        std::fs::write(partial_etag_path(&local), "\"9b2cf535\"").unwrap();
+        assert_eq!(
+            resume_point(&local).await,
+            (4, Some("\"9b2cf535\"".to_string()))
+        );
+
+        discard_partial(&local).await;
+        assert!(!local.exists());
+        assert!(!partial_etag_path(&local).exists());
+    }
+
+    #[test]
+    fn test_unflatten_remote_name() {
+        let known: HashMap<String, String> = [("my__save.sav", "my__save.sav")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        assert_eq!(
+            unflatten_remote_name("saves__slot1.sav", &known),
+            Some("saves/slot1.sav".to_string())
+        );
+        assert_eq!(unflatten_remote_name("my__save.sav", &known), None);
+        assert_eq!(unflatten_remote_name("plain.sav", &known), None);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_flat_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(&dir.path().to_string_lossy()).unwrap();
+
+        storage.upload("boilerroom/730/saves__slot1.sav", b"old".to_vec()).await.unwrap();
+        storage.upload("boilerroom/730/saves__slot2.sav", b"old".to_vec()).await.unwrap();
+        storage.upload("boilerroom/730/saves/slot2.sav", b"new".to_vec()).await.unwrap();
+        storage.upload("boilerroom/730/my__save.sav", b"keep".to_vec()).await.unwrap();
+
+        let moved = migrate_flat_layout(&storage, "730", &["my__save.sav".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(moved, 2);
+
+        let mut files = storage.list_files("730").await.unwrap();
+        files.sort();
+        assert_eq!(files, vec!["my__save.sav", "saves/slot1.sav", "saves/slot2.sav"]);
+        assert_eq!(storage.download("boilerroom/730/saves/slot1.sav").await.unwrap(), b"old");
+        assert_eq!(storage.download("boilerroom/730/saves/slot2.sav").await.unwrap(), b"new");
+    }
+
+    #[tokio::test]
+    async fn test_migrate_root_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(&dir.path().to_string_lossy()).unwrap();
+        storage.upload("boilerroom/730/Saves/a.sav", b"root 12".to_vec()).await.unwrap();
+        storage.upload("boilerroom/730/shared.sav", b"root 0".to_vec()).await.unwrap();
+
+        let file = |root: i32, path: &str| CloudFile {
+            path: path.to_string(),
+            root,
+            size: 0,
+            localtime: 0,
+            remotetime: 0,
+            sha: String::new(),
+            syncstate: 0,
+            resolved_path: None,
+        };
+        let files: HashMap<String, CloudFile> =
+            [file(12, "Saves/a.sav"), file(0, "shared.sav"), file(12, "shared.sav")]
+                .into_iter()
+                .map(|f| (crate::cloudsync::file_key(f.root, &f.path), f))
+                .collect();
+
+        let moved = migrate_root_layout(&storage, "730", &files).await.unwrap();
+        assert_eq!(moved, vec![("Saves/a.sav".to_string(), ".root12/Saves/a.sav".to_string())]);
+
+        let mut remote = storage.list_files("730").await.unwrap();
+        remote.sort();
+        // The root 0 file keeps its path
+        assert_eq!(remote, vec![".root12/Saves/a.sav", "shared.sav"]);
+        assert_eq!(storage.download("boilerroom/730/shared.sav").await.unwrap(), b"root 0");
+    }
+
+    /// Runs against a real S3-compatible server, e.g. a local MinIO:
+    /// BOILERROOM_S3_TEST_ENDPOINT=http://127.0.0.1:9000 BOILERROOM_S3_TEST_BUCKET=test
+    /// BOILERROOM_S3_TEST_ACCESS_KEY=minioadmin BOILERROOM_S3_TEST_SECRET_KEY=minioadmin
+    /// cargo test -- --ignored s3_storage
+    #[tokio::test]
+    #[ignore]
+    async fn test_s3_storage_roundtrip() {
+        let env = |name: &str| std::env::var(name).unwrap_or_default();
+        let config = CloudSyncConfig {
+            provider: "s3".to_string(),
+            s3_endpoint: env("BOILERROOM_S3_TEST_ENDPOINT"),
+            s3_bucket: env("BOILERROOM_S3_TEST_BUCKET"),
+            s3_access_key: env("BOILERROOM_S3_TEST_ACCESS_KEY"),
+            s3_secret_key: env("BOILERROOM_S3_TEST_SECRET_KEY"),
+            ..Default::default()
+        };
+        let storage = create_storage(&config).unwrap();
+
+        storage.test_connection().await.unwrap();
+        storage
+            .upload(&remote_file_path("730", "saves/slot 1.sav"), b"save data".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(storage.list_files("730").await.unwrap(), vec!["saves/slot 1.sav"]);
+        storage
+            .copy(
+                &remote_file_path("730", "saves/slot 1.sav"),
+                "boilerroom/.snapshots/730/copy.sav",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.download("boilerroom/.snapshots/730/copy.sav").await.unwrap(),
+            b"save data"
+        );
+
+        storage.delete("boilerroom/730/").await.unwrap();
+        storage.delete("boilerroom/.snapshots/730/").await.unwrap();
+        assert!(storage.list_files("730").await.unwrap().is_empty());
+    }
+}
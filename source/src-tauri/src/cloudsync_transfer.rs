@@ -0,0 +1,523 @@
+//! CloudSync Transfers - Streamed save transfers with retries and progress
+//!
+//! Wraps the streaming `CloudStorage` transfers with:
+//! - Retry with exponential backoff on transient failures (network errors,
+//!   stalled connections, 5xx/408/429 responses)
+//! - Throttled "cloudsync-progress" events for the frontend
+//! - Downloads into a `<file>.boilerroom-part` file next to the save, which is
+//!   resumed by the retry and only renamed into place once complete and
+//!   verified; the file it replaces goes to the local rollback area first
+//! - Deletions that keep a copy: local saves go to the rollback area, remote
+//!   ones to boilerroom/.trash/<app_id>/<trash_id>/ unless deleted outright
+
+use crate::cloudsync::{normalize_remote_path, remote_file_path};
+use crate::cloudsync_rollback::{replace_file, RollbackSession};
+use crate::cloudsync_snapshots;
+use crate::cloudsync_state::{self, FileDigest};
+use crate::cloudsync_storage::{parent_path, partial_length, CloudStorage};
+use serde::Serialize;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Event emitted while a save file is transferred
+pub const PROGRESS_EVENT: &str = "cloudsync-progress";
+
+/// Attempts made for a transfer before giving up
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; doubled for every further attempt
+const BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound for the delay between retries
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Minimum time between two progress events for the same file
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Progress of a single file transfer
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub app_id: String,
+    pub file_path: String,
+    /// "upload" or "download"
+    pub direction: String,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+// ============================================================================
+// Retry
+// ============================================================================
+
+/// Whether a failed transfer is worth retrying
+pub fn is_transient_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+
+    if let Some(pos) = error.find("status ") {
+        let code: String = error[pos + 7..].chars().take(3).collect();
+        if let Ok(code) = code.parse::<u16>() {
+            return code >= 500 || code == 408 || code == 429;
+        }
+    }
+
+    [
+        "timed out",
+        "timeout",
+        "error sending request",
+        "connection",
+        "broken pipe",
+        "error decoding response body",
+        "multipart upload no longer exists",
+    ]
+    .iter()
+    .any(|pattern| error.contains(pattern))
+}
+
+/// Delay before retry number `attempt` (1-based)
+pub fn backoff_delay(attempt: u32) -> Duration {
+    BASE_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_DELAY)
+}
+
+/// Run an operation, retrying transient failures with exponential backoff
+pub async fn with_retry<T, F, Fut>(what: &str, mut operation: F) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < MAX_ATTEMPTS && is_transient_error(&e) => {
+                let delay = backoff_delay(attempt);
+                eprintln!(
+                    "[CloudSync] {} failed ({}), retrying in {}s ({}/{})",
+                    what,
+                    e,
+                    delay.as_secs(),
+                    attempt,
+                    MAX_ATTEMPTS - 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// ============================================================================
+// Progress Events
+// ============================================================================
+
+/// Emits throttled progress events for one file
+struct ProgressEmitter<'a> {
+    app_handle: Option<&'a AppHandle>,
+    app_id: &'a str,
+    file_path: &'a str,
+    direction: &'static str,
+    last_emit: Mutex<Option<Instant>>,
+}
+
+impl<'a> ProgressEmitter<'a> {
+    fn new(
+        app_handle: Option<&'a AppHandle>,
+        app_id: &'a str,
+        file_path: &'a str,
+        direction: &'static str,
+    ) -> Self {
+        Self {
+            app_handle,
+            app_id,
+            file_path,
+            direction,
+            last_emit: Mutex::new(None),
+        }
+    }
+
+    fn report(&self, bytes_done: u64, bytes_total: u64) {
+        let app_handle = match self.app_handle {
+            Some(h) => h,
+            None => return,
+        };
+
+        // Always send the final event, otherwise at most one per interval
+        {
+            let mut last_emit = self.last_emit.lock().unwrap();
+            let due = last_emit.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL);
+            if bytes_done < bytes_total && !due {
+                return;
+            }
+            *last_emit = Some(Instant::now());
+        }
+
+        let _ = app_handle.emit(
+            PROGRESS_EVENT,
+            TransferProgress {
+                app_id: self.app_id.to_string(),
+                file_path: self.file_path.to_string(),
+                direction: self.direction.to_string(),
+                bytes_done,
+                bytes_total,
+            },
+        );
+    }
+}
+
+// ============================================================================
+// Transfers
+// ============================================================================
+
+/// Upload a save file from disk
+pub async fn upload_save_file(
+    storage: &dyn CloudStorage,
+    app_handle: Option<&AppHandle>,
+    app_id: &str,
+    file_path: &str,
+    local_path: &Path,
+) -> Result<(), String> {
+    let emitter = ProgressEmitter::new(app_handle, app_id, file_path, "upload");
+    let progress = |done: u64, total: u64| emitter.report(done, total);
+
+    with_retry(&format!("Upload of {}", file_path), || {
+        storage.upload_file_from(app_id, file_path, local_path, &progress)
+    })
+    .await
+}
+
+/// Path of the partial download kept next to a save file
+pub fn part_path(local_path: &Path) -> PathBuf {
+    let mut name = local_path.as_os_str().to_owned();
+    name.push(".boilerroom-part");
+    PathBuf::from(name)
+}
+
+/// Check that a finished download has the size the listing reported. A short
+/// file means the connection closed early; the retry resumes it.
+async fn verify_size(part: &Path, expected_size: Option<u64>) -> Result<(), String> {
+    let expected = match expected_size {
+        Some(size) => size,
+        None => return Ok(()),
+    };
+    let actual = partial_length(part).await;
+
+    if actual < expected {
+        Err(format!(
+            "Download incomplete: connection closed after {} of {} bytes",
+            actual, expected
+        ))
+    } else if actual > expected {
+        // Left over from an older version of the file; start over
+        let _ = tokio::fs::remove_file(part).await;
+        Err(format!(
+            "Download failed: got {} bytes, expected {}",
+            actual, expected
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Download a save file into its part file and return the part path and the
+/// digest of what was downloaded. Use `commit_part` to move it into place.
+pub async fn download_to_part(
+    storage: &dyn CloudStorage,
+    app_handle: Option<&AppHandle>,
+    app_id: &str,
+    file_path: &str,
+    local_path: &Path,
+    expected_size: Option<u64>,
+) -> Result<(PathBuf, FileDigest), String> {
+    if let Some(parent) = local_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let part = part_path(local_path);
+    let emitter = ProgressEmitter::new(app_handle, app_id, file_path, "download");
+    let progress = |done: u64, total: u64| emitter.report(done, total);
+    let (part_ref, progress_ref) = (&part, &progress);
+
+    with_retry(&format!("Download of {}", file_path), || async move {
+        storage
+            .download_file_to(app_id, file_path, part_ref, progress_ref)
+            .await?;
+        verify_size(part_ref, expected_size).await
+    })
+    .await?;
+
+    let digest = cloudsync_state::hash_file(&part)?;
+    Ok((part, digest))
+}
+
+/// Move a completed download into place, backing up the file it replaces
+pub fn commit_part(
+    part: &Path,
+    local_path: &Path,
+    file_path: &str,
+    rollback: Option<&RollbackSession>,
+) -> Result<(), String> {
+    if let Some(rollback) = rollback {
+        rollback.backup(file_path, local_path)?;
+    }
+    replace_file(part, local_path)
+}
+
+/// Download a save file to disk. When the expected digest is known the
+/// download is verified against its size and hash before it replaces the
+/// local file; a mismatch (e.g. a stale part file that was resumed) is
+/// retried once from scratch.
+pub async fn download_save_file(
+    storage: &dyn CloudStorage,
+    app_handle: Option<&AppHandle>,
+    app_id: &str,
+    file_path: &str,
+    local_path: &Path,
+    expected: Option<&FileDigest>,
+    rollback: Option<&RollbackSession>,
+) -> Result<FileDigest, String> {
+    let mut fresh = false;
+    loop {
+        let (part, digest) = download_to_part(
+            storage,
+            app_handle,
+            app_id,
+            file_path,
+            local_path,
+            expected.map(|d| d.size),
+        )
+        .await?;
+
+        match expected {
+            Some(expected) if expected != &digest => {
+                let _ = std::fs::remove_file(&part);
+                if fresh {
+                    return Err(format!(
+                        "Downloaded {} does not match the remote index",
+                        file_path
+                    ));
+                }
+                fresh = true;
+            }
+            _ => {
+                commit_part(&part, local_path, file_path, rollback)?;
+                return Ok(digest);
+            }
+        }
+    }
+}
+
+/// Download a file of a remote snapshot to its local save path. The download
+/// only replaces the local file if it has the size and (when known) the SHA1
+/// the snapshot recorded.
+#[allow(clippy::too_many_arguments)]
+pub async fn download_snapshot_file(
+    storage: &dyn CloudStorage,
+    app_handle: Option<&AppHandle>,
+    app_id: &str,
+    snapshot_id: &str,
+    file_path: &str,
+    local_path: &Path,
+    expected: Option<&FileDigest>,
+    rollback: Option<&RollbackSession>,
+) -> Result<FileDigest, String> {
+    if let Some(parent) = local_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let part = part_path(local_path);
+    let emitter = ProgressEmitter::new(app_handle, app_id, file_path, "download");
+    let progress = |done: u64, total: u64| emitter.report(done, total);
+    let (part_ref, progress_ref) = (&part, &progress);
+
+    with_retry(&format!("Download of {}", file_path), || async move {
+        cloudsync_snapshots::download_snapshot_file(
+            storage,
+            app_id,
+            snapshot_id,
+            file_path,
+            part_ref,
+            progress_ref,
+        )
+        .await?;
+        verify_size(part_ref, expected.map(|d| d.size)).await
+    })
+    .await?;
+
+    let digest = cloudsync_state::hash_file(&part)?;
+    if expected.is_some_and(|d| !d.sha.is_empty() && d.sha != digest.sha) {
+        let _ = std::fs::remove_file(&part);
+        return Err(format!(
+            "Downloaded {} does not match snapshot {}",
+            file_path, snapshot_id
+        ));
+    }
+    commit_part(&part, local_path, file_path, rollback)?;
+    Ok(digest)
+}
+
+// ============================================================================
+// Deletions
+// ============================================================================
+
+/// Delete a remote save file. With a trash ID it's copied into
+/// boilerroom/.trash/<app_id>/<trash_id>/ first.
+pub async fn delete_remote_save(
+    storage: &dyn CloudStorage,
+    app_id: &str,
+    file_path: &str,
+    trash_id: Option<&str>,
+) -> Result<(), String> {
+    let remote_path = remote_file_path(app_id, file_path);
+
+    if let Some(trash_id) = trash_id {
+        let target = format!(
+            "boilerroom/.trash/{}/{}/{}",
+            app_id,
+            trash_id,
+            normalize_remote_path(file_path)
+        );
+        storage.mkdir(parent_path(&target)).await?;
+        with_retry(&format!("Trashing of {}", file_path), || {
+            storage.copy(&remote_path, &target)
+        })
+        .await?;
+    }
+
+    with_retry(&format!("Deletion of {}", file_path), || storage.delete(&remote_path)).await
+}
+
+/// Delete a local save file, backing it up into the rollback point first
+pub fn delete_local_save(local_path: &Path, file_path: &str, rollback: &RollbackSession) -> Result<(), String> {
+    rollback.backup(file_path, local_path)?;
+    std::fs::remove_file(local_path)
+        .map_err(|e| format!("Failed to delete {}: {}", local_path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsync_storage::LocalStorage;
+
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error("Upload failed: status 503 Service Unavailable"));
+        assert!(is_transient_error("Download failed: status 429"));
+        assert!(is_transient_error("Upload failed: error sending request for url"));
+        assert!(is_transient_error("Download failed: operation timed out"));
+        assert!(!is_transient_error("Upload failed: status 403 Forbidden"));
+        assert!(!is_transient_error("File not found on server"));
+        assert!(!is_transient_error("Write failed: No space left on device"));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(1), Duration::from_secs(1));
+        assert_eq!(backoff_delay(2), Duration::from_secs(2));
+        assert_eq!(backoff_delay(4), Duration::from_secs(8));
+        assert_eq!(backoff_delay(10), MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_verify_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("slot1.sav.boilerroom-part");
+        std::fs::write(&part, b"12345").unwrap();
+
+        assert!(verify_size(&part, None).await.is_ok());
+        assert!(verify_size(&part, Some(5)).await.is_ok());
+
+        // Short downloads are retried (and resumed)
+        let short = verify_size(&part, Some(10)).await.unwrap_err();
+        assert!(is_transient_error(&short));
+        assert!(part.exists());
+
+        // Oversized part files are stale and get discarded
+        assert!(verify_size(&part, Some(3)).await.is_err());
+        assert!(!part.exists());
+    }
+
+    #[tokio::test]
+    async fn test_streamed_roundtrip() {
+        let remote = tempfile::tempdir().unwrap();
+        let local = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(remote.path().to_str().unwrap()).unwrap();
+
+        // Larger than one stream chunk, so progress is reported more than once
+        let content: Vec<u8> = (0..600_000u32).map(|i| (i % 251) as u8).collect();
+        let source = local.path().join("slot1.sav");
+        std::fs::write(&source, &content).unwrap();
+
+        let reports = Mutex::new(Vec::new());
+        let progress = |done: u64, total: u64| reports.lock().unwrap().push((done, total));
+        storage
+            .upload_file_from("730", "saves/slot1.sav", &source, &progress)
+            .await
+            .unwrap();
+        let reports = reports.into_inner().unwrap();
+        assert!(reports.len() > 1);
+        assert_eq!(reports.last(), Some(&(600_000, 600_000)));
+
+        let target = local.path().join("restored/slot1.sav");
+        let expected = cloudsync_state::hash_file(&source).unwrap();
+        let digest = download_save_file(&storage, None, "730", "saves/slot1.sav", &target, Some(&expected), None)
+            .await
+            .unwrap();
+        assert_eq!(digest, expected);
+        assert_eq!(std::fs::read(&target).unwrap(), content);
+        assert!(!part_path(&target).exists());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_download_is_verified() {
+        let remote = tempfile::tempdir().unwrap();
+        let local = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(remote.path().to_str().unwrap()).unwrap();
+        let snapshot_file = "boilerroom/.snapshots/730/20260101T000000Z/saves/slot1.sav";
+        storage.upload(snapshot_file, b"bad save".to_vec()).await.unwrap();
+
+        let target = local.path().join("slot1.sav");
+        std::fs::write(&target, b"live save").unwrap();
+        std::fs::write(local.path().join("good.sav"), b"old save").unwrap();
+        let expected = cloudsync_state::hash_file(&local.path().join("good.sav")).unwrap();
+
+        // Same size, different content: the live save stays
+        let err = download_snapshot_file(
+            &storage,
+            None,
+            "730",
+            "20260101T000000Z",
+            "saves/slot1.sav",
+            &target,
+            Some(&expected),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("does not match snapshot"));
+        assert_eq!(std::fs::read(&target).unwrap(), b"live save");
+        assert!(!part_path(&target).exists());
+
+        storage.upload(snapshot_file, b"old save".to_vec()).await.unwrap();
+        let digest = download_snapshot_file(
+            &storage,
+            None,
+            "730",
+            "20260101T000000Z",
+            "saves/slot1.sav",
+            &target,
+            Some(&expected),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(digest, expected);
+        assert_eq!(std::fs::read(&target).unwrap(), b"old save");
+    }
+}
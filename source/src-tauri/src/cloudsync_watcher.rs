@@ -1,6 +1,7 @@
 //! CloudSync File Watcher
 //!
-//! Watches remotecache.vdf files for changes and triggers sync operations.
+//! Watches remotecache.vdf files for changes and reports them to the sync
+//! worker (see `cloudsync_worker`).
 //! Uses the `notify` crate for cross-platform file system monitoring.
 
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
@@ -8,7 +9,7 @@ use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 /// Message sent when a remotecache.vdf file changes
 #[derive(Debug, Clone)]
@@ -45,13 +46,9 @@ impl CloudSyncWatcher {
     /// Start watching for file changes
     pub fn start(&mut self, app_ids: Vec<String>) -> Result<(), String> {
         let sender = self.event_sender.clone();
-        let debounce_duration = Duration::from_secs(2);
 
-        // Create a debouncing event handler
-        let last_events: Arc<Mutex<std::collections::HashMap<String, Instant>>> =
-            Arc::new(Mutex::new(std::collections::HashMap::new()));
-
-        let last_events_clone = last_events.clone();
+        // Every change is forwarded; bursts are coalesced by the sync worker,
+        // which has to see the last write of a burst, not just the first
         let event_handler = move |res: Result<Event, notify::Error>| {
             if let Ok(event) = res {
                 // Only care about modify and create events
@@ -65,30 +62,11 @@ impl CloudSyncWatcher {
                 for path in event.paths {
                     // Extract app_id from path
                     if let Some(app_id) = extract_app_id_from_path(&path) {
-                        // Debounce: skip if we've seen this app_id recently
-                        let now = Instant::now();
-                        let should_send = {
-                            let mut last = last_events_clone.lock().unwrap();
-                            if let Some(last_time) = last.get(&app_id) {
-                                if now.duration_since(*last_time) < debounce_duration {
-                                    false
-                                } else {
-                                    last.insert(app_id.clone(), now);
-                                    true
-                                }
-                            } else {
-                                last.insert(app_id.clone(), now);
-                                true
-                            }
-                        };
-
-                        if should_send {
-                            let _ = sender.send(CloudSyncEvent {
-                                app_id,
-                                path: path.clone(),
-                                timestamp: now,
-                            });
-                        }
+                        let _ = sender.send(CloudSyncEvent {
+                            app_id,
+                            path: path.clone(),
+                            timestamp: Instant::now(),
+                        });
                     }
                 }
             }
@@ -168,46 +146,12 @@ fn extract_app_id_from_path(path: &PathBuf) -> Option<String> {
     }
 }
 
-/// Find remotecache.vdf files for given app_ids
+/// Find remotecache.vdf files of the active Steam account for given app_ids
 fn find_remotecache_paths(app_ids: &[String]) -> Vec<(String, PathBuf)> {
-    let mut results = Vec::new();
-
-    let home = match dirs::home_dir() {
-        Some(h) => h,
-        None => return results,
-    };
-
-    let steam_path = if cfg!(target_os = "macos") {
-        home.join("Library/Application Support/Steam")
-    } else {
-        home.join(".local/share/Steam")
-    };
-
-    let userdata_path = steam_path.join("userdata");
-
-    if !userdata_path.exists() {
-        return results;
+    match crate::cloudsync::get_steam_user_id() {
+        Some(account_id) => crate::cloudsync::find_remotecache_files(&account_id, app_ids),
+        None => Vec::new(),
     }
-
-    // Iterate through user directories
-    if let Ok(user_dirs) = std::fs::read_dir(&userdata_path) {
-        for user_entry in user_dirs.flatten() {
-            if !user_entry.path().is_dir() {
-                continue;
-            }
-
-            // Check each app_id directory
-            for app_id in app_ids {
-                let remotecache = user_entry.path().join(app_id).join("remotecache.vdf");
-
-                if remotecache.exists() {
-                    results.push((app_id.clone(), remotecache));
-                }
-            }
-        }
-    }
-
-    results
 }
 
 /// Global watcher instance (managed by Tauri state)
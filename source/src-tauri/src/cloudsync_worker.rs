@@ -0,0 +1,310 @@
+//! CloudSync Worker - Background syncs triggered by the file watcher
+//!
+//! The watcher reports every change it sees; games usually write their cloud
+//! files in bursts, so changes are queued per app_id and only synced once the
+//! game has been quiet for a while. A single worker task owns the queue and
+//! starts the syncs, with a limit on how many run at once and never two for
+//! the same game.
+//!
+//! Syncs of a running game are deferred; the session tracker queues them
+//! again when the game exits.
+//!
+//! The frontend is told about each queued sync through `cloudsync-sync-status`
+//! events; per-file progress comes from the regular `cloudsync-progress` events.
+
+use crate::cloudsync::SyncResult;
+use crate::cloudsync_session::GameSessionTracker;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{mpsc, Semaphore};
+
+pub const SYNC_STATUS_EVENT: &str = "cloudsync-sync-status";
+
+/// How long a game must stop touching its files before a sync starts
+const QUIET_PERIOD: Duration = Duration::from_secs(5);
+
+/// Longest a sync is held back by a game that keeps writing
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Syncs allowed to run at the same time
+const MAX_CONCURRENT_SYNCS: usize = 2;
+
+/// Payload of `cloudsync-sync-status` events
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatusEvent {
+    pub app_id: String,
+    /// "queued", "deferred" (game running), "started", "finished" or "failed"
+    pub status: String,
+    /// Set when the status is "finished"
+    pub result: Option<SyncResult>,
+    /// Set when the status is "failed"
+    pub error: Option<String>,
+}
+
+enum WorkerMessage {
+    /// Files of a game changed
+    Changed(String),
+    /// A sync started by the worker is done
+    Finished(String),
+}
+
+// ============================================================================
+// Queue
+// ============================================================================
+
+/// A game waiting for its sync
+struct PendingSync {
+    first_change: Instant,
+    due: Instant,
+}
+
+/// Per-game coalescing of change notifications
+struct SyncQueue {
+    quiet_period: Duration,
+    max_delay: Duration,
+    pending: HashMap<String, PendingSync>,
+    running: HashSet<String>,
+    /// Games that changed while their sync was running
+    rerun: HashSet<String>,
+}
+
+impl SyncQueue {
+    fn new(quiet_period: Duration, max_delay: Duration) -> Self {
+        Self {
+            quiet_period,
+            max_delay,
+            pending: HashMap::new(),
+            running: HashSet::new(),
+            rerun: HashSet::new(),
+        }
+    }
+
+    /// Record a change. Returns true if this queued a new sync for the game.
+    fn request(&mut self, app_id: &str, now: Instant) -> bool {
+        if self.running.contains(app_id) {
+            return self.rerun.insert(app_id.to_string());
+        }
+
+        match self.pending.get_mut(app_id) {
+            Some(pending) => {
+                // Every change pushes the sync back, up to MAX_DELAY after the first one
+                pending.due = (now + self.quiet_period).min(pending.first_change + self.max_delay);
+                false
+            }
+            None => {
+                self.pending.insert(
+                    app_id.to_string(),
+                    PendingSync {
+                        first_change: now,
+                        due: now + self.quiet_period,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    /// When the next pending sync becomes due
+    fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|p| p.due).min()
+    }
+
+    /// Take the games whose sync is due, marking them as running
+    fn take_due(&mut self, now: Instant) -> Vec<String> {
+        let mut due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.due <= now)
+            .map(|(app_id, _)| app_id.clone())
+            .collect();
+        due.sort();
+
+        for app_id in &due {
+            self.pending.remove(app_id);
+            self.running.insert(app_id.clone());
+        }
+        due
+    }
+
+    /// Mark a game's sync as done, queueing another one if it changed meanwhile
+    fn finish(&mut self, app_id: &str, now: Instant) {
+        self.running.remove(app_id);
+        if self.rerun.remove(app_id) {
+            self.request(app_id, now);
+        }
+    }
+}
+
+// ============================================================================
+// Worker
+// ============================================================================
+
+/// Handle to the background sync worker (managed by Tauri state)
+#[derive(Clone)]
+pub struct CloudSyncWorker {
+    sender: mpsc::UnboundedSender<WorkerMessage>,
+}
+
+impl CloudSyncWorker {
+    /// Start the worker task
+    pub fn spawn(app_handle: AppHandle) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(run_worker(app_handle, sender.clone(), receiver));
+        Self { sender }
+    }
+
+    /// Ask for a sync of a game whose files changed
+    pub fn request_sync(&self, app_id: &str) {
+        if self.sender.send(WorkerMessage::Changed(app_id.to_string())).is_err() {
+            eprintln!("[CloudSync] Sync worker is not running, dropping change for {}", app_id);
+        }
+    }
+}
+
+fn emit_status(
+    app_handle: &AppHandle,
+    app_id: &str,
+    status: &str,
+    result: Option<SyncResult>,
+    error: Option<String>,
+) {
+    let _ = app_handle.emit(
+        SYNC_STATUS_EVENT,
+        SyncStatusEvent {
+            app_id: app_id.to_string(),
+            status: status.to_string(),
+            result,
+            error,
+        },
+    );
+}
+
+async fn run_worker(
+    app_handle: AppHandle,
+    sender: mpsc::UnboundedSender<WorkerMessage>,
+    mut receiver: mpsc::UnboundedReceiver<WorkerMessage>,
+) {
+    let mut queue = SyncQueue::new(QUIET_PERIOD, MAX_DELAY);
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_SYNCS));
+
+    loop {
+        // Nothing pending: sleep until the next message
+        let wake_at = queue
+            .next_due()
+            .unwrap_or_else(|| Instant::now() + Duration::from_secs(3600));
+
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Some(WorkerMessage::Changed(app_id)) => {
+                    if queue.request(&app_id, Instant::now()) {
+                        emit_status(&app_handle, &app_id, "queued", None, None);
+                    }
+                }
+                Some(WorkerMessage::Finished(app_id)) => queue.finish(&app_id, Instant::now()),
+                None => break,
+            },
+            _ = tokio::time::sleep_until(tokio::time::Instant::from_std(wake_at)) => {}
+        }
+
+        for app_id in queue.take_due(Instant::now()) {
+            let app_handle = app_handle.clone();
+            let sender = sender.clone();
+            let permits = permits.clone();
+
+            tauri::async_runtime::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+
+                // The session tracker queues another sync once the game exits
+                let running = app_handle
+                    .try_state::<GameSessionTracker>()
+                    .is_some_and(|tracker| tracker.is_running(&app_id));
+                if running {
+                    eprintln!("[CloudSync] Game {} is running, deferring background sync", app_id);
+                    emit_status(&app_handle, &app_id, "deferred", None, None);
+                    let _ = sender.send(WorkerMessage::Finished(app_id));
+                    return;
+                }
+
+                emit_status(&app_handle, &app_id, "started", None, None);
+                eprintln!("[CloudSync] Background sync started for {}", app_id);
+
+                match crate::commands::sync_game_cloud_saves(app_id.clone(), None, None, None, app_handle.clone()).await {
+                    Ok(result) => {
+                        eprintln!("[CloudSync] Background sync for {}: {}", app_id, result.message);
+                        emit_status(&app_handle, &app_id, "finished", Some(result), None);
+                    }
+                    Err(e) => {
+                        eprintln!("[CloudSync] Background sync failed for {}: {}", app_id, e);
+                        emit_status(&app_handle, &app_id, "failed", None, Some(e));
+                    }
+                }
+
+                let _ = sender.send(WorkerMessage::Finished(app_id));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> SyncQueue {
+        SyncQueue::new(Duration::from_secs(5), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_burst_is_coalesced() {
+        let mut queue = queue();
+        let start = Instant::now();
+
+        assert!(queue.request("730", start));
+        assert!(!queue.request("730", start + Duration::from_secs(2)));
+        assert!(queue.request("440", start + Duration::from_secs(3)));
+
+        // The second change pushed 730 back to t=7
+        assert!(queue.take_due(start + Duration::from_secs(6)).is_empty());
+        assert_eq!(queue.next_due(), Some(start + Duration::from_secs(7)));
+        assert_eq!(
+            queue.take_due(start + Duration::from_secs(8)),
+            vec!["440".to_string(), "730".to_string()]
+        );
+        assert_eq!(queue.next_due(), None);
+    }
+
+    #[test]
+    fn test_constant_writes_are_capped() {
+        let mut queue = queue();
+        let start = Instant::now();
+
+        for secs in (0..=70).step_by(2) {
+            queue.request("730", start + Duration::from_secs(secs));
+        }
+        assert_eq!(queue.next_due(), Some(start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_change_during_sync_reruns_once() {
+        let mut queue = queue();
+        let start = Instant::now();
+
+        queue.request("730", start);
+        assert_eq!(queue.take_due(start + Duration::from_secs(5)), vec!["730".to_string()]);
+
+        // Changes while running don't start a second sync
+        assert!(queue.request("730", start + Duration::from_secs(6)));
+        assert!(!queue.request("730", start + Duration::from_secs(7)));
+        assert!(queue.take_due(start + Duration::from_secs(20)).is_empty());
+
+        queue.finish("730", start + Duration::from_secs(20));
+        assert_eq!(queue.next_due(), Some(start + Duration::from_secs(25)));
+
+        // Without changes, finishing leaves nothing queued
+        assert_eq!(queue.take_due(start + Duration::from_secs(25)), vec!["730".to_string()]);
+        queue.finish("730", start + Duration::from_secs(30));
+        assert_eq!(queue.next_due(), None);
+    }
+}
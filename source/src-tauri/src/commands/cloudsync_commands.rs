@@ -4,18 +4,41 @@
 //! - Managing CloudSync configuration
 //! - Getting sync status for games
 //! - Triggering manual sync operations
+//! - Exporting and importing save archives
 
 use crate::cloudsync::{
-    parse_remotecache_vdf, resolve_cloud_file_path, CloudSyncConfig, CloudStatus,
-    GameCloudStatus, GlobalCloudStatus, SyncResult, WebDavClient,
+    find_remotecache_files, parse_remotecache_vdf, resolve_cloud_file_path, BulkSyncResult,
+    CloudSyncConfig, CloudStatus, DeletionMode, GameCloudStatus, GameFileRules, GameSyncOutcome,
+    GlobalCloudStatus, SyncMode, SyncResult,
 };
+use crate::cloudsync_accounts::{self, AccountStorage, SteamAccount};
+use crate::cloudsync_archive::{self, ArchiveFormat, ArchiveManifest, ArchivedFile};
+use crate::cloudsync_storage::{
+    create_backend, create_storage, migrate_flat_layout, migrate_root_layout, CloudStorage,
+    LayoutMigrationResult, RemoteEntry,
+};
+use crate::cloudsync_crypto::{self, EncryptedStorage};
+use crate::cloudsync_history::{self, ActiveSync, GameSyncStanding, SyncRun};
+use crate::cloudsync_registry;
+use crate::cloudsync_rollback::{self, RollbackInfo, RollbackSession};
+use crate::cloudsync_roots::{self, GameLocation};
+use crate::cloudsync_scan;
+use crate::cloudsync_session::{self, GameSessionTracker};
+use crate::cloudsync_snapshots::{self, SnapshotInfo};
+use crate::cloudsync_state::{
+    self, ConflictResolution, FileDigest, PlannedFile, RemoteIndex, RemoteVersion, SyncAction, Tombstone,
+};
+use crate::cloudsync_transfer;
 use crate::cloudsync_watcher::CloudSyncWatcherState;
-use std::path::PathBuf;
+use crate::cloudsync_worker::CloudSyncWorker;
+use futures::stream::{self, StreamExt};
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 use tauri_plugin_store::StoreExt;
 use walkdir::WalkDir;
 use crate::pcgamingwiki;
-use crate::cloudsync::{get_steam_user_id, CloudFile};
+use crate::pcgamingwiki_metadata;
+use crate::cloudsync::CloudFile;
 use std::collections::HashMap;
 
 // ============================================================================
@@ -59,18 +82,14 @@ pub async fn get_cloudsync_config(
     Ok(config)
 }
 
-/// Test WebDAV connection with provided configuration
+/// Test the storage backend connection with provided configuration
 #[tauri::command]
 pub async fn test_cloudsync_connection(config: CloudSyncConfig) -> Result<String, String> {
     if !config.enabled {
         return Err("CloudSync is not enabled".to_string());
     }
 
-    if config.webdav_url.is_empty() {
-        return Err("WebDAV URL is required".to_string());
-    }
-
-    let client = WebDavClient::new(&config)?;
+    let client = create_storage(&config)?;
     client.test_connection().await
 }
 
@@ -78,188 +97,494 @@ pub async fn test_cloudsync_connection(config: CloudSyncConfig) -> Result<String
 // Status Commands
 // ============================================================================
 
-/// Get cloud sync status for a specific game
+/// Get cloud sync status for a specific game, for the given Steam account
+/// (the active one if omitted). The status comes from the same per-file
+/// comparison a sync makes, and lists the files a sync would move.
 #[tauri::command]
 pub async fn get_game_cloud_status(
     app_id: String,
+    account_id: Option<String>,
     app_handle: tauri::AppHandle,
 ) -> Result<GameCloudStatus, String> {
+    let mut status = GameCloudStatus {
+        app_id: app_id.clone(),
+        status: CloudStatus::None,
+        last_sync: None,
+        pending_files: None,
+        error_message: None,
+        source: "none".to_string(),
+        files_to_upload: Vec::new(),
+        files_to_download: Vec::new(),
+    };
+
     // Check if CloudSync is enabled
     let config = get_cloudsync_config(app_handle.clone()).await?;
     let config = match config {
         Some(c) if c.enabled => c,
-        _ => {
-            return Ok(GameCloudStatus {
-                app_id,
-                status: CloudStatus::None,
-                last_sync: None,
-                pending_files: None,
-                error_message: None,
-                source: "none".to_string(),
-            });
+        _ => return Ok(status),
+    };
+
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    status.last_sync = cloudsync_history::last_sync(&account_id, &app_id);
+
+    let mut game_files = match collect_game_files(&app_id, &account_id, &config, false).await {
+        Ok(g) => g,
+        Err(e) => {
+            status.error_message = Some(e);
+            return Ok(status);
         }
     };
+    status.source = if game_files.is_fallback { "pcgamingwiki" } else { "steam_cloud" }.to_string();
+
+    let plan = match plan_game_sync(&app_id, &account_id, &config, &mut game_files).await {
+        Ok(p) => p,
+        Err(e) => {
+            status.status = CloudStatus::Error;
+            status.error_message = Some(e);
+            return Ok(status);
+        }
+    };
+    if plan.is_empty() {
+        // PCGamingWiki saves missing locally may still be on the remote,
+        // so only now is it known there are none
+        if game_files.is_fallback {
+            status.error_message =
+                Some("No cloud save data found (checked Steam & PCGamingWiki)".to_string());
+        }
+        return Ok(status);
+    }
+
+    // Unresolved conflicts of the last sync count until they're resolved
+    let mut conflicts = cloudsync_state::load_sync_state(&account_id, &app_id).conflicts;
+    let mut deletions = 0;
+    for file in plan {
+        match file.action {
+            SyncAction::Upload => status.files_to_upload.push(file.path),
+            SyncAction::Download => status.files_to_download.push(file.path),
+            SyncAction::DeleteLocal | SyncAction::DeleteRemote => deletions += 1,
+            SyncAction::Conflict if !conflicts.contains(&file.path) => conflicts.push(file.path),
+            _ => {}
+        }
+    }
 
-    // Find remotecache.vdf for this game
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let steam_path = if cfg!(target_os = "macos") {
-        home.join("Library/Application Support/Steam")
+    if !conflicts.is_empty() {
+        status.status = CloudStatus::Conflict;
+        status.pending_files = Some(conflicts.len() as u32);
+        status.error_message = Some(format!(
+            "{} files changed both locally and remotely",
+            conflicts.len()
+        ));
     } else {
-        home.join(".local/share/Steam")
+        let pending = status.files_to_upload.len() + status.files_to_download.len() + deletions;
+        status.status = if pending == 0 { CloudStatus::Synced } else { CloudStatus::Pending };
+        status.pending_files = Some(pending as u32);
+    }
+
+    Ok(status)
+}
+
+/// Get global cloud sync status, aggregated from the sync history and
+/// conflicts of the active account's games
+#[tauri::command]
+pub async fn get_global_cloud_status(
+    app_handle: tauri::AppHandle,
+) -> Result<GlobalCloudStatus, String> {
+    let config = get_cloudsync_config(app_handle.clone()).await?;
+
+    let mut status = GlobalCloudStatus {
+        enabled: config.is_some_and(|c| c.enabled),
+        is_syncing: cloudsync_history::is_syncing(),
+        games_synced: 0,
+        games_pending: 0,
+        games_with_conflicts: 0,
+        last_sync: None,
     };
+    let account_id = match cloudsync_accounts::active_account_id() {
+        Some(id) if status.enabled => id,
+        _ => return Ok(status),
+    };
+
+    let mut app_ids = cloudsync_history::history_games(&account_id);
+    for app_id in cloudsync_state::state_games(&account_id) {
+        if !app_ids.contains(&app_id) {
+            app_ids.push(app_id);
+        }
+    }
 
-    let userdata_path = steam_path.join("userdata");
-    let mut remotecache_path: Option<PathBuf> = None;
+    for app_id in app_ids {
+        let last_run = cloudsync_history::load_history(&account_id, &app_id).into_iter().next();
+        let conflicts = cloudsync_state::load_sync_state(&account_id, &app_id).conflicts.len();
+
+        match cloudsync_history::game_standing(last_run.as_ref(), conflicts) {
+            GameSyncStanding::Synced => status.games_synced += 1,
+            GameSyncStanding::Pending => status.games_pending += 1,
+            GameSyncStanding::Conflict => status.games_with_conflicts += 1,
+        }
 
-    // Find the remotecache.vdf file for this app
-    if let Ok(user_dirs) = std::fs::read_dir(&userdata_path) {
-        for user_entry in user_dirs.flatten() {
-            let candidate = user_entry.path().join(&app_id).join("remotecache.vdf");
-            if candidate.exists() {
-                remotecache_path = Some(candidate);
-                break;
+        // RFC 3339 timestamps in UTC compare chronologically as strings
+        if let Some(run) = last_run {
+            if status.last_sync.as_ref().is_none_or(|last| run.finished_at > *last) {
+                status.last_sync = Some(run.finished_at);
             }
         }
     }
 
-    let remotecache_path = match remotecache_path {
-        Some(p) => p,
-        None => {
-            // Attempt PCGamingWiki fallback
-            let steam_id = get_steam_user_id();
-            let save_roots = pcgamingwiki::find_save_locations(&app_id, steam_id.as_deref())
-                .await
-                .ok();
-
-            if let Some(roots) = save_roots {
-                if !roots.is_empty() {
-                    let mut local_count = 0;
-                    
-                    // Count files in these roots
-                    for root in roots {
-                        if root.exists() {
-                             local_count += WalkDir::new(root)
-                                .into_iter()
-                                .filter_map(|e| e.ok())
-                                .filter(|e| e.file_type().is_file())
-                                .count();
-                        }
-                    }
+    Ok(status)
+}
 
-                    // Check remote status
-                    let client = WebDavClient::new(&config)?;
-                    let remote_files = client.list_files(&app_id).await.unwrap_or_default();
-                    let remote_count = remote_files.len();
+/// Get the sync runs of a game, newest first
+#[tauri::command]
+pub async fn get_cloud_sync_history(
+    app_id: String,
+    account_id: Option<String>,
+) -> Result<Vec<SyncRun>, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    Ok(cloudsync_history::load_history(&account_id, &app_id))
+}
 
-                    let status = if remote_count == 0 && local_count > 0 {
-                        CloudStatus::Pending
-                    } else if remote_count == local_count {
-                        CloudStatus::Synced
-                    } else {
-                        CloudStatus::Pending
-                    };
+/// List the Steam accounts on this machine (the active one has `most_recent`)
+#[tauri::command]
+pub async fn list_steam_accounts() -> Result<Vec<SteamAccount>, String> {
+    Ok(cloudsync_accounts::list_accounts())
+}
 
-                   return Ok(GameCloudStatus {
-                        app_id,
-                        status,
-                        last_sync: None,
-                        pending_files: Some((local_count.abs_diff(remote_count)) as u32),
-                        error_message: None, 
-                        source: "pcgamingwiki".to_string(),
-                    });
-                }
-            }
+// ============================================================================
+// Sync Commands
+// ============================================================================
 
-            return Ok(GameCloudStatus {
-                app_id,
-                status: CloudStatus::None,
-                last_sync: None,
-                pending_files: None,
-                error_message: Some("No cloud save data found (checked Steam & PCGamingWiki)".to_string()),
-                source: "none".to_string(),
-            });
-        }
-    };
+/// The set of save files tracked for a game
+struct GameFiles {
+    files: HashMap<String, CloudFile>,
+    user_id: String,
+    /// Install directory and Proton prefix, for resolving cloud roots
+    location: GameLocation,
+    /// True when files came from PCGamingWiki (absolute `resolved_path`s)
+    is_fallback: bool,
+    /// Save and config locations of a PCGamingWiki game, for files missing on disk
+    save_roots: Vec<PathBuf>,
+    config_roots: Vec<PathBuf>,
+}
 
-    // Parse the remotecache.vdf
-    let content = std::fs::read_to_string(&remotecache_path)
-        .map_err(|e| format!("Failed to read remotecache.vdf: {}", e))?;
+/// Collect the save files of a game for an account from remotecache.vdf
+/// (plus the on-disk tree when the scan is enabled), falling back to PCGamingWiki.
+/// With `export_registry`, registry saves are exported from the prefix first;
+/// only runs that act on the files ask for it.
+async fn collect_game_files(
+    app_id: &str,
+    account_id: &str,
+    config: &CloudSyncConfig,
+    export_registry: bool,
+) -> Result<GameFiles, String> {
+    let user_dir = cloudsync_roots::steam_root()
+        .ok_or("Could not find home directory")?
+        .join("userdata")
+        .join(account_id);
 
-    let files = parse_remotecache_vdf(&content)?;
+    let scan = cloudsync_scan::scan_enabled(config, app_id);
+    let location = cloudsync_roots::locate_game(app_id);
 
-    if files.is_empty() {
-        return Ok(GameCloudStatus {
-            app_id,
-            status: CloudStatus::None,
-            last_sync: None,
-            pending_files: None,
-            error_message: None,
+    let candidate = user_dir.join(app_id).join("remotecache.vdf");
+    // With the scan enabled, a remote folder Steam hasn't indexed yet counts too
+    let has_remote_dir = scan && user_dir.join(app_id).join("remote").is_dir();
+    if candidate.exists() || has_remote_dir {
+        let mut files = if candidate.exists() {
+            let content = std::fs::read_to_string(&candidate)
+                .map_err(|e| format!("Failed to read remotecache.vdf: {}", e))?;
+            parse_remotecache_vdf(&content)?
+        } else {
+            HashMap::new()
+        };
+        if scan {
+            cloudsync_scan::union_local_tree(
+                &mut files,
+                app_id,
+                account_id,
+                &location,
+                &game_rules(config, app_id),
+            )?;
+        }
+
+        return Ok(GameFiles {
+            files,
+            user_id: account_id.to_string(),
+            location,
+            is_fallback: false,
+            save_roots: Vec::new(),
+            config_roots: Vec::new(),
         });
     }
 
-    // Check remote status
-    let client = WebDavClient::new(&config)?;
-    let remote_files = client.list_files(&app_id).await.unwrap_or_default();
+    // Save location fallback (Ludusavi manifest, then PCGamingWiki)
+    let mut found_files = HashMap::new();
 
-    let local_count = files.len();
-    let remote_count = remote_files.len();
+    if export_registry {
+        if let Err(e) = pcgamingwiki::export_registry_saves(app_id, Some(account_id)).await {
+            eprintln!("[CloudSync] Registry export failed for {}: {}", app_id, e);
+        }
+    }
 
-    // Simple status determination
-    let status = if remote_count == 0 && local_count > 0 {
-        CloudStatus::Pending
-    } else if remote_count == local_count {
-        CloudStatus::Synced
+    let save_roots = pcgamingwiki::find_save_locations(app_id, Some(account_id))
+        .await
+        .map_err(|e| format!("PCGamingWiki lookup failed: {}", e))?;
+
+    // Config files are kept apart from the saves, under config/, unless
+    // they are saved with them anyway
+    let mut config_roots = if config.sync_config_files {
+        pcgamingwiki_metadata::config_locations(app_id, Some(account_id)).await
     } else {
-        CloudStatus::Pending
+        Vec::new()
     };
+    config_roots.retain(|config_root| !save_roots.iter().any(|root| config_root.starts_with(root)));
+    let roots = save_roots
+        .iter()
+        .map(|root| (root, ""))
+        .chain(config_roots.iter().map(|root| (root, "config/")));
 
-    Ok(GameCloudStatus {
-        app_id,
-        status,
-        last_sync: None, // TODO: Track last sync time
-        pending_files: Some((local_count.abs_diff(remote_count)) as u32),
-        error_message: None,
-        source: "steam_cloud".to_string(),
+    for (root, key_prefix) in roots {
+        collect_root_files(root, key_prefix, &mut found_files);
+    }
+
+    Ok(GameFiles {
+        files: found_files,
+        user_id: account_id.to_string(),
+        location,
+        is_fallback: true,
+        save_roots,
+        config_roots,
     })
 }
 
-/// Get global cloud sync status
-#[tauri::command]
-pub async fn get_global_cloud_status(
-    app_handle: tauri::AppHandle,
-) -> Result<GlobalCloudStatus, String> {
-    let config = get_cloudsync_config(app_handle.clone()).await?;
+/// Add the PCGamingWiki files the remote or the last sync knows but that
+/// are missing on disk, so they get downloaded or their deletion passed on
+fn add_missing_fallback_files<'a>(
+    files: &mut HashMap<String, CloudFile>,
+    save_roots: &[PathBuf],
+    config_roots: &[PathBuf],
+    keys: impl Iterator<Item = &'a String>,
+) {
+    for key in keys {
+        if files.contains_key(key) {
+            continue;
+        }
+        let path = match key.strip_prefix("config/") {
+            Some(rel) => fallback_path(rel, config_roots),
+            None => fallback_path(key, save_roots),
+        };
+        if let Some(path) = path {
+            files.insert(
+                key.clone(),
+                CloudFile {
+                    path: key.clone(),
+                    root: -1,
+                    size: 0,
+                    localtime: 0,
+                    remotetime: 0,
+                    sha: String::new(),
+                    syncstate: 0,
+                    resolved_path: Some(path),
+                },
+            );
+        }
+    }
+}
 
-    match config {
-        Some(c) if c.enabled => Ok(GlobalCloudStatus {
-            enabled: true,
-            is_syncing: false, // TODO: Track active sync state
-            games_synced: 0,
-            games_pending: 0,
-            games_with_conflicts: 0,
-            last_sync: None,
-        }),
-        _ => Ok(GlobalCloudStatus {
-            enabled: false,
-            is_syncing: false,
-            games_synced: 0,
-            games_pending: 0,
-            games_with_conflicts: 0,
-            last_sync: None,
-        }),
+/// Where a file missing on disk belongs among `roots`, given its path
+/// relative to them: the single-file location of that name, else the first
+/// directory holding its parent directory (or just the first directory)
+fn fallback_path(key: &str, roots: &[PathBuf]) -> Option<PathBuf> {
+    if !cloudsync_archive::is_safe_relative(key) {
+        return None;
+    }
+    let single_file = roots
+        .iter()
+        .find(|root| !root.is_dir() && root.file_name().is_some_and(|name| name.to_string_lossy() == key));
+    if let Some(root) = single_file {
+        return Some(root.clone());
     }
+
+    let dirs: Vec<&PathBuf> = roots.iter().filter(|root| root.is_dir()).collect();
+    let parent = Path::new(key).parent().filter(|p| !p.as_os_str().is_empty());
+    dirs.iter()
+        .find(|root| parent.is_some_and(|p| root.join(p).is_dir()))
+        .or(dirs.first())
+        .map(|root| root.join(key))
 }
 
-// ============================================================================
-// Sync Commands
-// ============================================================================
+/// Add the files under a save location to `files`, keyed by their path
+/// relative to it (prefixed with `key_prefix`). Files CloudSync leaves next
+/// to saves (part files, conflict copies) are skipped.
+fn collect_root_files(root: &Path, key_prefix: &str, files: &mut HashMap<String, CloudFile>) {
+    if !root.exists() {
+        return;
+    }
+    // Ludusavi locations can be single files, kept under their own name
+    let base = match root.parent() {
+        Some(parent) if root.is_file() => parent.to_path_buf(),
+        _ => root.to_path_buf(),
+    };
+    for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file()
+            || cloudsync_scan::is_sync_artifact(&entry.file_name().to_string_lossy())
+        {
+            continue;
+        }
+        let path = entry.path();
+        // Create relative path from root
+        if let Ok(rel_path) = path.strip_prefix(&base) {
+            let rel_path_str = format!("{}{}", key_prefix, rel_path.to_string_lossy().replace('\\', "/"));
+
+            let metadata = std::fs::metadata(path).ok();
+            let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+            let time = metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            files.insert(
+                rel_path_str.clone(),
+                CloudFile {
+                    path: rel_path_str,
+                    root: -1, // Custom/Absolute
+                    size,
+                    localtime: time,
+                    remotetime: 0,
+                    sha: String::new(),
+                    syncstate: 0,
+                    resolved_path: Some(path.to_path_buf()),
+                },
+            );
+        }
+    }
+}
+
+/// File rules configured for a game (defaults if none)
+fn game_rules(config: &CloudSyncConfig, app_id: &str) -> GameFileRules {
+    config.game_rules.get(app_id).cloned().unwrap_or_default()
+}
+
+/// Resolve the local path of a tracked save file
+fn local_path_for(
+    file: &CloudFile,
+    app_id: &str,
+    user_id: &str,
+    location: &GameLocation,
+) -> Option<PathBuf> {
+    if file.root < 0 {
+        // Fallback files always carry their absolute path
+        file.resolved_path.clone()
+    } else {
+        resolve_cloud_file_path(file, app_id, user_id, location)
+    }
+}
+
+/// List a game's remote files by path. With the scan enabled, files another
+/// device's scan uploaded are added to `files`.
+async fn list_remote_files(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    config: &CloudSyncConfig,
+    files: &mut HashMap<String, CloudFile>,
+    is_fallback: bool,
+) -> Result<HashMap<String, RemoteEntry>, String> {
+    let remote_entries: HashMap<String, RemoteEntry> = client
+        .list_file_entries(app_id)
+        .await?
+        .into_iter()
+        .map(|e| (e.name.clone(), e))
+        .collect();
+
+    if !is_fallback && cloudsync_scan::scan_enabled(config, app_id) {
+        cloudsync_scan::union_remote_files(
+            files,
+            remote_entries.keys().map(|k| k.as_str()),
+            &game_rules(config, app_id),
+        )?;
+    }
+
+    Ok(remote_entries)
+}
+
+/// Work out what a sync would do with each file, without transferring or
+/// writing anything (status polls and dry runs use it). Remote files the index can't vouch for are judged by their size
+/// (see `cloudsync_state::estimate_remote_digest`). Sorted by path.
+async fn plan_game_sync(
+    app_id: &str,
+    account_id: &str,
+    config: &CloudSyncConfig,
+    game: &mut GameFiles,
+) -> Result<Vec<PlannedFile>, String> {
+    let client = cloudsync_accounts::create_account_storage(config, account_id).await?;
+    let remote_entries =
+        list_remote_files(client.as_ref(), app_id, config, &mut game.files, game.is_fallback).await?;
+
+    let remote_index = cloudsync_state::load_remote_index(client.as_ref(), app_id).await;
+    let state = cloudsync_state::load_sync_state(account_id, app_id);
+    if game.is_fallback {
+        let known = remote_entries.keys().chain(state.files.keys());
+        add_missing_fallback_files(&mut game.files, &game.save_roots, &game.config_roots, known);
+    }
+    let local_empty = !local_saves_exist(&game.files, app_id, &game.user_id, &game.location);
+
+    let mut plan = Vec::new();
+    for (file_path, cloud_file) in &game.files {
+        let local_path = match local_path_for(cloud_file, app_id, &game.user_id, &game.location) {
+            Some(p) => p,
+            None => continue,
+        };
+
+        let local = if local_path.exists() {
+            match cloudsync_state::hash_file(&local_path) {
+                Ok(d) => Some(d),
+                Err(e) => {
+                    eprintln!("[CloudSync] {}", e);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        let base = cloudsync_state::base_digest(&state, &remote_index, file_path);
+        let remote = remote_entries.get(file_path).map(|entry| {
+            cloudsync_state::estimate_remote_digest(
+                entry,
+                state.remote_versions.get(file_path),
+                remote_index.files.get(file_path),
+                base,
+            )
+        });
+
+        plan.push(PlannedFile {
+            path: file_path.clone(),
+            action: cloudsync_state::guard_wiped_side(
+                cloudsync_state::decide_sync_action(local.as_ref(), remote.as_ref(), base),
+                local_empty,
+                remote_entries.is_empty(),
+            ),
+        });
+    }
 
-/// Sync cloud saves for a specific game
+    plan.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(plan)
+}
+
+/// Sync cloud saves for a specific game, for the given Steam account
+/// (the active one if omitted). `mode` defaults to a full sync; `files`
+/// limits the run to those save paths.
 #[tauri::command]
 pub async fn sync_game_cloud_saves(
     app_id: String,
+    account_id: Option<String>,
+    mode: Option<SyncMode>,
+    files: Option<Vec<String>>,
     app_handle: tauri::AppHandle,
 ) -> Result<SyncResult, String> {
+    let mode = mode.unwrap_or(SyncMode::Full);
+    let only = files.filter(|f| !f.is_empty());
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+
     // Get config
     let config = get_cloudsync_config(app_handle.clone()).await?;
     let config = match config {
@@ -269,257 +594,1450 @@ pub async fn sync_game_cloud_saves(
         }
     };
 
-    // Find remotecache.vdf
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let steam_path = if cfg!(target_os = "macos") {
-        home.join("Library/Application Support/Steam")
+    if mode == SyncMode::DryRun {
+        return dry_run_game_sync(&app_id, &account_id, only.as_deref(), &config).await;
+    }
+    sync_and_record(&app_id, &account_id, mode, only.as_deref(), &config, Some(&app_handle)).await
+}
+
+/// Fail if any requested path isn't a save file of the game
+fn check_requested_files(files: &HashMap<String, CloudFile>, only: &[String]) -> Result<(), String> {
+    let unknown: Vec<&str> = only
+        .iter()
+        .filter(|p| !files.contains_key(*p))
+        .map(|p| p.as_str())
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
     } else {
-        home.join(".local/share/Steam")
+        Err(format!("Not save files of this game: {}", unknown.join(", ")))
+    }
+}
+
+/// Report what a full sync would do with each file, transferring nothing.
+/// Dry runs aren't recorded in the sync history.
+async fn dry_run_game_sync(
+    app_id: &str,
+    account_id: &str,
+    only: Option<&[String]>,
+    config: &CloudSyncConfig,
+) -> Result<SyncResult, String> {
+    let mut game = collect_game_files(app_id, account_id, config, false).await?;
+    let mut planned = plan_game_sync(app_id, account_id, config, &mut game).await?;
+    if let Some(only) = only {
+        check_requested_files(&game.files, only)?;
+        planned.retain(|p| only.contains(&p.path));
+    }
+
+    let count = |action: SyncAction| planned.iter().filter(|p| p.action == action).count();
+    let conflicts: Vec<String> = planned
+        .iter()
+        .filter(|p| p.action == SyncAction::Conflict)
+        .map(|p| p.path.clone())
+        .collect();
+
+    Ok(SyncResult {
+        success: true,
+        message: format!(
+            "Dry run: {} to upload, {} to download, {} to delete, {} conflicts",
+            count(SyncAction::Upload),
+            count(SyncAction::Download),
+            count(SyncAction::DeleteLocal) + count(SyncAction::DeleteRemote),
+            conflicts.len()
+        ),
+        files_uploaded: 0,
+        files_downloaded: 0,
+        files_deleted: 0,
+        conflicts,
+        planned,
+    })
+}
+
+/// Sync a game's saves with the stored config
+pub(crate) async fn run_game_sync(
+    app_id: &str,
+    account_id: Option<&str>,
+    mode: SyncMode,
+    app_handle: &tauri::AppHandle,
+) -> Result<SyncResult, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id)?;
+
+    // Get config
+    let config = get_cloudsync_config(app_handle.clone()).await?;
+    let config = match config {
+        Some(c) if c.enabled => c,
+        _ => {
+            return Err("CloudSync is not enabled".to_string());
+        }
     };
 
-    let userdata_path = steam_path.join("userdata");
-    let mut remotecache_path: Option<PathBuf> = None;
-    let mut user_id: Option<String> = None;
+    sync_and_record(app_id, &account_id, mode, None, &config, Some(app_handle)).await
+}
 
-    if let Ok(user_dirs) = std::fs::read_dir(&userdata_path) {
-        for user_entry in user_dirs.flatten() {
-            let candidate = user_entry.path().join(&app_id).join("remotecache.vdf");
-            if candidate.exists() {
-                remotecache_path = Some(candidate);
-                user_id = user_entry.file_name().into_string().ok();
-                break;
-            }
+/// Sync a game's saves, limited to the paths in `only` if given, and record
+/// the run in its history. Uploads are held back while the game is running,
+/// since it may still be writing them. Without an app handle (headless runs)
+/// no progress events are sent.
+async fn sync_and_record(
+    app_id: &str,
+    account_id: &str,
+    mode: SyncMode,
+    only: Option<&[String]>,
+    config: &CloudSyncConfig,
+    app_handle: Option<&tauri::AppHandle>,
+) -> Result<SyncResult, String> {
+    let _active = ActiveSync::begin(app_id);
+    let mut run = SyncRun::start(mode);
+    let result = sync_game(app_id, account_id, mode, only, config, app_handle, &mut run).await;
+
+    match &result {
+        Ok(r) => {
+            run.files_uploaded = r.files_uploaded;
+            run.files_downloaded = r.files_downloaded;
+            run.files_deleted = r.files_deleted;
+            run.conflicts = r.conflicts.len() as u32;
+            run.finish(r.success, &r.message);
         }
+        Err(e) => {
+            run.errors.push(e.clone());
+            run.finish(false, e);
+        }
+    }
+    if let Err(e) = cloudsync_history::record_run(account_id, app_id, run) {
+        eprintln!("[CloudSync] Failed to record sync history for {}: {}", app_id, e);
     }
 
-    let (files, is_fallback) = match remotecache_path {
-        Some(p) => {
-            let content = std::fs::read_to_string(&p)
-                .map_err(|e| format!("Failed to read remotecache.vdf: {}", e))?;
-             (parse_remotecache_vdf(&content)?, false)
-        },
-        None => {
-            // PCGamingWiki Fallback
-            let mut found_files = HashMap::new();
-            let steam_id = get_steam_user_id();
-            
-            // Update the outer user_id option if we found one
-            if let Some(ref sid) = steam_id {
-                user_id = Some(sid.clone());
-            }
+    result
+}
 
-            let save_roots = pcgamingwiki::find_save_locations(&app_id, steam_id.as_deref())
-                .await
-                .map_err(|e| format!("PCGamingWiki lookup failed: {}", e))?;
+/// The sync itself; transfer totals and per-file errors go into `run`
+async fn sync_game(
+    app_id: &str,
+    account_id: &str,
+    mode: SyncMode,
+    only: Option<&[String]>,
+    config: &CloudSyncConfig,
+    app_handle: Option<&tauri::AppHandle>,
+    run: &mut SyncRun,
+) -> Result<SyncResult, String> {
+    let GameFiles {
+        mut files,
+        user_id,
+        location,
+        is_fallback,
+        save_roots,
+        config_roots,
+    } = collect_game_files(app_id, account_id, config, true).await?;
 
-            if save_roots.is_empty() {
-                 return Ok(SyncResult {
-                    success: false,
-                    message: "No cloud save data found via PCGamingWiki".to_string(),
-                    files_uploaded: 0,
-                    files_downloaded: 0,
-                    conflicts: vec![],
-                });
-            }
-
-            for root in save_roots {
-                if root.exists() {
-                    for entry in WalkDir::new(&root).into_iter().filter_map(|e| e.ok()) {
-                        if entry.file_type().is_file() {
-                             let path = entry.path();
-                             // Create relative path from root
-                             if let Ok(rel_path) = path.strip_prefix(&root) {
-                                let rel_path_str = rel_path.to_string_lossy().replace('\\', "/");
-                                
-                                let metadata = std::fs::metadata(path).ok();
-                                let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-                                let time = metadata.as_ref()
-                                    .and_then(|m| m.modified().ok())
-                                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                    .map(|d| d.as_secs())
-                                    .unwrap_or(0);
-
-                                found_files.insert(rel_path_str.clone(), CloudFile {
-                                    path: rel_path_str,
-                                    root: -1, // Custom/Absolute
-                                    size,
-                                    localtime: time,
-                                    remotetime: 0,
-                                    sha: String::new(),
-                                    syncstate: 0,
-                                    resolved_path: Some(path.to_path_buf()),
-                                });
-                             }
-                        }
-                    }
-                }
-            }
-             
-            if found_files.is_empty() {
-                 return Ok(SyncResult {
-                    success: false,
-                    message: "PCGamingWiki path found but no files exist".to_string(),
-                    files_uploaded: 0,
-                    files_downloaded: 0,
-                    conflicts: vec![],
-                });
-            }
-            
-            (found_files, true)
-        }
-    };
+    let hold_uploads = game_running(app_id, &location).await?;
+    let mut uploads_held = 0u32;
+    // Files the mode leaves for another sync
+    let mut files_skipped = 0u32;
 
-    let user_id = user_id.unwrap_or_default();
+    let client = cloudsync_accounts::create_account_storage(config, account_id).await?;
+    let remote_entries =
+        list_remote_files(client.as_ref(), app_id, config, &mut files, is_fallback).await?;
+    let mut state = cloudsync_state::load_sync_state(account_id, app_id);
+    if is_fallback {
+        let known = remote_entries.keys().chain(state.files.keys());
+        add_missing_fallback_files(&mut files, &save_roots, &config_roots, known);
+    }
 
     if files.is_empty() {
+        let message = if is_fallback {
+            "No cloud save data found (checked Steam & PCGamingWiki)"
+        } else {
+            "No files to sync"
+        };
         return Ok(SyncResult {
-            success: true,
-            message: "No files to sync".to_string(),
+            success: !is_fallback,
+            message: message.to_string(),
             files_uploaded: 0,
             files_downloaded: 0,
+            files_deleted: 0,
             conflicts: vec![],
+            planned: vec![],
         });
     }
+    if let Some(only) = only {
+        check_requested_files(&files, only)?;
+    }
+
+    let mut remote_index = cloudsync_state::load_remote_index(client.as_ref(), app_id).await;
+    let mut index_changed = false;
+    let rollback = RollbackSession::new(app_id)?;
+
+    // Deletions aren't passed on from a side that lost all of its saves
+    let local_empty = !local_saves_exist(&files, app_id, &user_id, &location);
+    let remote_empty = remote_entries.is_empty();
+    let trash_id = (config.deletion_mode == DeletionMode::Trash)
+        .then(|| cloudsync_snapshots::snapshot_id_from_time(chrono::Utc::now()));
 
-    let client = WebDavClient::new(&config)?;
     let mut files_uploaded = 0u32;
     let mut files_downloaded = 0u32;
-    let conflicts: Vec<String> = Vec::new();
+    let mut files_deleted = 0u32;
+    let mut conflicts: Vec<String> = Vec::new();
+    let mut snapshot_taken = false;
+    // Registry exports this run downloaded, to be imported into the prefix
+    let mut registry_downloads: Vec<(String, PathBuf)> = Vec::new();
+    let synced_before = state.files.clone();
 
     // Process each file
     for (file_path, cloud_file) in &files {
-        // Resolve local path
-        // Resolve local path
-        let local_path = if is_fallback {
-             cloud_file.resolved_path.clone().unwrap() // Fallback always has resolved_path
-        } else {
-             match resolve_cloud_file_path(&cloud_file, &app_id, &user_id, None) {
-                Some(p) => p,
-                None => {
-                    eprintln!(
-                        "[CloudSync] Could not resolve path for {}: root={}",
-                        file_path, cloud_file.root
-                    );
+        if only.is_some_and(|only| !only.contains(file_path)) {
+            continue;
+        }
+
+        let local_path = match local_path_for(cloud_file, app_id, &user_id, &location) {
+            Some(p) => p,
+            None => {
+                eprintln!(
+                    "[CloudSync] Could not resolve path for {}: root={}",
+                    file_path, cloud_file.root
+                );
+                continue;
+            }
+        };
+
+        let local = if local_path.exists() {
+            match cloudsync_state::hash_file(&local_path) {
+                Ok(d) => Some(d),
+                Err(e) => {
+                    eprintln!("[CloudSync] {}", e);
+                    run.errors.push(format!("{}: {}", file_path, e));
                     continue;
                 }
             }
+        } else {
+            None
         };
 
-        // Check if local file exists
-        let local_exists = local_path.exists();
-        let local_time = if local_exists {
-            std::fs::metadata(&local_path)
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs())
-                .unwrap_or(cloud_file.localtime)
+        // Remote digest comes from the index; files uploaded by older versions
+        // (or changed behind the index's back, detected by size, ETag or
+        // modification time) are downloaded next to the save, hashed once and
+        // added to it
+        let mut remote_part: Option<PathBuf> = None;
+        let remote = if let Some(entry) = remote_entries.get(file_path) {
+            let indexed = cloudsync_state::indexed_digest(
+                entry,
+                state.remote_versions.get(file_path),
+                remote_index.files.get(file_path),
+                cloudsync_state::base_digest(&state, &remote_index, file_path),
+            );
+            match indexed.cloned() {
+                Some(d) => Some(d),
+                None => match cloudsync_transfer::download_to_part(
+                    client.as_ref(),
+                    app_handle,
+                    app_id,
+                    file_path,
+                    &local_path,
+                    Some(entry.size),
+                )
+                .await
+                {
+                    Ok((part, digest)) => {
+                        remote_index.files.insert(file_path.clone(), digest.clone());
+                        index_changed = true;
+                        remote_part = Some(part);
+                        Some(digest)
+                    }
+                    Err(e) => {
+                        eprintln!("[CloudSync] Download failed for {}: {}", file_path, e);
+                        run.errors.push(format!("{}: {}", file_path, e));
+                        continue;
+                    }
+                },
+            }
         } else {
-            0
+            None
         };
 
-        // Check remote file
-        let remote_time = client
-            .get_file_info(&app_id, file_path)
-            .await
-            .unwrap_or(None);
+        let base = cloudsync_state::base_digest(&state, &remote_index, file_path);
+        let action = cloudsync_state::action_for_mode(
+            cloudsync_state::guard_wiped_side(
+                cloudsync_state::decide_sync_action(local.as_ref(), remote.as_ref(), base),
+                local_empty,
+                remote_empty,
+            ),
+            mode,
+            local.is_some(),
+            remote.is_some(),
+        );
 
-        match (local_exists, remote_time) {
-            (true, None) => {
-                // Local exists, remote doesn't - upload
-                if let Ok(data) = std::fs::read(&local_path) {
-                    if let Err(e) = client.upload_file(&app_id, file_path, data).await {
-                        eprintln!("[CloudSync] Upload failed for {}: {}", file_path, e);
-                    } else {
+        // A downloaded copy is only kept if it's going to replace the local file
+        if action != Some(SyncAction::Download) {
+            if let Some(part) = remote_part.take() {
+                let _ = std::fs::remove_file(part);
+            }
+        }
+
+        let action = match action {
+            Some(a) => a,
+            None => {
+                files_skipped += 1;
+                continue;
+            }
+        };
+
+        match action {
+            SyncAction::None => {
+                match local {
+                    Some(digest) => {
+                        state.files.insert(file_path.clone(), digest);
+                    }
+                    // Gone on both sides
+                    None => {
+                        state.files.remove(file_path);
+                    }
+                }
+                match remote_entries.get(file_path) {
+                    Some(entry) => {
+                        state
+                            .remote_versions
+                            .insert(file_path.clone(), RemoteVersion::of(entry));
+                    }
+                    None => {
+                        state.remote_versions.remove(file_path);
+                    }
+                }
+            }
+            SyncAction::Upload if hold_uploads => {
+                uploads_held += 1;
+                eprintln!("[CloudSync] Holding upload of {} while the game is running", file_path);
+            }
+            SyncAction::Upload => {
+                ensure_snapshot(client.as_ref(), app_id, &files, config, &mut snapshot_taken).await;
+                let digest = local.expect("upload requires a local file");
+                match cloudsync_transfer::upload_save_file(
+                    client.as_ref(),
+                    app_handle,
+                    app_id,
+                    file_path,
+                    &local_path,
+                )
+                .await
+                {
+                    Ok(_) => {
                         files_uploaded += 1;
+                        run.bytes_uploaded += digest.size;
                         eprintln!("[CloudSync] Uploaded: {}", file_path);
+                        remote_index.files.insert(file_path.clone(), digest.clone());
+                        remote_index.deleted.remove(file_path);
+                        index_changed = true;
+                        state.deleted.remove(file_path);
+                        state.files.insert(file_path.clone(), digest);
+                        // Known once the next listing shows it
+                        state.remote_versions.remove(file_path);
+                    }
+                    Err(e) => {
+                        eprintln!("[CloudSync] Upload failed for {}: {}", file_path, e);
+                        run.errors.push(format!("{}: {}", file_path, e));
                     }
                 }
             }
-            (false, Some(_)) => {
-                // Remote exists, local doesn't - download
-                match client.download_file(&app_id, file_path).await {
-                    Ok(data) => {
-                        // Create parent directories
-                        if let Some(parent) = local_path.parent() {
-                            let _ = std::fs::create_dir_all(parent);
+            SyncAction::Download if hold_uploads && cloudsync_registry::is_export(app_id, &local_path) => {
+                if let Some(part) = remote_part.take() {
+                    let _ = std::fs::remove_file(part);
+                }
+                uploads_held += 1;
+                eprintln!("[CloudSync] Holding registry save {} while the game is running", file_path);
+            }
+            SyncAction::Download => {
+                let digest = remote.expect("download requires a remote file");
+                let result = match remote_part {
+                    Some(part) => cloudsync_transfer::commit_part(
+                        &part,
+                        &local_path,
+                        file_path,
+                        Some(&rollback),
+                    ),
+                    None => cloudsync_transfer::download_save_file(
+                        client.as_ref(),
+                        app_handle,
+                        app_id,
+                        file_path,
+                        &local_path,
+                        Some(&digest),
+                        Some(&rollback),
+                    )
+                    .await
+                    .map(|_| ()),
+                };
+                match result {
+                    Ok(_) => {
+                        files_downloaded += 1;
+                        run.bytes_downloaded += digest.size;
+                        eprintln!("[CloudSync] Downloaded: {}", file_path);
+                        if cloudsync_registry::is_export(app_id, &local_path) {
+                            registry_downloads.push((file_path.clone(), local_path.clone()));
                         }
-                        if let Err(e) = std::fs::write(&local_path, data) {
-                            eprintln!("[CloudSync] Write failed for {}: {}", file_path, e);
-                        } else {
-                            files_downloaded += 1;
-                            eprintln!("[CloudSync] Downloaded: {}", file_path);
+                        state.deleted.remove(file_path);
+                        state.files.insert(file_path.clone(), digest);
+                        if let Some(entry) = remote_entries.get(file_path) {
+                            state
+                                .remote_versions
+                                .insert(file_path.clone(), RemoteVersion::of(entry));
                         }
                     }
                     Err(e) => {
                         eprintln!("[CloudSync] Download failed for {}: {}", file_path, e);
+                        run.errors.push(format!("{}: {}", file_path, e));
                     }
                 }
             }
-            (true, Some(remote_ts)) => {
-                // Both exist - compare timestamps
-                let time_diff = if local_time > remote_ts {
-                    local_time - remote_ts
-                } else {
-                    remote_ts - local_time
-                };
-
-                // Allow 5 second tolerance for clock skew
-                if time_diff <= 5 {
-                    // Files are in sync
-                    continue;
+            SyncAction::DeleteLocal => {
+                let digest = local.expect("local deletion requires a local file");
+                match cloudsync_transfer::delete_local_save(&local_path, file_path, &rollback) {
+                    Ok(_) => {
+                        files_deleted += 1;
+                        eprintln!("[CloudSync] Deleted locally: {}", file_path);
+                        state.files.remove(file_path);
+                        state.deleted.insert(file_path.clone(), tombstone(digest));
+                    }
+                    Err(e) => {
+                        eprintln!("[CloudSync] Local deletion failed for {}: {}", file_path, e);
+                        run.errors.push(format!("{}: {}", file_path, e));
+                    }
                 }
-
-                if local_time > remote_ts {
-                    // Local is newer - upload
-                    if let Ok(data) = std::fs::read(&local_path) {
-                        if let Err(e) = client.upload_file(&app_id, file_path, data).await {
-                            eprintln!("[CloudSync] Upload failed for {}: {}", file_path, e);
-                        } else {
-                            files_uploaded += 1;
-                            eprintln!("[CloudSync] Uploaded (newer): {}", file_path);
-                        }
+            }
+            SyncAction::DeleteRemote if hold_uploads => {
+                uploads_held += 1;
+                eprintln!("[CloudSync] Holding deletion of {} while the game is running", file_path);
+            }
+            SyncAction::DeleteRemote => {
+                ensure_snapshot(client.as_ref(), app_id, &files, config, &mut snapshot_taken).await;
+                let digest = remote.expect("remote deletion requires a remote file");
+                match cloudsync_transfer::delete_remote_save(
+                    client.as_ref(),
+                    app_id,
+                    file_path,
+                    trash_id.as_deref(),
+                )
+                .await
+                {
+                    Ok(_) => {
+                        files_deleted += 1;
+                        eprintln!("[CloudSync] Deleted remotely: {}", file_path);
+                        remote_index.files.remove(file_path);
+                        remote_index.deleted.insert(file_path.clone(), tombstone(digest.clone()));
+                        index_changed = true;
+                        state.files.remove(file_path);
+                        state.deleted.insert(file_path.clone(), tombstone(digest));
                     }
-                } else {
-                    // Remote is newer - download
-                    match client.download_file(&app_id, file_path).await {
-                        Ok(data) => {
-                            if let Some(parent) = local_path.parent() {
-                                let _ = std::fs::create_dir_all(parent);
-                            }
-                            if let Err(e) = std::fs::write(&local_path, data) {
-                                eprintln!("[CloudSync] Write failed for {}: {}", file_path, e);
-                            } else {
-                                files_downloaded += 1;
-                                eprintln!("[CloudSync] Downloaded (newer): {}", file_path);
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("[CloudSync] Download failed for {}: {}", file_path, e);
-                        }
+                    Err(e) => {
+                        eprintln!("[CloudSync] Remote deletion failed for {}: {}", file_path, e);
+                        run.errors.push(format!("{}: {}", file_path, e));
                     }
                 }
             }
-            (false, None) => {
-                // Neither exists - skip
+            SyncAction::Conflict => {
+                eprintln!("[CloudSync] Conflict: {} changed locally and remotely", file_path);
+                conflicts.push(file_path.clone());
             }
         }
     }
 
-    Ok(SyncResult {
-        success: true,
-        message: format!(
-            "Sync complete: {} uploaded, {} downloaded",
-            files_uploaded, files_downloaded
-        ),
-        files_uploaded,
-        files_downloaded,
-        conflicts,
-    })
-}
-
-// ============================================================================
-// Watcher Commands
+    // Downloaded registry exports only take effect once imported into the
+    // prefix, which Wine would overwrite while it runs. Exports that can't be
+    // imported keep their old sync state, so the next sync downloads them
+    // again instead of uploading the re-exported old key.
+    if !registry_downloads.is_empty() {
+        let exports: Vec<PathBuf> = registry_downloads.iter().map(|(_, path)| path.clone()).collect();
+        if let Err(e) = import_registry_exports(app_id, &location, &exports, &rollback).await {
+            eprintln!("[CloudSync] Registry import failed for {}: {}", app_id, e);
+            run.errors.push(e);
+            for (file_path, _) in &registry_downloads {
+                match synced_before.get(file_path) {
+                    Some(digest) => state.files.insert(file_path.clone(), digest.clone()),
+                    None => state.files.remove(file_path),
+                };
+                state.remote_versions.remove(file_path);
+            }
+        }
+    }
+    state.remote_versions.retain(|path, _| state.files.contains_key(path));
+
+    run.files_pending = uploads_held + files_skipped + run.errors.len() as u32;
+
+    if let Some(id) = rollback.finish() {
+        eprintln!("[CloudSync] Overwritten local saves backed up as rollback {}", id);
+    }
+
+    if let Some(only) = only {
+        // Conflicts of files outside this run still stand
+        conflicts.extend(state.conflicts.iter().filter(|p| !only.contains(p)).cloned());
+    }
+    conflicts.sort();
+    state.conflicts = conflicts.clone();
+    if let Err(e) = cloudsync_state::save_sync_state(&state) {
+        eprintln!("[CloudSync] {}", e);
+    }
+    if index_changed {
+        if let Err(e) = cloudsync_state::save_remote_index(client.as_ref(), app_id, &remote_index).await {
+            eprintln!("[CloudSync] Failed to update remote index for {}: {}", app_id, e);
+        }
+    }
+
+    if snapshot_taken {
+        if let Err(e) = cloudsync_snapshots::prune_snapshots(
+            client.as_ref(),
+            app_id,
+            config.snapshot_retention_count,
+            config.snapshot_retention_days,
+        )
+        .await
+        {
+            eprintln!("[CloudSync] Snapshot pruning failed for {}: {}", app_id, e);
+        }
+    }
+
+    let mut message = format!(
+        "Sync complete: {} uploaded, {} downloaded",
+        files_uploaded, files_downloaded
+    );
+    if files_deleted > 0 {
+        message.push_str(&format!(", {} deleted", files_deleted));
+    }
+    if !conflicts.is_empty() {
+        message.push_str(&format!(", {} conflicts need resolving", conflicts.len()));
+    }
+    if uploads_held > 0 {
+        message.push_str(&format!(", {} uploads held until the game exits", uploads_held));
+    }
+    if files_skipped > 0 {
+        message.push_str(&format!(", {} files left for a full sync", files_skipped));
+    }
+
+    Ok(SyncResult {
+        success: true,
+        message,
+        files_uploaded,
+        files_downloaded,
+        files_deleted,
+        conflicts,
+        planned: vec![],
+    })
+}
+
+/// Whether any of a game's save files exists locally
+fn local_saves_exist(
+    files: &HashMap<String, CloudFile>,
+    app_id: &str,
+    user_id: &str,
+    location: &GameLocation,
+) -> bool {
+    files
+        .values()
+        .filter_map(|f| local_path_for(f, app_id, user_id, location))
+        .any(|p| p.exists())
+}
+
+/// Import downloaded registry exports into the game's prefix, unless Wine is
+/// running in it (checked right before the .reg files are rewritten)
+async fn import_registry_exports(
+    app_id: &str,
+    location: &GameLocation,
+    exports: &[PathBuf],
+    rollback: &RollbackSession,
+) -> Result<(), String> {
+    let prefix = location
+        .proton_prefix
+        .as_deref()
+        .ok_or("No Proton prefix to import registry saves into")?;
+
+    if game_running(app_id, location).await? {
+        return Err(format!("Game {} is running, registry saves not imported", app_id));
+    }
+
+    cloudsync_registry::import_exports(app_id, prefix, exports, rollback).map(|_| ())
+}
+
+/// Whether a game is running, checked on a blocking thread (it reads /proc)
+async fn game_running(app_id: &str, location: &GameLocation) -> Result<bool, String> {
+    let (app_id, location) = (app_id.to_string(), location.clone());
+    tokio::task::spawn_blocking(move || cloudsync_session::is_game_running(&app_id, &location))
+        .await
+        .map_err(|e| format!("Failed to check game processes: {}", e))
+}
+
+/// Tombstone for a file deleted now
+fn tombstone(digest: FileDigest) -> Tombstone {
+    Tombstone {
+        digest,
+        deleted_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Snapshot the remote set once per sync, right before the first upload
+/// overwrites anything
+async fn ensure_snapshot(
+    client: &dyn CloudStorage,
+    app_id: &str,
+    files: &HashMap<String, CloudFile>,
+    config: &CloudSyncConfig,
+    snapshot_taken: &mut bool,
+) {
+    if *snapshot_taken || config.snapshot_retention_count == 0 {
+        return;
+    }
+    *snapshot_taken = true;
+
+    if let Err(e) = cloudsync_snapshots::create_snapshot(client, app_id, files).await {
+        eprintln!("[CloudSync] Snapshot failed for {}: {}", app_id, e);
+    }
+}
+
+// ============================================================================
+// Bulk Sync Commands
+// ============================================================================
+
+/// Games synced at the same time by a bulk sync
+const BULK_SYNC_CONCURRENCY: usize = 4;
+
+/// Store file the GUI keeps its settings in, read directly by headless runs
+/// (tauri-plugin-store puts it in the app data dir, named after the identifier)
+const SETTINGS_STORE_DIR: &str = "com.boilerroom.app";
+
+/// Installed games of an account with saves to sync: those Steam Cloud
+/// tracks (remotecache.vdf), then those the Ludusavi manifest or PCGamingWiki
+/// knows save locations for
+async fn discover_cloud_games(account_id: &str) -> Vec<String> {
+    let installed = cloudsync_roots::installed_app_ids();
+    let mut app_ids: Vec<String> = find_remotecache_files(account_id, &installed)
+        .into_iter()
+        .map(|(app_id, _)| app_id)
+        .collect();
+
+    let others: Vec<&String> = installed.iter().filter(|id| !app_ids.contains(id)).collect();
+    let found: Vec<String> = stream::iter(others)
+        .map(|app_id| async move {
+            let roots = pcgamingwiki::find_save_locations(app_id, Some(account_id))
+                .await
+                .unwrap_or_default();
+            roots.iter().any(|r| r.exists()).then(|| app_id.clone())
+        })
+        .buffer_unordered(BULK_SYNC_CONCURRENCY)
+        .filter_map(|app_id| async move { app_id })
+        .collect()
+        .await;
+
+    app_ids.extend(found);
+    app_ids.sort();
+    app_ids
+}
+
+/// Sync every discovered game of an account, a few at a time
+async fn bulk_sync(
+    account_id: &str,
+    mode: SyncMode,
+    config: &CloudSyncConfig,
+    app_handle: Option<&tauri::AppHandle>,
+) -> BulkSyncResult {
+    let app_ids = discover_cloud_games(account_id).await;
+    eprintln!("[CloudSync] Bulk sync of {} games", app_ids.len());
+
+    let mut games: Vec<GameSyncOutcome> = stream::iter(app_ids)
+        .map(|app_id| async move {
+            let result = if mode == SyncMode::DryRun {
+                dry_run_game_sync(&app_id, account_id, None, config).await
+            } else {
+                sync_and_record(&app_id, account_id, mode, None, config, app_handle).await
+            };
+            match result {
+                Ok(r) => GameSyncOutcome {
+                    app_id,
+                    result: Some(r),
+                    error: None,
+                },
+                Err(e) => {
+                    eprintln!("[CloudSync] Bulk sync failed for {}: {}", app_id, e);
+                    GameSyncOutcome {
+                        app_id,
+                        result: None,
+                        error: Some(e),
+                    }
+                }
+            }
+        })
+        .buffer_unordered(BULK_SYNC_CONCURRENCY)
+        .collect()
+        .await;
+    games.sort_by(|a, b| a.app_id.cmp(&b.app_id));
+
+    let mut report = BulkSyncResult {
+        success: true,
+        message: String::new(),
+        files_uploaded: 0,
+        files_downloaded: 0,
+        files_deleted: 0,
+        conflicts: Vec::new(),
+        games: Vec::new(),
+    };
+    let mut failed = 0;
+    for game in &games {
+        match &game.result {
+            Some(r) => {
+                report.files_uploaded += r.files_uploaded;
+                report.files_downloaded += r.files_downloaded;
+                report.files_deleted += r.files_deleted;
+                report
+                    .conflicts
+                    .extend(r.conflicts.iter().map(|path| format!("{}/{}", game.app_id, path)));
+                if !r.success {
+                    failed += 1;
+                }
+            }
+            None => failed += 1,
+        }
+    }
+
+    report.success = failed == 0;
+    report.message = format!(
+        "Synced {} games: {} uploaded, {} downloaded, {} conflicts, {} failed",
+        games.len() - failed,
+        report.files_uploaded,
+        report.files_downloaded,
+        report.conflicts.len(),
+        failed
+    );
+    report.games = games;
+    report
+}
+
+/// Sync every installed game with cloud saves, for the given Steam account
+/// (the active one if omitted)
+#[tauri::command]
+pub async fn sync_all_cloud_saves(
+    account_id: Option<String>,
+    mode: Option<SyncMode>,
+    app_handle: tauri::AppHandle,
+) -> Result<BulkSyncResult, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+
+    // Get config
+    let config = get_cloudsync_config(app_handle.clone()).await?;
+    let config = match config {
+        Some(c) if c.enabled => c,
+        _ => {
+            return Err("CloudSync is not enabled".to_string());
+        }
+    };
+
+    Ok(bulk_sync(&account_id, mode.unwrap_or(SyncMode::Full), &config, Some(&app_handle)).await)
+}
+
+/// Read the CloudSync config straight from the settings store file
+fn load_stored_config() -> Result<Option<CloudSyncConfig>, String> {
+    let path = dirs::data_dir()
+        .ok_or("Could not find data directory")?
+        .join(SETTINGS_STORE_DIR)
+        .join("settings.json");
+    let content = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(_) => return Ok(None),
+    };
+    let store: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse settings store: {}", e))?;
+
+    Ok(store
+        .get("cloudsync_config")
+        .and_then(|v| serde_json::from_value(v.clone()).ok()))
+}
+
+/// Value of a `--flag value` command-line option
+fn cli_option<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+/// Sync every game without starting the GUI, e.g. from cron:
+/// `boilerroom --sync-all-cloud-saves [--account <id>] [--mode <mode>]`.
+/// Prints the report as JSON and returns the process exit code
+/// (0 all synced, 1 some games failed, 2 the sync couldn't start).
+pub fn sync_all_cloud_saves_headless(args: &[String]) -> i32 {
+    let result = tauri::async_runtime::block_on(async {
+        let mode = match cli_option(args, "--mode") {
+            Some(m) => serde_json::from_value(serde_json::Value::String(m.to_string()))
+                .map_err(|_| format!("Unknown sync mode: {}", m))?,
+            None => SyncMode::Full,
+        };
+        let account_id = cloudsync_accounts::resolve_account(cli_option(args, "--account"))?;
+        let config = match load_stored_config()? {
+            Some(c) if c.enabled => c,
+            _ => return Err("CloudSync is not enabled".to_string()),
+        };
+
+        Ok(bulk_sync(&account_id, mode, &config, None).await)
+    });
+
+    match result {
+        Ok(report) => {
+            match serde_json::to_string_pretty(&report) {
+                Ok(json) => println!("{}", json),
+                Err(_) => println!("{}", report.message),
+            }
+            if report.success {
+                0
+            } else {
+                1
+            }
+        }
+        Err(e) => {
+            eprintln!("[CloudSync] {}", e);
+            2
+        }
+    }
+}
+
+// ============================================================================
+// Conflict Commands
+// ============================================================================
+
+/// List files with unresolved sync conflicts for a game
+#[tauri::command]
+pub async fn get_cloud_conflicts(
+    app_id: String,
+    account_id: Option<String>,
+) -> Result<Vec<String>, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    Ok(cloudsync_state::load_sync_state(&account_id, &app_id).conflicts)
+}
+
+/// Resolve a single sync conflict
+#[tauri::command]
+pub async fn resolve_cloud_conflict(
+    app_id: String,
+    file_path: String,
+    resolution: ConflictResolution,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let resolutions = HashMap::from([(file_path, resolution)]);
+    resolve_cloud_conflicts(app_id, resolutions, account_id, app_handle).await
+}
+
+/// Resolve several sync conflicts of a game at once. The remote set is
+/// snapshotted once before the first upload, and the local files replaced
+/// are backed up as one rollback point. Files that fail are left in conflict.
+#[tauri::command]
+pub async fn resolve_cloud_conflicts(
+    app_id: String,
+    resolutions: HashMap<String, ConflictResolution>,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    let config = match get_cloudsync_config(app_handle.clone()).await? {
+        Some(c) if c.enabled => c,
+        _ => return Err("CloudSync is not enabled".to_string()),
+    };
+
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    let mut state = cloudsync_state::load_sync_state(&account_id, &app_id);
+    if let Some(file_path) = resolutions.keys().find(|p| !state.conflicts.contains(p)) {
+        return Err(format!("No conflict recorded for {}", file_path));
+    }
+
+    let game_files = collect_game_files(&app_id, &account_id, &config, false).await?;
+    let client = cloudsync_accounts::create_account_storage(&config, &account_id).await?;
+    let mut remote_index = cloudsync_state::load_remote_index(client.as_ref(), &app_id).await;
+    let rollback = RollbackSession::new(&app_id)?;
+    let mut snapshot_taken = false;
+    let mut index_changed = false;
+    let mut errors: Vec<String> = Vec::new();
+
+    let mut resolutions: Vec<(String, ConflictResolution)> = resolutions.into_iter().collect();
+    resolutions.sort_by(|a, b| a.0.cmp(&b.0));
+    for (file_path, resolution) in resolutions {
+        let resolved = resolve_conflict(
+            client.as_ref(),
+            &app_handle,
+            &app_id,
+            &file_path,
+            resolution,
+            &game_files,
+            &config,
+            &mut remote_index,
+            &rollback,
+            &mut snapshot_taken,
+        )
+        .await;
+        match resolved {
+            Ok(digest) => {
+                index_changed |= resolution != ConflictResolution::KeepRemote;
+                state.files.insert(file_path.clone(), digest);
+                state.remote_versions.remove(&file_path);
+                state.conflicts.retain(|p| p != &file_path);
+                eprintln!("[CloudSync] Resolved conflict for {} ({:?})", file_path, resolution);
+            }
+            Err(e) => {
+                eprintln!("[CloudSync] Could not resolve conflict for {}: {}", file_path, e);
+                errors.push(format!("{}: {}", file_path, e));
+            }
+        }
+    }
+
+    if let Some(id) = rollback.finish() {
+        eprintln!("[CloudSync] Overwritten local saves backed up as rollback {}", id);
+    }
+    let saved = cloudsync_state::save_sync_state(&state);
+
+    if index_changed {
+        if let Err(e) = cloudsync_state::save_remote_index(client.as_ref(), &app_id, &remote_index).await {
+            errors.push(e);
+        }
+    }
+
+    if snapshot_taken {
+        if let Err(e) = cloudsync_snapshots::prune_snapshots(
+            client.as_ref(),
+            &app_id,
+            config.snapshot_retention_count,
+            config.snapshot_retention_days,
+        )
+        .await
+        {
+            eprintln!("[CloudSync] Snapshot pruning failed for {}: {}", app_id, e);
+        }
+    }
+
+    saved?;
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(())
+}
+
+/// Resolve one conflict, returning the digest both sides now share
+#[allow(clippy::too_many_arguments)]
+async fn resolve_conflict(
+    client: &dyn CloudStorage,
+    app_handle: &tauri::AppHandle,
+    app_id: &str,
+    file_path: &str,
+    resolution: ConflictResolution,
+    game_files: &GameFiles,
+    config: &CloudSyncConfig,
+    remote_index: &mut RemoteIndex,
+    rollback: &RollbackSession,
+    snapshot_taken: &mut bool,
+) -> Result<FileDigest, String> {
+    let cloud_file = game_files
+        .files
+        .get(file_path)
+        .ok_or_else(|| format!("{} is no longer tracked for this game", file_path))?;
+    let local_path = local_path_for(cloud_file, app_id, &game_files.user_id, &game_files.location)
+        .ok_or_else(|| format!("Could not resolve local path for {}", file_path))?;
+
+    match resolution {
+        ConflictResolution::KeepLocal | ConflictResolution::KeepBoth => {
+            if resolution == ConflictResolution::KeepBoth {
+                // Preserve the remote version next to the local file
+                let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+                let copy_path = cloudsync_state::conflict_copy_path(&local_path, &timestamp);
+                cloudsync_transfer::download_save_file(
+                    client,
+                    Some(app_handle),
+                    app_id,
+                    file_path,
+                    &copy_path,
+                    None,
+                    None,
+                )
+                .await?;
+                eprintln!("[CloudSync] Kept remote copy as {}", copy_path.display());
+            }
+
+            ensure_snapshot(client, app_id, &game_files.files, config, snapshot_taken).await;
+            let digest = cloudsync_state::hash_file(&local_path)?;
+            cloudsync_transfer::upload_save_file(client, Some(app_handle), app_id, file_path, &local_path)
+                .await?;
+            remote_index.files.insert(file_path.to_string(), digest.clone());
+            remote_index.deleted.remove(file_path);
+            Ok(digest)
+        }
+        ConflictResolution::KeepRemote => {
+            let digest = cloudsync_transfer::download_save_file(
+                client,
+                Some(app_handle),
+                app_id,
+                file_path,
+                &local_path,
+                remote_index.files.get(file_path),
+                Some(rollback),
+            )
+            .await?;
+            if cloudsync_registry::is_export(app_id, &local_path) {
+                let exports = std::slice::from_ref(&local_path);
+                import_registry_exports(app_id, &game_files.location, exports, rollback).await?;
+            }
+            Ok(digest)
+        }
+    }
+}
+
+// ============================================================================
+// Snapshot Commands
+// ============================================================================
+
+/// List remote save snapshots for a game, newest first
+#[tauri::command]
+pub async fn list_cloud_snapshots(
+    app_id: String,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SnapshotInfo>, String> {
+    let config = match get_cloudsync_config(app_handle).await? {
+        Some(c) if c.enabled => c,
+        _ => return Err("CloudSync is not enabled".to_string()),
+    };
+
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    let client = cloudsync_accounts::create_account_storage(&config, &account_id).await?;
+    cloudsync_snapshots::list_snapshots(client.as_ref(), &app_id).await
+}
+
+/// Restore a snapshot's files to their local save locations
+#[tauri::command]
+pub async fn restore_cloud_snapshot(
+    app_id: String,
+    snapshot_id: String,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<SyncResult, String> {
+    let config = match get_cloudsync_config(app_handle.clone()).await? {
+        Some(c) if c.enabled => c,
+        _ => return Err("CloudSync is not enabled".to_string()),
+    };
+
+    let user_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    let client = cloudsync_accounts::create_account_storage(&config, &user_id).await?;
+    let snapshot = cloudsync_snapshots::get_snapshot(client.as_ref(), &app_id, &snapshot_id).await?;
+    let keys = cloudsync_snapshots::snapshot_file_keys(client.as_ref(), &app_id, &snapshot).await?;
+    let location = cloudsync_roots::locate_game(&app_id);
+
+    let mut files_downloaded = 0u32;
+    let mut errors: Vec<String> = Vec::new();
+    let rollback = RollbackSession::new(&app_id)?;
+
+    for (file, key) in snapshot.files.iter().zip(&keys) {
+        let local_path = match local_path_for(file, &app_id, &user_id, &location) {
+            Some(p) => p,
+            None => {
+                errors.push(format!("{}: could not resolve local path", file.path));
+                continue;
+            }
+        };
+
+        match cloudsync_transfer::download_snapshot_file(
+            client.as_ref(),
+            Some(&app_handle),
+            &app_id,
+            &snapshot_id,
+            key,
+            &local_path,
+            snapshot.digests.get(key),
+            Some(&rollback),
+        )
+        .await
+        {
+            Ok(_) => {
+                files_downloaded += 1;
+                eprintln!("[CloudSync] Restored: {}", file.path);
+            }
+            Err(e) => errors.push(format!("{}: {}", file.path, e)),
+        }
+    }
+    rollback.finish();
+
+    Ok(SyncResult {
+        success: errors.is_empty(),
+        message: if errors.is_empty() {
+            format!("Restored {} files from snapshot {}", files_downloaded, snapshot_id)
+        } else {
+            format!(
+                "Restored {} files from snapshot {}, {} failed: {}",
+                files_downloaded,
+                snapshot_id,
+                errors.len(),
+                errors.join("; ")
+            )
+        },
+        files_uploaded: 0,
+        files_downloaded,
+        files_deleted: 0,
+        conflicts: vec![],
+        planned: vec![],
+    })
+}
+
+// ============================================================================
+// Local Rollback Commands
+// ============================================================================
+
+/// List the local backups of saves that downloads overwrote, newest first
+#[tauri::command]
+pub async fn list_local_rollbacks(app_id: String) -> Result<Vec<RollbackInfo>, String> {
+    cloudsync_rollback::list_rollbacks(&app_id)
+}
+
+/// Put the local saves of a rollback point back in place
+#[tauri::command]
+pub async fn restore_local_rollback(
+    app_id: String,
+    rollback_id: String,
+) -> Result<SyncResult, String> {
+    let result = cloudsync_rollback::restore_rollback(&app_id, &rollback_id)?;
+
+    Ok(SyncResult {
+        success: result.errors.is_empty(),
+        message: if result.errors.is_empty() {
+            format!("Restored {} files from rollback {}", result.files_restored, rollback_id)
+        } else {
+            format!(
+                "Restored {} files from rollback {}, {} failed: {}",
+                result.files_restored,
+                rollback_id,
+                result.errors.len(),
+                result.errors.join("; ")
+            )
+        },
+        files_uploaded: 0,
+        files_downloaded: 0,
+        files_deleted: 0,
+        conflicts: vec![],
+        planned: vec![],
+    })
+}
+
+// ============================================================================
+// Archive Commands
+// ============================================================================
+
+/// Pack a game's save files into a portable archive at `destination`, for
+/// the given Steam account (the active one if omitted). The format follows
+/// the file extension (.7z or zip) unless given.
+#[tauri::command]
+pub async fn export_cloud_saves(
+    app_id: String,
+    destination: String,
+    account_id: Option<String>,
+    format: Option<ArchiveFormat>,
+    app_handle: tauri::AppHandle,
+) -> Result<ArchiveManifest, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    // Exports work without a storage backend; the config only adds file rules
+    let config = get_cloudsync_config(app_handle.clone()).await?.unwrap_or_default();
+    let game = collect_game_files(&app_id, &account_id, &config, true).await?;
+
+    let mut paths: Vec<&String> = game.files.keys().collect();
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let cloud_file = &game.files[path];
+        let local_path = match local_path_for(cloud_file, &app_id, &game.user_id, &game.location) {
+            Some(p) if p.is_file() => p,
+            _ => continue,
+        };
+        let mut file = cloud_file.clone();
+        file.size = std::fs::metadata(&local_path).map(|m| m.len()).unwrap_or(file.size);
+        file.resolved_path = Some(local_path);
+        files.push(ArchivedFile {
+            archive_path: cloudsync_archive::archive_path_for(&file),
+            file,
+        });
+    }
+    if files.is_empty() {
+        return Err(format!("No save files found for {}", app_id));
+    }
+
+    let format = format.unwrap_or_else(|| {
+        if destination.to_lowercase().ends_with(".7z") {
+            ArchiveFormat::SevenZ
+        } else {
+            ArchiveFormat::Zip
+        }
+    });
+    let manifest = ArchiveManifest::new(&app_id, &account_id, game.location, files);
+
+    let written = manifest.clone();
+    tokio::task::spawn_blocking(move || {
+        cloudsync_archive::write_archive(Path::new(&destination), format, &written)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+
+    eprintln!("[CloudSync] Exported {} save files of {}", manifest.files.len(), app_id);
+    Ok(manifest)
+}
+
+/// Restore the saves of an exported archive on this machine, for the given
+/// Steam account (the active one if omitted). Replaced local files are kept
+/// as a rollback point.
+#[tauri::command]
+pub async fn import_cloud_saves(
+    archive_path: String,
+    account_id: Option<String>,
+) -> Result<SyncResult, String> {
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+
+    // Extracting validates the manifest, so nothing is written for a
+    // malformed archive
+    let (dir, manifest) = tokio::task::spawn_blocking(move || {
+        let dir = tempfile::tempdir().map_err(|e| format!("Failed to create temp dir: {}", e))?;
+        let manifest = cloudsync_archive::extract_archive(Path::new(&archive_path), dir.path())?;
+        Ok::<_, String>((dir, manifest))
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))??;
+
+    // Files PCGamingWiki located may only go to this machine's save (and
+    // config) locations
+    let has_custom = manifest.files.iter().any(|f| f.file.root < 0);
+    let mut save_roots = if has_custom {
+        pcgamingwiki::find_save_locations(&manifest.app_id, Some(&account_id))
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("[CloudSync] No save locations for {}: {}", manifest.app_id, e);
+                Vec::new()
+            })
+    } else {
+        Vec::new()
+    };
+    if manifest.files.iter().any(|f| f.file.root < 0 && f.file.path.starts_with("config/")) {
+        save_roots.extend(pcgamingwiki_metadata::config_locations(&manifest.app_id, Some(&account_id)).await);
+    }
+
+    let (app_id, result, rollback_id) = tokio::task::spawn_blocking(move || {
+        let location = cloudsync_roots::locate_game(&manifest.app_id);
+        let rollback = RollbackSession::new(&manifest.app_id)?;
+        let result = cloudsync_archive::import_files(
+            dir.path(),
+            &manifest,
+            &account_id,
+            &location,
+            &save_roots,
+            Some(&rollback),
+        );
+        Ok::<_, String>((manifest.app_id, result, rollback.finish()))
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))??;
+
+    if let Some(id) = rollback_id {
+        eprintln!("[CloudSync] Overwritten local saves backed up as rollback {}", id);
+    }
+
+    Ok(SyncResult {
+        success: result.errors.is_empty(),
+        message: if result.errors.is_empty() {
+            format!("Imported {} save files of {}", result.files_restored, app_id)
+        } else {
+            format!(
+                "Imported {} save files of {}, {} failed: {}",
+                result.files_restored,
+                app_id,
+                result.errors.len(),
+                result.errors.join("; ")
+            )
+        },
+        files_uploaded: 0,
+        files_downloaded: result.files_restored,
+        files_deleted: 0,
+        conflicts: vec![],
+        planned: vec![],
+    })
+}
+
+// ============================================================================
+// Maintenance Commands
+// ============================================================================
+
+/// Bring remote data up to the current layout: games from before accounts
+/// were separated move into the account's namespace (along with their local
+/// sync state), uploads with the old flattened `__` naming move to the
+/// directory layout, and files on roots other than 0 move under their key
+/// (`.root<N>/<path>`). Migrates one game, or every game of the account on
+/// the remote or with a local sync state if `app_id` is omitted.
+#[tauri::command]
+pub async fn migrate_cloud_storage_layout(
+    app_id: Option<String>,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<LayoutMigrationResult, String> {
+    let config = match get_cloudsync_config(app_handle).await? {
+        Some(c) if c.enabled => c,
+        _ => return Err("CloudSync is not enabled".to_string()),
+    };
+
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    let app_ids = match app_id {
+        Some(id) => vec![id],
+        None => {
+            let backend = create_backend(&config)?;
+            let is_game_dir = |e: &RemoteEntry| {
+                e.is_dir && !e.name.is_empty() && e.name.chars().all(|c| c.is_ascii_digit())
+            };
+
+            let mut app_ids: Vec<String> = AccountStorage::new(create_backend(&config)?, &account_id)
+                .list("boilerroom")
+                .await?
+                .into_iter()
+                .filter(is_game_dir)
+                .map(|e| e.name)
+                .collect();
+
+            // Games from before accounts were separated sit next to the
+            // accounts folder
+            for entry in backend.list("boilerroom").await?.into_iter().filter(is_game_dir) {
+                if !app_ids.contains(&entry.name) {
+                    app_ids.push(entry.name);
+                }
+            }
+            for id in cloudsync_state::legacy_state_games() {
+                if !app_ids.contains(&id) {
+                    app_ids.push(id);
+                }
+            }
+            app_ids
+        }
+    };
+
+    let mut result = LayoutMigrationResult::default();
+    let backend = create_backend(&config)?;
+
+    for app_id in app_ids {
+        result.games_checked += 1;
+        match cloudsync_accounts::adopt_legacy_data(backend.as_ref(), &account_id, &app_id).await {
+            Ok(moved) => result.files_migrated += moved,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", app_id, e));
+                continue;
+            }
+        }
+        match cloudsync_state::adopt_legacy_state(&account_id, &app_id) {
+            Ok(adopted) => result.states_adopted += adopted as u32,
+            Err(e) => result.errors.push(format!("{}: {}", app_id, e)),
+        }
+
+        // Known save paths let names that really contain `__` survive the migration
+        let files = match collect_game_files(&app_id, &account_id, &config, false).await {
+            Ok(game_files) => game_files.files,
+            Err(_) => HashMap::new(),
+        };
+        let known_paths: Vec<String> = files.values().map(|f| f.path.clone()).collect();
+
+        let client = match cloudsync_accounts::create_account_storage(&config, &account_id).await {
+            Ok(client) => client,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", app_id, e));
+                continue;
+            }
+        };
+        match migrate_flat_layout(client.as_ref(), &app_id, &known_paths).await {
+            Ok(moved) => result.files_migrated += moved,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", app_id, e));
+                continue;
+            }
+        }
+
+        let renamed = match migrate_root_layout(client.as_ref(), &app_id, &files).await {
+            Ok(moves) => {
+                result.files_migrated += moves.len() as u32;
+                rename_synced_files(client.as_ref(), &account_id, &app_id, &moves).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = renamed {
+            result.errors.push(format!("{}: {}", app_id, e));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Follow files moved to new keys in the remote index and the local sync state
+async fn rename_synced_files(
+    client: &dyn CloudStorage,
+    account_id: &str,
+    app_id: &str,
+    moves: &[(String, String)],
+) -> Result<(), String> {
+    if moves.is_empty() {
+        return Ok(());
+    }
+
+    let mut index = cloudsync_state::load_remote_index(client, app_id).await;
+    let mut state = cloudsync_state::load_sync_state(account_id, app_id);
+    for (from, to) in moves {
+        if let Some(digest) = index.files.remove(from) {
+            index.files.insert(to.clone(), digest);
+        }
+        if let Some(digest) = state.files.remove(from) {
+            state.files.insert(to.clone(), digest);
+        }
+        // The copy is a new version of the file
+        state.remote_versions.remove(from);
+        for conflict in state.conflicts.iter_mut().filter(|c| *c == from) {
+            *conflict = to.clone();
+        }
+    }
+
+    cloudsync_state::save_remote_index(client, app_id, &index).await?;
+    cloudsync_state::save_sync_state(&state)
+}
+
+// ============================================================================
+// Encryption Commands
+// ============================================================================
+
+/// Store the encryption passphrase (outside the settings store).
+/// An empty passphrase removes it.
+#[tauri::command]
+pub async fn set_cloud_encryption_passphrase(passphrase: String) -> Result<(), String> {
+    if passphrase.is_empty() {
+        cloudsync_crypto::clear_passphrase()
+    } else {
+        cloudsync_crypto::save_passphrase(&passphrase)
+    }
+}
+
+/// Check whether an encryption passphrase is available
+#[tauri::command]
+pub async fn has_cloud_encryption_passphrase() -> Result<bool, String> {
+    Ok(cloudsync_crypto::load_passphrase().is_some())
+}
+
+/// Generate a new encryption key for a game and re-encrypt its remote set
+/// (saves, snapshots and index). Returns the number of files re-encrypted.
+#[tauri::command]
+pub async fn rotate_cloud_encryption_key(
+    app_id: String,
+    account_id: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<u32, String> {
+    let config = match get_cloudsync_config(app_handle).await? {
+        Some(c) if c.enabled => c,
+        _ => return Err("CloudSync is not enabled".to_string()),
+    };
+    if !config.encryption_enabled {
+        return Err("Encryption is not enabled".to_string());
+    }
+
+    let passphrase =
+        cloudsync_crypto::load_passphrase().ok_or("Encryption is enabled but no passphrase is set")?;
+    let account_id = cloudsync_accounts::resolve_account(account_id.as_deref())?;
+    let backend = create_backend(&config)?;
+    let storage = EncryptedStorage::new(Box::new(AccountStorage::new(backend, &account_id)), passphrase);
+    storage.rotate_key(&app_id).await
+}
+
+// ============================================================================
+// Watcher Commands
 // ============================================================================
 
 /// Start the cloud sync file watcher
@@ -537,16 +2055,24 @@ pub async fn start_cloud_watcher(
         }
     };
     
-    // Start watcher with event handler
-    let _handle = app_handle.clone();
+    let worker = match app_handle.try_state::<CloudSyncWorker>() {
+        Some(w) => w.inner().clone(),
+        None => return Err("CloudSync worker not initialized".to_string()),
+    };
+
+    // Sync each game when it exits too, not only on remotecache.vdf changes
+    if let Some(tracker) = app_handle.try_state::<GameSessionTracker>() {
+        tracker.track(&app_ids);
+    }
+
+    // Start watcher, handing every change to the background sync worker
     state.start(app_ids, move |event| {
         eprintln!(
             "[CloudSync] File changed: app_id={}, path={:?}",
             event.app_id,
             event.path
         );
-        // TODO: Trigger sync for this app_id
-        // This would emit an event to the frontend or directly call sync
+        worker.request_sync(&event.app_id);
     })?;
 
     Ok(())
@@ -558,6 +2084,9 @@ pub async fn stop_cloud_watcher(app_handle: tauri::AppHandle) -> Result<(), Stri
     if let Some(state) = app_handle.try_state::<CloudSyncWatcherState>() {
         state.stop();
     }
+    if let Some(tracker) = app_handle.try_state::<GameSessionTracker>() {
+        tracker.clear();
+    }
 
     Ok(())
 }
@@ -570,3 +2099,221 @@ pub async fn is_cloud_watcher_running(app_handle: tauri::AppHandle) -> Result<bo
         .map(|s| s.is_running())
         .unwrap_or(false))
 }
+
+// ============================================================================
+// Game Session Commands
+// ============================================================================
+
+/// Pull the latest remote saves, then launch the game through Steam.
+/// The pull is skipped if the game is already running; a failed pull is
+/// reported but doesn't stop the launch.
+#[tauri::command]
+pub async fn launch_game_with_cloud_sync(
+    app_id: String,
+    app_handle: tauri::AppHandle,
+) -> Result<SyncResult, String> {
+    let enabled = get_cloudsync_config(app_handle.clone())
+        .await?
+        .is_some_and(|c| c.enabled);
+    let location = cloudsync_roots::locate_game(&app_id);
+    let running = enabled && game_running(&app_id, &location).await?;
+
+    let result = if !enabled {
+        SyncResult {
+            success: true,
+            message: "CloudSync is disabled, launched without syncing".to_string(),
+            files_uploaded: 0,
+            files_downloaded: 0,
+            files_deleted: 0,
+            conflicts: vec![],
+            planned: vec![],
+        }
+    } else if running {
+        SyncResult {
+            success: true,
+            message: "Game is already running, skipped pre-launch sync".to_string(),
+            files_uploaded: 0,
+            files_downloaded: 0,
+            files_deleted: 0,
+            conflicts: vec![],
+            planned: vec![],
+        }
+    } else {
+        match run_game_sync(&app_id, None, SyncMode::DownloadOnly, &app_handle).await {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("[CloudSync] Pre-launch sync failed for {}: {}", app_id, e);
+                SyncResult {
+                    success: false,
+                    message: format!("Pre-launch sync failed: {}", e),
+                    files_uploaded: 0,
+                    files_downloaded: 0,
+                    files_deleted: 0,
+                    conflicts: vec![],
+                    planned: vec![],
+                }
+            }
+        }
+    };
+
+    // Sync the saves back once the game exits
+    if enabled {
+        if let Some(tracker) = app_handle.try_state::<GameSessionTracker>() {
+            tracker.track(std::slice::from_ref(&app_id));
+        }
+    }
+
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    std::process::Command::new(opener)
+        .arg(format!("steam://rungameid/{}", app_id))
+        .spawn()
+        .map_err(|e| format!("Failed to launch game: {}", e))?;
+    eprintln!("[CloudSync] Launched game {}", app_id);
+
+    Ok(result)
+}
+
+/// Check if a game is running
+#[tauri::command]
+pub async fn is_game_session_active(app_id: String) -> Result<bool, String> {
+    let location = cloudsync_roots::locate_game(&app_id);
+    game_running(&app_id, &location).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloudsync_storage::LocalStorage;
+
+    /// Every file under `root` with its content
+    fn tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        let mut files: Vec<(PathBuf, Vec<u8>)> = WalkDir::new(root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| (e.path().to_path_buf(), std::fs::read(e.path()).unwrap()))
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn fallback_file(path: &str, resolved: PathBuf) -> CloudFile {
+        CloudFile {
+            path: path.to_string(),
+            root: -1,
+            size: 0,
+            localtime: 0,
+            remotetime: 0,
+            sha: String::new(),
+            syncstate: 0,
+            resolved_path: Some(resolved),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_game_sync_writes_nothing() {
+        let remote = tempfile::tempdir().unwrap();
+        let backend = LocalStorage::new(remote.path().to_str().unwrap()).unwrap();
+        // Data from before accounts were separated stays where it is
+        backend.mkdir("boilerroom/730").await.unwrap();
+        backend.upload("boilerroom/730/slot1.sav", b"legacy".to_vec()).await.unwrap();
+        let storage = AccountStorage::new(Box::new(backend), "42");
+        storage.mkdir("boilerroom/.index").await.unwrap();
+        storage.upload("boilerroom/.index/730.json", b"{}".to_vec()).await.unwrap();
+
+        let saves = tempfile::tempdir().unwrap();
+        std::fs::write(saves.path().join("slot1.sav"), b"local").unwrap();
+        std::fs::write(saves.path().join("slot2.sav"), b"local").unwrap();
+        let mut game = GameFiles {
+            files: ["slot1.sav", "slot2.sav"]
+                .iter()
+                .map(|p| (p.to_string(), fallback_file(p, saves.path().join(p))))
+                .collect(),
+            user_id: "42".to_string(),
+            location: GameLocation::default(),
+            is_fallback: true,
+            save_roots: vec![saves.path().to_path_buf()],
+            config_roots: Vec::new(),
+        };
+        let config = CloudSyncConfig {
+            enabled: true,
+            provider: "local".to_string(),
+            local_path: remote.path().to_string_lossy().to_string(),
+            ..Default::default()
+        };
+
+        let remote_before = tree(remote.path());
+        let local_before = tree(saves.path());
+        let plan = plan_game_sync("730", "42", &config, &mut game).await.unwrap();
+
+        let paths: Vec<&str> = plan.iter().map(|p| p.path.as_str()).collect();
+        assert_eq!(paths, vec!["slot1.sav", "slot2.sav"]);
+        assert!(plan.iter().all(|p| p.action == SyncAction::Upload));
+        assert_eq!(tree(remote.path()), remote_before);
+        assert_eq!(tree(saves.path()), local_before);
+    }
+
+    #[test]
+    fn test_collect_root_files_skips_sync_artifacts() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("profiles")).unwrap();
+        for name in [
+            "slot1.sav",
+            "slot1.sav.boilerroom-part",
+            "slot1.sav.boilerroom-tmp",
+            "slot1.conflict-20240102T030405Z.sav",
+            "profiles/p1.dat",
+            "profiles/p1.dat.boilerroom-part",
+        ] {
+            std::fs::write(root.path().join(name), b"data").unwrap();
+        }
+
+        let mut files = HashMap::new();
+        collect_root_files(root.path(), "", &mut files);
+        collect_root_files(&root.path().join("slot1.sav.boilerroom-part"), "", &mut files);
+        let mut keys: Vec<&str> = files.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["profiles/p1.dat", "slot1.sav"]);
+    }
+
+    #[test]
+    fn test_add_missing_fallback_files() {
+        let saves = tempfile::tempdir().unwrap();
+        let slots = saves.path().join("Slots");
+        let profiles = saves.path().join("Profiles");
+        std::fs::create_dir_all(slots.join("auto")).unwrap();
+        std::fs::create_dir_all(profiles.join("p1")).unwrap();
+        std::fs::write(slots.join("slot1.sav"), b"local").unwrap();
+        let options = saves.path().join("options.ini");
+        let save_roots = vec![options.clone(), slots.clone(), profiles.clone()];
+        let config = saves.path().join("Config");
+        std::fs::create_dir_all(&config).unwrap();
+        let config_roots = vec![config.clone()];
+
+        let mut files = HashMap::new();
+        collect_root_files(&slots, "", &mut files);
+        let known: Vec<String> = [
+            "slot1.sav",
+            "slot2.sav",
+            "auto/slot3.sav",
+            "p1/profile.dat",
+            "options.ini",
+            "config/settings.ini",
+            "../escape.sav",
+        ]
+        .iter()
+        .map(|k| k.to_string())
+        .collect();
+        add_missing_fallback_files(&mut files, &save_roots, &config_roots, known.iter());
+
+        let path = |key: &str| files.get(key).and_then(|f| f.resolved_path.clone());
+        assert_eq!(path("slot1.sav"), Some(slots.join("slot1.sav")));
+        assert_eq!(path("slot2.sav"), Some(slots.join("slot2.sav")));
+        assert_eq!(path("auto/slot3.sav"), Some(slots.join("auto/slot3.sav")));
+        assert_eq!(path("p1/profile.dat"), Some(profiles.join("p1/profile.dat")));
+        assert_eq!(path("options.ini"), Some(options));
+        assert_eq!(path("config/settings.ini"), Some(config.join("settings.ini")));
+        assert_eq!(path("../escape.sav"), None);
+        assert!(files.values().all(|f| f.root == -1));
+    }
+}
@@ -1,6 +1,7 @@
 //! Depot keys only install command - configures Steam without downloading
 
 use super::connection::SshConfig;
+use crate::vdf::AppManifest;
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 use std::net::TcpStream;
@@ -90,7 +91,7 @@ pub async fn install_depot_keys_only(
             }
         }
 
-        let acf_content = build_acf_state_flags_6(&app_id, &game_name);
+        let acf_content = build_acf_state_flags_6(&app_id, &game_name)?;
         let acf_path =
             shellexpand::tilde(&format!("{}/appmanifest_{}.acf", steamapps_path, app_id))
                 .to_string();
@@ -187,7 +188,7 @@ pub async fn install_depot_keys_only(
         }
 
         // Create ACF
-        let acf_content = build_acf_state_flags_6(&app_id, &game_name);
+        let acf_content = build_acf_state_flags_6(&app_id, &game_name)?;
         let acf_remote_path = format!("{}/appmanifest_{}.acf", steamapps_path, app_id);
 
         if let Ok(mut channel) = sess.channel_session() {
@@ -246,7 +247,7 @@ pub async fn install_depot_keys_only(
 }
 
 /// Build ACF content with StateFlags=6 (Update Required)
-fn build_acf_state_flags_6(app_id: &str, game_name: &str) -> String {
+fn build_acf_state_flags_6(app_id: &str, game_name: &str) -> Result<String, String> {
     let install_dir: String = game_name
         .chars()
         .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
@@ -258,32 +259,17 @@ fn build_acf_state_flags_6(app_id: &str, game_name: &str) -> String {
         install_dir
     };
 
-    format!(
-        r#""AppState"
-{{
-	"appid"		"{app_id}"
-	"Universe"		"1"
-	"name"		"{game_name}"
-	"StateFlags"		"6"
-	"installdir"		"{install_dir}"
-	"SizeOnDisk"		"0"
-	"buildid"		"0"
-	"InstalledDepots"
-	{{
-	}}
-	"UserConfig"
-	{{
-		"platform_override_dest"		"linux"
-		"platform_override_source"		"windows"
-	}}
-	"MountedConfig"
-	{{
-		"platform_override_dest"		"linux"
-		"platform_override_source"		"windows"
-	}}
-}}"#,
-        app_id = app_id,
-        game_name = game_name,
-        install_dir = install_dir
-    )
+    AppManifest {
+        appid: app_id.to_string(),
+        universe: Some(1),
+        name: Some(game_name.to_string()),
+        state_flags: Some(6),
+        installdir: install_dir.to_string(),
+        size_on_disk: Some(0),
+        buildid: Some("0".to_string()),
+        user_config: Some(AppManifest::windows_platform_override()),
+        mounted_config: Some(AppManifest::windows_platform_override()),
+        ..Default::default()
+    }
+    .to_vdf()
 }
@@ -2,6 +2,7 @@
 
 use super::connection::SshConfig;
 use super::slssteam::ssh_exec;
+use crate::vdf::{AppManifest, LibraryFolders};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::Read;
@@ -27,41 +28,26 @@ pub struct InstalledDepot {
     pub manifest_id: String,
 }
 
-/// Helper to extract library paths from VDF content
+/// Library directories listed in libraryfolders.vdf content
 fn extract_library_paths_from_vdf(content: &str) -> Vec<String> {
-    let mut paths = Vec::new();
-    let mut tokens = Vec::new();
-    let mut in_quote = false;
-    let mut current_token = String::new();
-
-    for c in content.chars() {
-        if c == '"' {
-            if in_quote {
-                tokens.push(current_token.clone());
-                current_token.clear();
-                in_quote = false;
-            } else {
-                in_quote = true;
-            }
-        } else if in_quote {
-            current_token.push(c);
-        }
-    }
-
-    let mut i = 0;
-    while i < tokens.len() {
-        if tokens[i] == "path" && i + 1 < tokens.len() {
-            let path = &tokens[i + 1];
-            if !path.is_empty() {
-                paths.push(path.clone());
-            }
-            i += 2;
-        } else {
-            i += 1;
-        }
-    }
+    LibraryFolders::parse(content)
+        .map(|library| library.paths())
+        .unwrap_or_default()
+}
 
-    paths
+/// Line printed between the app manifests of a remote library
+const MANIFEST_SEPARATOR: &str = "--boilerroom-acf--";
+
+/// installdir -> appid of the app manifests a remote library printed, one
+/// after the other with `MANIFEST_SEPARATOR` between them
+fn remote_app_manifests(output: &str) -> HashMap<String, String> {
+    output
+        .split(MANIFEST_SEPARATOR)
+        .filter(|content| !content.trim().is_empty())
+        .filter_map(|content| AppManifest::parse(content).ok())
+        .filter(|manifest| !manifest.appid.is_empty() && !manifest.installdir.is_empty())
+        .map(|manifest| (manifest.installdir, manifest.appid))
+        .collect()
 }
 
 /// Helper function to parse Steam library paths from libraryfolders.vdf
@@ -116,28 +102,12 @@ pub async fn list_installed_games(config: SshConfig) -> Result<Vec<InstalledGame
         let common_path = format!("{}/common", steamapps_path);
 
         let acf_cmd = format!(
-            "for f in {}/appmanifest_*.acf; do \
-                if [ -f \"$f\" ]; then \
-                    appid=$(grep -m1 '\"appid\"' \"$f\" | grep -oE '[0-9]+'); \
-                    installdir=$(grep -m1 '\"installdir\"' \"$f\" | sed 's/.*\"installdir\"[[:space:]]*\"\\([^\"]*\\)\".*/\\1/'); \
-                    echo \"$installdir|$appid\"; \
-                fi; \
+            "for f in '{}'/appmanifest_*.acf; do \
+                [ -f \"$f\" ] && cat \"$f\" && echo && echo '{}'; \
             done 2>/dev/null",
-            steamapps_path
+            steamapps_path, MANIFEST_SEPARATOR
         );
-
-        let acf_output = ssh_exec(&sess, &acf_cmd)?;
-        let mut installdir_to_appid: HashMap<String, String> = HashMap::new();
-        for line in acf_output.lines() {
-            let parts: Vec<&str> = line.split('|').collect();
-            if parts.len() == 2 {
-                let installdir = parts[0].trim().to_string();
-                let appid = parts[1].trim().to_string();
-                if !installdir.is_empty() && !appid.is_empty() {
-                    installdir_to_appid.insert(installdir, appid);
-                }
-            }
-        }
+        let installdir_to_appid = remote_app_manifests(&ssh_exec(&sess, &acf_cmd)?);
 
         let list_cmd = format!("ls -1 '{}' 2>/dev/null || echo ''", common_path);
         let output = ssh_exec(&sess, &list_cmd)?;
@@ -181,15 +151,9 @@ pub async fn list_installed_games(config: SshConfig) -> Result<Vec<InstalledGame
     Ok(games)
 }
 
-#[tauri::command]
-pub async fn list_installed_games_local() -> Result<Vec<InstalledGame>, String> {
+/// steamapps directories of the local Steam libraries
+fn local_steamapps_dirs(home: &Path) -> Vec<PathBuf> {
     use std::collections::HashSet;
-    use std::fs;
-    use walkdir::WalkDir;
-
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    let mut games = Vec::new();
-    let steam_user_id = crate::cloudsync::get_steam_user_id();
 
     let primary_steam_path = if cfg!(target_os = "macos") {
         home.join("Library/Application Support/Steam")
@@ -206,7 +170,7 @@ pub async fn list_installed_games_local() -> Result<Vec<InstalledGame>, String>
     }
 
     let vdf_path = primary_steam_path.join("steamapps/libraryfolders.vdf");
-    if let Ok(content) = fs::read_to_string(&vdf_path) {
+    if let Ok(content) = std::fs::read_to_string(&vdf_path) {
         for path_str in extract_library_paths_from_vdf(&content) {
             let p = Path::new(&path_str);
             let steamapps = if let Ok(canonical) = std::fs::canonicalize(p) {
@@ -220,46 +184,63 @@ pub async fn list_installed_games_local() -> Result<Vec<InstalledGame>, String>
         }
     }
 
-    let library_paths: Vec<PathBuf> = library_paths_set.into_iter().collect();
+    library_paths_set.into_iter().collect()
+}
 
-    for steamapps in library_paths {
-        if !steamapps.exists() {
-            continue;
-        }
+/// (appid, installdir) of every app manifest in a steamapps directory
+fn local_app_manifests(steamapps: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(steamapps) else {
+        return Vec::new();
+    };
 
-        let mut installdir_to_appid: HashMap<String, String> = HashMap::new();
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("appmanifest_") && name.ends_with(".acf")
+        })
+        .filter_map(|entry| {
+            let content = std::fs::read_to_string(entry.path()).ok()?;
+            let manifest = AppManifest::parse(&content).ok()?;
+            (!manifest.appid.is_empty() && !manifest.installdir.is_empty())
+                .then_some((manifest.appid, manifest.installdir))
+        })
+        .collect()
+}
 
-        if let Ok(entries) = fs::read_dir(&steamapps) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if name.starts_with("appmanifest_") && name.ends_with(".acf") {
-                        if let Ok(content) = fs::read_to_string(&path) {
-                            let mut appid = String::new();
-                            let mut installdir = String::new();
-
-                            for line in content.lines() {
-                                if line.contains("\"appid\"") {
-                                    if let Some(id) = line.split('"').nth(3) {
-                                        appid = id.to_string();
-                                    }
-                                }
-                                if line.contains("\"installdir\"") {
-                                    if let Some(dir) = line.split('"').nth(3) {
-                                        installdir = dir.to_string();
-                                    }
-                                }
-                            }
+/// Install directory of a locally installed game, from the same library scan
+/// as `list_installed_games_local`
+pub(crate) fn local_install_dir(app_id: &str) -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    local_steamapps_dirs(&home).into_iter().find_map(|steamapps| {
+        local_app_manifests(&steamapps)
+            .into_iter()
+            .find(|(appid, _)| appid == app_id)
+            .map(|(_, installdir)| steamapps.join("common").join(installdir))
+            .filter(|dir| dir.is_dir())
+    })
+}
 
-                            if !appid.is_empty() && !installdir.is_empty() {
-                                installdir_to_appid.insert(installdir, appid);
-                            }
-                        }
-                    }
-                }
-            }
+#[tauri::command]
+pub async fn list_installed_games_local() -> Result<Vec<InstalledGame>, String> {
+    use std::fs;
+    use walkdir::WalkDir;
+
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    let mut games = Vec::new();
+    let steam_user_id = crate::cloudsync::get_steam_user_id();
+
+    for steamapps in local_steamapps_dirs(&home) {
+        if !steamapps.exists() {
+            continue;
         }
 
+        let installdir_to_appid: HashMap<String, String> = local_app_manifests(&steamapps)
+            .into_iter()
+            .map(|(appid, installdir)| (installdir, appid))
+            .collect();
+
         let common_path = steamapps.join("common");
         if !common_path.exists() {
             continue;
@@ -591,6 +572,16 @@ fn remove_app_from_config(content: &str, app_id: &str) -> Result<String, String>
     serde_yaml::to_string(&doc).map_err(|e| format!("Failed to serialize YAML: {}", e))
 }
 
+/// PCGamingWiki metadata of a game: developers, engine, graphics APIs,
+/// config file locations, fixes and Linux notes
+#[tauri::command]
+pub async fn get_game_metadata(
+    app_id: String,
+) -> Result<crate::pcgamingwiki_metadata::GameMetadata, String> {
+    let steam_user_id = crate::cloudsync::get_steam_user_id();
+    crate::pcgamingwiki_metadata::game_metadata(&app_id, steam_user_id.as_deref()).await
+}
+
 #[tauri::command]
 pub async fn check_game_update(
     app_id: String,
@@ -612,3 +603,22 @@ pub async fn check_game_update(
 }
 
 use std::io::Write;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_app_manifests() {
+        let output = format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\t\"1245620\"\n\t\"installdir\"\t\t\"ELDEN RING\"\n}}\n\n{sep}\n\
+             \"AppState\" {{ \"appid\" \"70\" \"installdir\" \"Half \\\"Life\\\"\" }}\n{sep}\n\
+             not a manifest\n{sep}\n",
+            sep = MANIFEST_SEPARATOR
+        );
+        let manifests = remote_app_manifests(&output);
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests["ELDEN RING"], "1245620");
+        assert_eq!(manifests["Half \"Life\""], "70");
+    }
+}
@@ -1,5 +1,6 @@
 //! SteamCMD integration commands
 
+use crate::vdf;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process::{Command, Stdio};
@@ -54,97 +55,41 @@ pub async fn steamcmd_get_app_info(app_id: String) -> Result<AppSteamInfo, Strin
 }
 
 fn parse_steamcmd_output(app_id: &str, output: &str) -> Result<AppSteamInfo, String> {
+    let block = vdf::find_block(output, app_id).ok_or("App info not found in output")?;
+    let app_info = vdf::parse(block)?;
+    let app = app_info.value.get_obj().ok_or("App info is not a block")?;
+    let text = |path: &[&str]| vdf::lookup_str(app, path).map(|value| value.to_string());
+
     let mut info = AppSteamInfo {
         app_id: app_id.to_string(),
-        name: None,
-        oslist: None,
-        installdir: None,
+        name: text(&["common", "name"]),
+        oslist: text(&["common", "oslist"]),
+        installdir: text(&["config", "installdir"]),
         depots: HashMap::new(),
     };
 
-    let app_marker = format!("\"{}\"", app_id);
-    let start_idx = match output.find(&app_marker) {
-        Some(idx) => idx,
-        None => return Err("App info not found in output".to_string()),
-    };
-
-    let app_section = &output[start_idx..];
-
-    for line in app_section.lines() {
-        let trimmed = line.trim();
-        if let Some((key, value)) = parse_vdf_line(trimmed) {
-            match key.as_str() {
-                "name" if info.name.is_none() => info.name = Some(value),
-                "oslist" if info.oslist.is_none() => info.oslist = Some(value),
-                "installdir" if info.installdir.is_none() => info.installdir = Some(value),
-                _ => {}
-            }
-        }
-    }
-
-    let mut in_depots = false;
-    let mut current_depot_id: Option<String> = None;
-    let mut brace_depth = 0;
-
-    for line in app_section.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.contains("\"depots\"") {
-            in_depots = true;
+    // "depots" also holds "branches" and "baselanguages" next to the depot IDs
+    let depots = vdf::get_obj(app, "depots").into_iter().flat_map(|depots| depots.iter());
+    for (depot_id, values) in depots {
+        let Some(depot) = values.first().and_then(|v| v.get_obj()) else {
             continue;
-        }
-
-        if !in_depots {
+        };
+        if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
             continue;
         }
-
-        if trimmed == "{" {
-            brace_depth += 1;
-            continue;
-        }
-        if trimmed == "}" {
-            brace_depth -= 1;
-            if brace_depth == 0 {
-                in_depots = false;
-                current_depot_id = None;
-            } else if brace_depth == 1 {
-                current_depot_id = None;
-            }
-            continue;
-        }
-
-        if brace_depth == 1 {
-            if let Some(depot_id) = parse_depot_id(trimmed) {
-                current_depot_id = Some(depot_id.clone());
-                info.depots.insert(
-                    depot_id,
-                    DepotSteamInfo {
-                        name: None,
-                        oslist: None,
-                        size: None,
-                    },
-                );
-            }
-        }
-
-        if brace_depth >= 2 {
-            if let Some(ref depot_id) = current_depot_id {
-                if let Some((key, value)) = parse_vdf_line(trimmed) {
-                    if let Some(depot) = info.depots.get_mut(depot_id) {
-                        match key.as_str() {
-                            "name" => depot.name = Some(value),
-                            "oslist" => depot.oslist = Some(value),
-                            "size" | "download" => {
-                                if let Ok(size) = value.parse::<u64>() {
-                                    depot.size = Some(size);
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-            }
-        }
+        let size = ["size", "download"]
+            .iter()
+            .find_map(|key| vdf::lookup_str(depot, &["manifests", "public", key]))
+            .or_else(|| vdf::get_str(depot, "maxsize"))
+            .and_then(|size| size.parse().ok());
+        info.depots.insert(
+            depot_id.to_string(),
+            DepotSteamInfo {
+                name: vdf::get_str(depot, "name").map(|name| name.to_string()),
+                oslist: vdf::lookup_str(depot, &["config", "oslist"]).map(|os| os.to_string()),
+                size,
+            },
+        );
     }
 
     eprintln!(
@@ -156,35 +101,54 @@ fn parse_steamcmd_output(app_id: &str, output: &str) -> Result<AppSteamInfo, Str
     Ok(info)
 }
 
-fn parse_vdf_line(line: &str) -> Option<(String, String)> {
-    let line = line.trim();
-    if !line.starts_with('"') {
-        return None;
-    }
-
-    let parts: Vec<&str> = line.split('"').collect();
-    if parts.len() >= 4 {
-        let key = parts[1].to_string();
-        let value = parts[3].to_string();
-        return Some((key, value));
-    }
-
-    None
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_steamcmd_output() {
+        let output = r#"Connecting anonymously to Steam Public...OK
+AppID : 730, change number : 20000000/0, last change : Mon Jan  1 00:00:00 2024
+"730"
+{
+	"common"
+	{
+		"name"		"Counter-Strike 2"
+		"oslist"		"windows,linux"
+	}
+	"config"
+	{
+		"installdir"		"Counter-Strike Global Offensive"
+	}
+	"depots"
+	{
+		"2347771"
+		{
+			"config" { "oslist"		"windows" }
+			"manifests"
+			{
+				"public"
+				{
+					"gid"		"1"
+					"size"		"1000"
+					"download"		"400"
+				}
+			}
+		}
+		"branches" { "public" { "buildid" "1" } }
+		"baselanguages"		"english"
+	}
 }
-
-fn parse_depot_id(line: &str) -> Option<String> {
-    let line = line.trim();
-    if !line.starts_with('"') {
-        return None;
+Unloading Steam API...OK
+"#;
+        let info = parse_steamcmd_output("730", output).unwrap();
+        assert_eq!(info.name.as_deref(), Some("Counter-Strike 2"));
+        assert_eq!(info.installdir.as_deref(), Some("Counter-Strike Global Offensive"));
+        assert_eq!(info.depots.len(), 1);
+        let depot = &info.depots["2347771"];
+        assert_eq!(depot.oslist.as_deref(), Some("windows"));
+        assert_eq!(depot.size, Some(1000));
+
+        assert!(parse_steamcmd_output("570", output).is_err());
     }
-
-    let parts: Vec<&str> = line.split('"').collect();
-    if parts.len() >= 2 {
-        let id = parts[1];
-        if id.chars().all(|c| c.is_ascii_digit()) && !id.is_empty() {
-            return Some(id.to_string());
-        }
-    }
-
-    None
 }
@@ -1,6 +1,7 @@
 //! Transfer commands - rsync game copy to remote Steam Deck
 
 use super::connection::SshConfig;
+use crate::vdf::AppManifest;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::PathBuf;
@@ -204,28 +205,27 @@ pub async fn copy_game_to_remote(
                 // Create ACF manifest
                 let steamapps_dir = remote_path.trim_end_matches('/').trim_end_matches("/common");
                 let acf_path = format!("{}/appmanifest_{}.acf", steamapps_dir, app_id);
-                let acf_content = format!(
-                    r#""AppState"
-{{
-	"appid"		"{app_id}"
-	"Universe"		"1"
-	"name"		"{game_name}"
-	"StateFlags"		"4"
-	"installdir"		"{folder_name}"
-	"UserConfig"
-	{{
-		"platform_override_dest"		"linux"
-		"platform_override_source"		"windows"
-	}}
-}}"#,
-                    app_id = app_id, game_name = game_name, folder_name = folder_name
-                );
-
-                if let Ok(mut channel) = sess.channel_session() {
-                    if channel.exec(&format!("cat > \"{}\"", acf_path)).is_ok() {
-                        let _ = channel.write_all(acf_content.as_bytes());
-                        let _ = channel.send_eof();
-                        let _ = channel.wait_close();
+                let acf_content = AppManifest {
+                    appid: app_id.clone(),
+                    universe: Some(1),
+                    name: Some(game_name.clone()),
+                    state_flags: Some(4),
+                    installdir: folder_name.clone(),
+                    user_config: Some(AppManifest::windows_platform_override()),
+                    ..Default::default()
+                }
+                .to_vdf();
+
+                match acf_content {
+                    Err(e) => eprintln!("[ACF] Failed to create {}: {}", acf_path, e),
+                    Ok(acf_content) => {
+                        if let Ok(mut channel) = sess.channel_session() {
+                            if channel.exec(&format!("cat > \"{}\"", acf_path)).is_ok() {
+                                let _ = channel.write_all(acf_content.as_bytes());
+                                let _ = channel.send_eof();
+                                let _ = channel.wait_close();
+                            }
+                        }
                     }
                 }
             }
@@ -1,13 +1,32 @@
 //! VDF (Valve Data Format) parser for config.vdf manipulation
 //! This module handles adding DecryptionKey entries to Steam's config.vdf
 
+use crate::vdf;
+use keyvalues_parser::Obj;
 use std::collections::HashSet;
 
+/// Where Steam keeps depot keys: InstallConfigStore > Software > Valve > Steam > depots
+const DEPOTS_PATH: [&str; 4] = ["Software", "Valve", "Steam", "depots"];
+
 /// Add decryption keys to config.vdf content
-/// Returns the modified content with new keys added (avoiding duplicates)
+/// Returns the modified content with new keys added (avoiding duplicates).
+/// Only the depots section is touched; the rest of the file, comments and
+/// key order included, is kept as it was.
 pub fn add_decryption_keys_to_vdf(content: &str, depot_keys: &[(String, String)]) -> String {
+    let config = match vdf::parse(content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[config_vdf] Warning: Could not parse config.vdf: {}", e);
+            return content.to_string();
+        }
+    };
+    let Some(store) = config.value.get_obj() else {
+        eprintln!("[config_vdf] Warning: config.vdf has no InstallConfigStore block");
+        return content.to_string();
+    };
+
     // Build set of existing depot IDs to avoid duplicates
-    let existing_depots = extract_existing_depot_ids(content);
+    let existing_depots = extract_existing_depot_ids(store);
 
     // Filter out depots that already exist
     let new_keys: Vec<_> = depot_keys
@@ -20,203 +39,150 @@ pub fn add_decryption_keys_to_vdf(content: &str, depot_keys: &[(String, String)]
         return content.to_string();
     }
 
+    let mut path = vec![config.key.as_ref()];
+    path.extend(DEPOTS_PATH);
+    let Some((depth, close)) = find_block_end(content, &path) else {
+        eprintln!("[config_vdf] Warning: config.vdf has no InstallConfigStore block");
+        return content.to_string();
+    };
+
     eprintln!(
         "[config_vdf] Adding {} new depot keys to config.vdf",
         new_keys.len()
     );
 
-    // Build the new depot entries
-    let new_entries = build_depot_entries(&new_keys);
+    // Missing sections along the way are created
+    let mut lines = Vec::new();
+    for (level, key) in path.iter().enumerate().skip(depth) {
+        let indent = "\t".repeat(level);
+        lines.push(format!("{}\"{}\"", indent, key));
+        lines.push(format!("{}{{", indent));
+    }
+    let indent = "\t".repeat(path.len());
+    for (depot_id, key) in new_keys {
+        lines.push(format!("{}\"{}\"", indent, depot_id));
+        lines.push(format!("{}{{", indent));
+        lines.push(format!("{}\t\"DecryptionKey\"\t\t\"{}\"", indent, key));
+        lines.push(format!("{}}}", indent));
+    }
+    for level in (depth..path.len()).rev() {
+        lines.push(format!("{}}}", "\t".repeat(level)));
+    }
+    let entries = lines.join("\n");
 
-    // Find where to insert: look for "depots" section
-    if let Some(insert_pos) = find_depots_insert_position(content) {
-        let mut result = String::with_capacity(content.len() + new_entries.len());
-        result.push_str(&content[..insert_pos]);
-        result.push_str(&new_entries);
-        result.push_str(&content[insert_pos..]);
-        result
+    // Go in before the closing brace, on its own line
+    let line_start = content[..close].rfind('\n').map_or(0, |i| i + 1);
+    if content[line_start..close].trim().is_empty() {
+        format!("{}{}\n{}", &content[..line_start], entries, &content[line_start..])
     } else {
-        // No "depots" section found - need to add full nested structure:
-        // InstallConfigStore > Software > Valve > Steam > depots
-        if let Some(insert_pos) = find_steam_section_position(content) {
-            // Found "Steam" section inside Software > Valve, add depots inside it
-            let mut result = String::with_capacity(content.len() + new_entries.len() + 100);
-            result.push_str(&content[..insert_pos]);
-            result.push_str("\t\t\t\t\"depots\"\n\t\t\t\t{\n");
-            result.push_str(&new_entries);
-            result.push_str("\t\t\t\t}\n");
-            result.push_str(&content[insert_pos..]);
-            result
-        } else if let Some(insert_pos) = find_install_config_store_position(content) {
-            // No Steam section, create full nested structure
-            let mut result = String::with_capacity(content.len() + new_entries.len() + 200);
-            result.push_str(&content[..insert_pos]);
-            result.push_str("\t\"Software\"\n\t{\n");
-            result.push_str("\t\t\"Valve\"\n\t\t{\n");
-            result.push_str("\t\t\t\"Steam\"\n\t\t\t{\n");
-            result.push_str("\t\t\t\t\"depots\"\n\t\t\t\t{\n");
-            result.push_str(&new_entries);
-            result.push_str("\t\t\t\t}\n");
-            result.push_str("\t\t\t}\n");
-            result.push_str("\t\t}\n");
-            result.push_str("\t}\n");
-            result.push_str(&content[insert_pos..]);
-            result
-        } else {
-            // Fallback: append at the end (shouldn't happen with valid config.vdf)
-            eprintln!("[config_vdf] Warning: Could not find proper insertion point");
-            content.to_string()
-        }
+        format!(
+            "{}\n{}\n{}{}",
+            &content[..close],
+            entries,
+            "\t".repeat(depth - 1),
+            &content[close..]
+        )
     }
 }
 
-/// Extract depot IDs that already exist in the config.vdf
-fn extract_existing_depot_ids(content: &str) -> HashSet<String> {
-    let mut depots = HashSet::new();
-
-    // Look for pattern: "depots" followed by depot ID blocks
-    // Format:
-    // "depots"
-    // {
-    //     "1234567"
-    //     {
-
-    let mut in_depots_section = false;
-    let mut brace_depth = 0;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.contains("\"depots\"") && !trimmed.starts_with("//") {
-            in_depots_section = true;
-            continue;
-        }
-
-        if in_depots_section {
-            if trimmed == "{" {
-                brace_depth += 1;
-            } else if trimmed == "}" {
-                brace_depth -= 1;
-                if brace_depth == 0 {
-                    in_depots_section = false;
+/// Deepest block along `path` (keys matched case-insensitively): how many
+/// keys of the path were found, and the offset of that block's closing brace
+fn find_block_end(text: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let mut found: Option<(usize, usize)> = None;
+    let mut open: Vec<String> = Vec::new();
+    let mut key: Option<String> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut token = String::new();
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                    token.push(c);
+                }
+                key = if key.is_none() { Some(token) } else { None };
+            }
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' => open.push(key.take().unwrap_or_default()),
+            '}' => {
+                let matched = open.len() <= path.len()
+                    && open
+                        .iter()
+                        .zip(path)
+                        .all(|(key, want)| key.eq_ignore_ascii_case(want));
+                if matched && found.is_none_or(|(depth, _)| open.len() > depth) {
+                    found = Some((open.len(), i));
                 }
-            } else if brace_depth == 1 {
-                // At depth 1, we should see depot IDs like "1234567"
-                if let Some(depot_id) = extract_quoted_string(trimmed) {
-                    // Verify it's a numeric depot ID
-                    if depot_id.chars().all(|c| c.is_ascii_digit()) {
-                        depots.insert(depot_id);
+                open.pop()?;
+                key = None;
+            }
+            // Conditionals like [$WIN32] belong to the key/value before them
+            '[' => {
+                for (_, c) in chars.by_ref() {
+                    if c == ']' {
+                        break;
                     }
                 }
             }
+            c if c.is_whitespace() || c == '\u{feff}' => {}
+            // Unquoted token
+            _ => {
+                let mut token = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"\"{}".contains(*c))
+                {
+                    token.push(c);
+                }
+                key = if key.is_none() { Some(token) } else { None };
+            }
         }
     }
 
-    depots
+    found.filter(|(depth, _)| *depth > 0)
 }
 
-/// Build VDF entries for the new depot keys
-/// Uses 5 levels of indentation to match Steam's config.vdf structure:
-/// InstallConfigStore > Software > Valve > Steam > depots > {depot_id}
-fn build_depot_entries(keys: &[&(String, String)]) -> String {
-    let mut result = String::new();
-
-    for (depot_id, key) in keys {
-        // 5 tabs = inside: InstallConfigStore > Software > Valve > Steam > depots
-        result.push_str(&format!(
-            "\t\t\t\t\t\"{}\"\n\t\t\t\t\t{{\n\t\t\t\t\t\t\"DecryptionKey\"\t\t\"{}\"\n\t\t\t\t\t}}\n",
-            depot_id, key
-        ));
-    }
-
-    result
-}
-
-/// Find the position to insert new depot entries (after "depots" { )
-fn find_depots_insert_position(content: &str) -> Option<usize> {
-    // Find "depots" line
-    let depots_pattern = "\"depots\"";
-    let depots_pos = content.find(depots_pattern)?;
-
-    // Find the opening brace after "depots"
-    let after_depots = &content[depots_pos..];
-    let brace_pos = after_depots.find('{')?;
-
-    // Position is right after the opening brace and its newline
-    let absolute_pos = depots_pos + brace_pos + 1;
-
-    // Skip any whitespace/newline after the brace
-    let remainder = &content[absolute_pos..];
-    let skip = remainder
-        .chars()
-        .take_while(|c| *c == '\n' || *c == '\r')
-        .count();
-
-    Some(absolute_pos + skip)
-}
+/// Every depot block of every "depots" section, with its depot ID
+fn depot_entries<'a>(obj: &'a Obj<'a>) -> Vec<(&'a str, &'a Obj<'a>)> {
+    let mut entries = Vec::new();
 
-/// Find position after "InstallConfigStore" { to add new "depots" section
-fn find_install_config_store_position(content: &str) -> Option<usize> {
-    let pattern = "\"InstallConfigStore\"";
-    let pos = content.find(pattern)?;
-
-    let after = &content[pos..];
-    let brace_pos = after.find('{')?;
-    let absolute_pos = pos + brace_pos + 1;
-
-    // Skip newline
-    let remainder = &content[absolute_pos..];
-    let skip = remainder
-        .chars()
-        .take_while(|c| *c == '\n' || *c == '\r')
-        .count();
-
-    Some(absolute_pos + skip)
-}
-
-/// Find position after "Steam" section { inside Software > Valve > Steam
-/// This is the correct location for the "depots" section in config.vdf
-fn find_steam_section_position(content: &str) -> Option<usize> {
-    // Look for "Steam" section that's inside "Valve" (not standalone)
-    // The pattern in a real config.vdf is:
-    // "Software" > "Valve" > "Steam" > { ... }
-    let pattern = "\"Steam\"";
-
-    // Find all occurrences and look for one that's inside Valve section
-    let mut search_start = 0;
-    while let Some(pos) = content[search_start..].find(pattern) {
-        let absolute_pos = search_start + pos;
-
-        // Check if this is likely inside a Valve section by looking at prior content
-        let prior = &content[..absolute_pos];
-        if prior.contains("\"Valve\"") && prior.contains("\"Software\"") {
-            // Found Steam inside Software > Valve structure
-            let after = &content[absolute_pos..];
-            if let Some(brace_pos) = after.find('{') {
-                let insert_pos = absolute_pos + brace_pos + 1;
-
-                // Skip newline after brace
-                let remainder = &content[insert_pos..];
-                let skip = remainder
-                    .chars()
-                    .take_while(|c| *c == '\n' || *c == '\r')
-                    .count();
-
-                return Some(insert_pos + skip);
+    for (key, values) in obj.iter() {
+        for block in values.iter().filter_map(|v| v.get_obj()) {
+            if !key.eq_ignore_ascii_case("depots") {
+                entries.extend(depot_entries(block));
+                continue;
             }
+            // Depot IDs are numeric
+            entries.extend(
+                block
+                    .iter()
+                    .filter(|(id, _)| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+                    .filter_map(|(id, values)| Some((id.as_ref(), values.first()?.get_obj()?))),
+            );
         }
-
-        search_start = absolute_pos + pattern.len();
     }
 
-    None
+    entries
 }
 
-/// Extract a quoted string value from a VDF line
-fn extract_quoted_string(line: &str) -> Option<String> {
-    let start = line.find('"')? + 1;
-    let end = line[start..].find('"')? + start;
-    Some(line[start..end].to_string())
+/// Extract depot IDs that already exist in the config.vdf
+fn extract_existing_depot_ids(config: &Obj<'_>) -> HashSet<String> {
+    depot_entries(config)
+        .into_iter()
+        .map(|(depot_id, _)| depot_id.to_string())
+        .collect()
 }
 
 /// Extract depot decryption keys from config.vdf that match an app_id range
@@ -225,67 +191,39 @@ fn extract_quoted_string(line: &str) -> Option<String> {
 /// Returns a Vec of (depot_id, decryption_key) pairs that were found
 #[allow(dead_code)]
 pub fn extract_depot_keys_by_app_id(content: &str, app_id: &str) -> Vec<(String, String)> {
-    let mut result = Vec::new();
-
     let app_id_num: u64 = match app_id.parse() {
         Ok(n) => n,
-        Err(_) => return result,
+        Err(_) => return Vec::new(),
+    };
+    let config = match vdf::parse(content) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[config_vdf] Warning: Could not parse config.vdf: {}", e);
+            return Vec::new();
+        }
     };
 
     // Also include the app_id itself as a depot (main depot)
     let min_depot = app_id_num;
     let max_depot = app_id_num + 100; // Depots are typically app_id, app_id+1, app_id+2, etc.
 
-    // Parse the depots section looking for matching depot IDs
-    let mut in_depots_section = false;
-    let mut brace_depth = 0;
-    let mut current_depot_id: Option<String> = None;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
-
-        if trimmed.contains("\"depots\"") && !trimmed.starts_with("//") {
-            in_depots_section = true;
-            continue;
-        }
-
-        if in_depots_section {
-            if trimmed == "{" {
-                brace_depth += 1;
-            } else if trimmed == "}" {
-                brace_depth -= 1;
-                if brace_depth == 1 {
-                    current_depot_id = None; // Exiting a depot block
-                }
-                if brace_depth == 0 {
-                    in_depots_section = false;
-                }
-            } else if brace_depth == 1 {
-                // At depth 1, we should see depot IDs like "1234567"
-                if let Some(depot_id) = extract_quoted_string(trimmed) {
-                    if depot_id.chars().all(|c| c.is_ascii_digit()) {
-                        if let Ok(depot_num) = depot_id.parse::<u64>() {
-                            if depot_num >= min_depot && depot_num <= max_depot {
-                                current_depot_id = Some(depot_id);
-                            }
-                        }
-                    }
-                }
-            } else if brace_depth == 2 && current_depot_id.is_some() {
-                // Inside a depot block, look for DecryptionKey
-                if trimmed.contains("\"DecryptionKey\"") {
-                    // Extract the key value - it's the second quoted string on this line
-                    let parts: Vec<_> = trimmed.split('"').collect();
-                    if parts.len() >= 4 {
-                        let key = parts[3].to_string();
-                        if !key.is_empty() {
-                            result.push((current_depot_id.clone().unwrap(), key));
-                        }
-                    }
-                }
-            }
-        }
-    }
+    let result: Vec<(String, String)> = config
+        .value
+        .get_obj()
+        .map(depot_entries)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(depot_id, _)| {
+            depot_id
+                .parse::<u64>()
+                .map(|n| n >= min_depot && n <= max_depot)
+                .unwrap_or(false)
+        })
+        .filter_map(|(depot_id, depot)| {
+            let key = vdf::get_str(depot, "DecryptionKey")?;
+            (!key.is_empty()).then(|| (depot_id.to_string(), key.to_string()))
+        })
+        .collect();
 
     eprintln!(
         "[config_vdf] Found {} depot keys in range [{}, {}] for app {}",
@@ -358,6 +296,60 @@ mod tests {
         assert!(result.contains("\"anotherkey\""));
     }
 
+    #[test]
+    fn test_add_keys_keeps_rest_of_file() {
+        let content = "\u{feff}\"InstallConfigStore\"
+{
+\t// Written by Steam
+\t\"Software\"
+\t{
+\t\t\"Valve\"
+\t\t{
+\t\t\t\"Steam\"
+\t\t\t{
+\t\t\t\t\"SurveyDate\"\t\t\"2024-01-01\"
+\t\t\t\t\"depots\"
+\t\t\t\t{
+\t\t\t\t\t\"228988\"
+\t\t\t\t\t{
+\t\t\t\t\t\t\"DecryptionKey\"\t\t\"abc123\"
+\t\t\t\t\t}
+\t\t\t\t}
+\t\t\t\t\"AutoUpdateWindowEnabled\"\t\t\"0\"
+\t\t\t}
+\t\t}
+\t}
+\t\"Music\" { \"LocalLibrary\" { \"Directories\" \"}\" } }
+}
+";
+
+        let keys = vec![("123456".to_string(), "newkey123".to_string())];
+        let result = add_decryption_keys_to_vdf(content, &keys);
+
+        let added = "\t\t\t\t\t\"123456\"
+\t\t\t\t\t{
+\t\t\t\t\t\t\"DecryptionKey\"\t\t\"newkey123\"
+\t\t\t\t\t}
+";
+        let at = content.find("\t\t\t\t}\n\t\t\t\t\"AutoUpdate").unwrap();
+        assert_eq!(result, format!("{}{}{}", &content[..at], added, &content[at..]));
+    }
+
+    #[test]
+    fn test_add_keys_creates_missing_sections() {
+        let content = "\"InstallConfigStore\"\n{\n\t\"Software\"\n\t{\n\t}\n}\n";
+
+        let keys = vec![("123456".to_string(), "newkey123".to_string())];
+        let result = add_decryption_keys_to_vdf(content, &keys);
+
+        let config = vdf::parse(&result).unwrap();
+        let store = config.value.get_obj().unwrap();
+        let mut path = DEPOTS_PATH.to_vec();
+        path.extend(["123456", "DecryptionKey"]);
+        assert_eq!(vdf::lookup_str(store, &path), Some("newkey123"));
+        assert!(result.starts_with("\"InstallConfigStore\"\n{\n\t\"Software\"\n\t{\n\t\t\"Valve\"\n"));
+    }
+
     #[test]
     fn test_extract_existing_depot_ids() {
         let content = r#""InstallConfigStore"
@@ -375,7 +367,8 @@ mod tests {
 	}
 }"#;
 
-        let depots = extract_existing_depot_ids(content);
+        let config = vdf::parse(content).unwrap();
+        let depots = extract_existing_depot_ids(config.value.get_obj().unwrap());
         assert!(depots.contains("228988"));
         assert!(depots.contains("123456"));
         assert_eq!(depots.len(), 2);
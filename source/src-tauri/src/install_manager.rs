@@ -1,4 +1,5 @@
 use crate::commands::SshConfig;
+use crate::vdf::{AppManifest, InstalledDepot};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
@@ -1097,36 +1098,38 @@ impl InstallManager {
             // Calculate size on disk from file count (estimate)
             let size_on_disk = file_count * 1024 * 1024; // Rough estimate
             
-            // Build InstalledDepots section from actual depots
-            let installed_depots_str = depots.iter()
-                .map(|d| format!("\t\t\"{}\"\n\t\t{{\n\t\t\t\"manifest\"\t\t\"{}\"\n\t\t\t\"size\"\t\t\"0\"\n\t\t}}", d.depot_id, d.manifest_id))
-                .collect::<Vec<_>>()
-                .join("\n");
-            
-            let acf_content = format!(
-                r#""AppState"
-{{
-	"appid"		"{app_id}"
-	"Universe"		"1"
-	"name"		"{game_name}"
-	"StateFlags"		"4"
-	"installdir"		"{folder_name}"
-	"SizeOnDisk"		"{size_on_disk}"
-	"buildid"		"0"
-	"InstalledDepots"
-	{{
-{installed_depots}
-	}}
-}}"#,
-                app_id = app_id_clone,
-                game_name = game_name_clone,
-                folder_name = folder_name_clone,
-                size_on_disk = size_on_disk,
-                installed_depots = installed_depots_str
-            );
+            let manifest = AppManifest {
+                appid: app_id_clone.clone(),
+                universe: Some(1),
+                name: Some(game_name_clone.clone()),
+                state_flags: Some(4),
+                installdir: folder_name_clone.clone(),
+                size_on_disk: Some(size_on_disk as u64),
+                buildid: Some("0".to_string()),
+                // Built from the depots actually installed
+                installed_depots: depots
+                    .iter()
+                    .map(|d| {
+                        let depot = InstalledDepot {
+                            manifest: d.manifest_id.clone(),
+                            size: Some(0),
+                        };
+                        (d.depot_id.clone(), depot)
+                    })
+                    .collect(),
+                ..Default::default()
+            };
             
             let acf_filename = format!("appmanifest_{}.acf", app_id_clone);
             
+            let Ok(acf_content) = manifest
+                .to_vdf()
+                .inspect_err(|e| eprintln!("[ACF] Failed to create {}: {}", acf_filename, e))
+            else {
+                m.update_status("error", "Failed to create Steam manifest");
+                return;
+            };
+
             if ssh_config.is_local {
                 // Local: write directly
                 let steamapps_path = std::path::PathBuf::from(&target_dir);
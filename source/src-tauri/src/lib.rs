@@ -1,12 +1,31 @@
 mod achievements;
 mod cloudsync;
+mod cloudsync_accounts;
+mod cloudsync_archive;
+mod cloudsync_crypto;
+mod cloudsync_history;
+mod cloudsync_registry;
+mod cloudsync_rollback;
+mod cloudsync_roots;
+mod cloudsync_scan;
+mod cloudsync_session;
+mod cloudsync_snapshots;
+mod cloudsync_state;
+mod cloudsync_storage;
+mod cloudsync_transfer;
 mod cloudsync_watcher;
+mod cloudsync_worker;
 mod commands;
 mod config_vdf;
 mod install_manager;
+mod ludusavi;
 mod steam_cm;
 mod steamless;
 mod pcgamingwiki;
+mod pcgamingwiki_cache;
+mod pcgamingwiki_metadata;
+mod pcgamingwiki_wikitext;
+pub mod vdf;
 
 use commands::*;
 use install_manager::InstallManager;
@@ -16,6 +35,12 @@ use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Headless bulk sync for cron jobs: no window, exit when done
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--sync-all-cloud-saves") {
+        std::process::exit(commands::sync_all_cloud_saves_headless(&args));
+    }
+
     // Linux WebKit/Wayland fix - must be set before WebKit initializes
     // WebKit has issues with Wayland on many distros (Arch, SteamOS, etc.)
     #[cfg(target_os = "linux")]
@@ -68,6 +93,8 @@ pub fn run() {
             let handle = app.handle().clone();
             app.manage(InstallManager::new(handle));
             app.manage(crate::cloudsync_watcher::CloudSyncWatcherState::default());
+            app.manage(crate::cloudsync_worker::CloudSyncWorker::spawn(app.handle().clone()));
+            app.manage(crate::cloudsync_session::GameSessionTracker::spawn(app.handle().clone()));
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
@@ -111,6 +138,7 @@ pub fn run() {
             list_installed_games_local,
             uninstall_game,
             check_game_update,
+            get_game_metadata,
             check_game_installed,
             get_steam_libraries,
             copy_game_to_remote,
@@ -177,10 +205,28 @@ pub fn run() {
             test_cloudsync_connection,
             get_game_cloud_status,
             get_global_cloud_status,
+            list_steam_accounts,
+            get_cloud_sync_history,
             sync_game_cloud_saves,
+            sync_all_cloud_saves,
+            list_cloud_snapshots,
+            restore_cloud_snapshot,
+            get_cloud_conflicts,
+            resolve_cloud_conflict,
+            resolve_cloud_conflicts,
+            list_local_rollbacks,
+            restore_local_rollback,
+            export_cloud_saves,
+            import_cloud_saves,
+            migrate_cloud_storage_layout,
+            set_cloud_encryption_passphrase,
+            has_cloud_encryption_passphrase,
+            rotate_cloud_encryption_key,
             start_cloud_watcher,
             stop_cloud_watcher,
             is_cloud_watcher_running,
+            launch_game_with_cloud_sync,
+            is_game_session_active,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,406 @@
+//! Ludusavi Manifest - Offline database of game save locations
+//!
+//! Ludusavi's manifest (https://github.com/mtkennerly/ludusavi-manifest) maps
+//! every game to the paths its saves are kept in, written with placeholders
+//! such as `<base>` (install directory), `<winAppData>` or `<storeUserId>`.
+//! Games are looked up by Steam app ID; Windows paths are resolved into the
+//! game's Proton prefix, Linux and macOS paths into the user's home.
+//!
+//! The manifest is read from the first of:
+//! - ~/.local/share/boilerroom/ludusavi/manifest.yaml
+//! - ~/.config/ludusavi/manifest.yaml                  (Ludusavi's own copy)
+//! - ~/.var/app/com.github.mtkennerly.ludusavi/config/ludusavi/manifest.yaml
+
+use crate::cloudsync_roots::{self, resolve_proton_root, root, GameLocation};
+use crate::cloudsync_scan::{glob_to_regex, is_sync_artifact};
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+const MANIFEST_LOCATIONS: &[&str] = &[
+    ".local/share/boilerroom/ludusavi/manifest.yaml",
+    ".config/ludusavi/manifest.yaml",
+    ".var/app/com.github.mtkennerly.ludusavi/config/ludusavi/manifest.yaml",
+];
+
+/// A game's entry in the manifest (only the fields used here)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ManifestGame {
+    files: HashMap<String, FileRule>,
+    install_dir: HashMap<String, serde_yaml::Value>,
+    steam: Option<SteamInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct SteamInfo {
+    id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct FileRule {
+    tags: Vec<String>,
+    when: Vec<Constraint>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Constraint {
+    os: Option<String>,
+    store: Option<String>,
+}
+
+/// Manifest games by Steam app ID
+type ManifestIndex = HashMap<String, ManifestGame>;
+
+/// Last manifest loaded, with the file and modification time it came from
+static LOADED: Mutex<Option<(PathBuf, SystemTime, Arc<ManifestIndex>)>> = Mutex::new(None);
+
+fn parse_manifest(content: &str) -> Result<ManifestIndex, String> {
+    let games: HashMap<String, ManifestGame> = serde_yaml::from_str(content)
+        .map_err(|e| format!("Failed to parse Ludusavi manifest: {}", e))?;
+
+    Ok(games
+        .into_values()
+        .filter_map(|game| {
+            let id = game.steam.as_ref()?.id?;
+            Some((id.to_string(), game))
+        })
+        .collect())
+}
+
+fn manifest_path() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    MANIFEST_LOCATIONS
+        .iter()
+        .map(|location| home.join(location))
+        .find(|path| path.is_file())
+}
+
+/// The manifest, parsed once and reloaded when the file changes. The
+/// manifest is large, so it is parsed on a blocking thread and without
+/// holding the lock (lookups racing the first load may both parse it).
+async fn load_manifest() -> Option<Arc<ManifestIndex>> {
+    let path = manifest_path()?;
+    let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+
+    if let Some((loaded_path, loaded_modified, index)) = LOADED.lock().unwrap().as_ref() {
+        if *loaded_path == path && *loaded_modified == modified {
+            return Some(index.clone());
+        }
+    }
+
+    let read_path = path.clone();
+    let index = tokio::task::spawn_blocking(move || {
+        std::fs::read_to_string(&read_path)
+            .map_err(|e| format!("Failed to read Ludusavi manifest: {}", e))
+            .and_then(|content| parse_manifest(&content))
+    })
+    .await
+    .map_err(|e| format!("Ludusavi manifest task failed: {}", e))
+    .and_then(|index| index);
+    match index {
+        Ok(index) => {
+            info!("Ludusavi: Loaded {} Steam games from {}", index.len(), path.display());
+            let index = Arc::new(index);
+            *LOADED.lock().unwrap() = Some((path, modified, index.clone()));
+            Some(index)
+        }
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    }
+}
+
+// ============================================================================
+// Path Resolution
+// ============================================================================
+
+/// What placeholders resolve to for one game
+struct PathContext {
+    app_id: String,
+    user_id: Option<String>,
+    location: GameLocation,
+    home: PathBuf,
+}
+
+impl PathContext {
+    /// OS the game's saves are written by ("windows" under Proton)
+    fn platform(&self) -> &'static str {
+        if self.location.proton_prefix.is_some() {
+            "windows"
+        } else if cfg!(target_os = "macos") {
+            "mac"
+        } else {
+            "linux"
+        }
+    }
+
+    /// Whether a rule applies to a Steam game on this platform. Only save
+    /// files are synced; untagged rules are assumed to be saves.
+    fn applies(&self, rule: &FileRule) -> bool {
+        let is_save = rule.tags.is_empty() || rule.tags.iter().any(|t| t == "save");
+        let platform = self.platform();
+        let when_matches = rule.when.is_empty()
+            || rule.when.iter().any(|c| {
+                c.os.as_deref().is_none_or(|os| os == platform)
+                    && c.store.as_deref().is_none_or(|store| store == "steam")
+            });
+        is_save && when_matches
+    }
+
+    fn placeholder(&self, name: &str) -> Option<String> {
+        let install_dir = self.location.install_dir.as_ref();
+        let windows_root = |id: i32| resolve_proton_root(id, self.location.proton_prefix.as_ref()?);
+        let drive_c = self.location.proton_prefix.as_ref().map(|p| p.join("drive_c"));
+
+        let path = match name {
+            "base" => install_dir.cloned(),
+            "game" => install_dir.and_then(|d| d.file_name()).map(PathBuf::from),
+            // <root>/steamapps/common/<game>
+            "root" => install_dir.and_then(|d| d.ancestors().nth(3)).map(Path::to_path_buf),
+            "storeGameId" => Some(PathBuf::from(&self.app_id)),
+            // Without an account, any account's folder matches
+            "storeUserId" => Some(PathBuf::from(self.user_id.as_deref().unwrap_or("*"))),
+            "home" => match &drive_c {
+                Some(drive_c) => Some(drive_c.join("users/steamuser")),
+                None => Some(self.home.clone()),
+            },
+            "osUserName" => match &drive_c {
+                Some(_) => Some(PathBuf::from("steamuser")),
+                None => self.home.file_name().map(PathBuf::from),
+            },
+            "winAppData" => windows_root(root::WIN_APP_DATA_ROAMING),
+            "winLocalAppData" => windows_root(root::WIN_APP_DATA_LOCAL),
+            "winLocalAppDataLow" => windows_root(root::WIN_APP_DATA_LOCAL_LOW),
+            "winDocuments" => windows_root(root::WIN_MY_DOCUMENTS),
+            "winProgramData" => windows_root(root::WIN_PROGRAM_DATA),
+            "winPublic" => drive_c.map(|d| d.join("users/Public")),
+            "winDir" => drive_c.map(|d| d.join("windows")),
+            "xdgData" if drive_c.is_none() => dirs::data_dir(),
+            "xdgConfig" if drive_c.is_none() => dirs::config_dir(),
+            // Registry keys and anything unknown
+            _ => None,
+        }?;
+        Some(path.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// Replace every `<placeholder>` in a manifest path. None if one of them
+    /// doesn't apply to this game.
+    fn resolve(&self, raw: &str) -> Option<String> {
+        let mut resolved = String::new();
+        let mut rest = raw;
+
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>')?;
+            resolved.push_str(&rest[..start]);
+            resolved.push_str(&self.placeholder(&rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        resolved.push_str(rest);
+
+        Some(resolved.replace('\\', "/"))
+    }
+}
+
+fn has_wildcard(segment: &str) -> bool {
+    segment.contains('*') || segment.contains('?')
+}
+
+/// Paths matching a resolved manifest path. Paths without wildcards are
+/// returned as they are, whether they exist or not.
+fn expand_pattern(pattern: &str) -> Vec<PathBuf> {
+    if !has_wildcard(pattern) {
+        return vec![PathBuf::from(pattern)];
+    }
+
+    let segments: Vec<&str> = pattern.split('/').collect();
+    let literal = segments.iter().take_while(|s| !has_wildcard(s)).count();
+    let base = PathBuf::from(segments[..literal].join("/"));
+    let rest = segments[literal..].join("/");
+
+    let regex = match glob_to_regex(&rest) {
+        Ok(regex) => regex,
+        Err(e) => {
+            warn!("Ludusavi: Skipping path {}: {}", pattern, e);
+            return Vec::new();
+        }
+    };
+    let max_depth = if rest.contains("**") {
+        usize::MAX
+    } else {
+        segments.len() - literal
+    };
+
+    let mut matches: Vec<PathBuf> = WalkDir::new(&base)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !is_sync_artifact(&e.file_name().to_string_lossy()))
+        .filter(|e| {
+            e.path()
+                .strip_prefix(&base)
+                .is_ok_and(|rel| regex.is_match(&rel.to_string_lossy()))
+        })
+        .map(|e| e.into_path())
+        .collect();
+
+    // A match inside another one would be walked twice
+    matches.sort();
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for path in matches {
+        if !kept.iter().any(|parent| path.starts_with(parent)) {
+            kept.push(path);
+        }
+    }
+    kept
+}
+
+fn save_paths(game: &ManifestGame, context: &PathContext) -> Vec<PathBuf> {
+    let mut patterns: Vec<&String> = game
+        .files
+        .iter()
+        .filter(|(_, rule)| context.applies(rule))
+        .map(|(path, _)| path)
+        .collect();
+    patterns.sort();
+
+    let mut paths: Vec<PathBuf> = patterns
+        .into_iter()
+        .filter_map(|raw| context.resolve(raw))
+        .flat_map(|pattern| expand_pattern(&pattern))
+        .collect();
+    paths.dedup();
+    paths
+}
+
+/// Save locations of a Steam game according to the Ludusavi manifest.
+/// None when there is no manifest or it doesn't list the game.
+pub async fn find_save_locations(app_id: &str, steam_user_id: Option<&str>) -> Option<Vec<PathBuf>> {
+    let manifest = load_manifest().await?;
+    let app_id = app_id.to_string();
+    let steam_user_id = steam_user_id.map(|s| s.to_string());
+    // Expanding the globs walks the save directories
+    tokio::task::spawn_blocking(move || locations_in(&manifest, &app_id, steam_user_id.as_deref()))
+        .await
+        .ok()?
+}
+
+fn locations_in(manifest: &ManifestIndex, app_id: &str, steam_user_id: Option<&str>) -> Option<Vec<PathBuf>> {
+    let game = manifest.get(app_id)?;
+
+    let mut location = cloudsync_roots::locate_game(app_id);
+    // Games that aren't installed still have a predictable install dir name
+    if location.install_dir.is_none() {
+        location.install_dir = game.install_dir.keys().min().and_then(|name| {
+            Some(cloudsync_roots::steam_root()?.join("steamapps/common").join(name))
+        });
+    }
+
+    let context = PathContext {
+        app_id: app_id.to_string(),
+        user_id: steam_user_id.map(|s| s.to_string()),
+        location,
+        home: dirs::home_dir()?,
+    };
+    Some(save_paths(game, &context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+Example Game:
+  files:
+    <winAppData>/Example/<storeUserId>/*.sav:
+      tags:
+        - save
+    <base>/settings.ini:
+      tags:
+        - config
+    <xdgData>/example:
+      when:
+        - os: linux
+    <home>/Saved Games/Example: {}
+  installDir:
+    Example Game: {}
+  steam:
+    id: 4000
+No Steam Game:
+  files:
+    <base>/save.dat: {}
+"#;
+
+    fn proton_context(root: &Path) -> PathContext {
+        PathContext {
+            app_id: "4000".to_string(),
+            user_id: Some("42".to_string()),
+            location: GameLocation {
+                install_dir: Some(root.join("steamapps/common/Example Game")),
+                proton_prefix: Some(root.join("steamapps/compatdata/4000/pfx")),
+            },
+            home: root.join("home"),
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let index = parse_manifest(MANIFEST).unwrap();
+        assert_eq!(index.len(), 1);
+        let game = &index["4000"];
+        assert_eq!(game.files.len(), 4);
+        assert_eq!(game.files["<base>/settings.ini"].tags, vec!["config".to_string()]);
+    }
+
+    #[test]
+    fn test_resolve_placeholders() {
+        let root = tempfile::tempdir().unwrap();
+        let context = proton_context(root.path());
+        let prefix = root.path().join("steamapps/compatdata/4000/pfx/drive_c");
+
+        assert_eq!(
+            context.resolve("<winAppData>/Example/<storeUserId>").unwrap(),
+            format!("{}/users/steamuser/AppData/Roaming/Example/42", prefix.display())
+        );
+        assert_eq!(
+            context.resolve("<root>/steamapps/common/<game>/save.dat").unwrap(),
+            format!("{}/steamapps/common/Example Game/save.dat", root.path().display())
+        );
+        // Linux-only and registry paths don't apply to a Proton game
+        assert_eq!(context.resolve("<xdgData>/example"), None);
+        assert_eq!(context.resolve("<regHkcu>/Software/Example"), None);
+    }
+
+    #[test]
+    fn test_save_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let context = proton_context(root.path());
+        let game = parse_manifest(MANIFEST).unwrap().remove("4000").unwrap();
+
+        let saves = root
+            .path()
+            .join("steamapps/compatdata/4000/pfx/drive_c/users/steamuser/AppData/Roaming/Example/42");
+        std::fs::create_dir_all(&saves).unwrap();
+        std::fs::write(saves.join("slot1.sav"), "1").unwrap();
+        std::fs::write(saves.join("slot1.bak"), "1").unwrap();
+
+        let paths = save_paths(&game, &context);
+        assert_eq!(
+            paths,
+            vec![
+                root.path()
+                    .join("steamapps/compatdata/4000/pfx/drive_c/users/steamuser/Saved Games/Example"),
+                saves.join("slot1.sav"),
+            ]
+        );
+    }
+}
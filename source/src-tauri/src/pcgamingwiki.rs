@@ -1,8 +1,15 @@
+use crate::cloudsync_registry::{self, Hive};
+use crate::cloudsync_roots::{self, resolve_proton_root, root};
+use crate::pcgamingwiki_cache::{self, CachedSaves};
+use crate::pcgamingwiki_wikitext as wikitext;
+use chrono::Utc;
 use log::{info, warn};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-const API_BASE_URL: &str = "https://www.pcgamingwiki.com/w/api.php";
+pub(crate) const API_BASE_URL: &str = "https://www.pcgamingwiki.com/w/api.php";
 
 #[derive(Debug, Deserialize)]
 struct CargoResponse {
@@ -52,27 +59,175 @@ struct WikitextContent {
     content: String,
 }
 
-/// Entry point to find save locations for a Steam AppID
+/// Entry point to find save locations for a Steam AppID.
+/// User overrides come first, then the offline Ludusavi manifest, then the
+/// wiki (through its on-disk cache). Registry saves are found at their
+/// exports, which only syncs write (see `export_registry_saves`).
 pub async fn find_save_locations(app_id: &str, steam_user_id: Option<&str>) -> Result<Vec<PathBuf>, String> {
+    let (_, saves) = find_saves(app_id, steam_user_id).await?;
+    Ok(save_paths(app_id, saves))
+}
+
+/// Export the registry saves of a game from its Proton prefix, so the files
+/// at its save locations match the registry right now
+pub async fn export_registry_saves(app_id: &str, steam_user_id: Option<&str>) -> Result<(), String> {
+    let (context, saves) = find_saves(app_id, steam_user_id).await?;
+    for save in saves {
+        if let ResolvedSave::Registry(hive, key) = save {
+            if let Err(e) = cloudsync_registry::export_to_file(app_id, &context.prefix, hive, &key) {
+                warn!("Skipping registry save {}: {}", key, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn find_saves(app_id: &str, steam_user_id: Option<&str>) -> Result<(PathContext, Vec<ResolvedSave>), String> {
+    let context = PathContext::for_game(app_id, steam_user_id).ok_or("Could not find home directory")?;
+    let save_override = pcgamingwiki_cache::load_override(app_id)
+        .unwrap_or_else(|e| {
+            warn!("{}", e);
+            None
+        })
+        .unwrap_or_default();
+    let override_saves: Vec<ResolvedSave> = save_override
+        .paths
+        .iter()
+        .filter_map(|path| context.resolve(path))
+        .collect();
+
+    let mut saves = Vec::new();
+    if save_override.pin {
+        info!("PCGamingWiki: Using pinned save paths for AppID {}", app_id);
+    } else if let Some(found) = crate::ludusavi::find_save_locations(app_id, steam_user_id).await.filter(|p| !p.is_empty()) {
+        info!("Ludusavi: Found {} save locations for AppID {}", found.len(), app_id);
+        saves = found.into_iter().map(ResolvedSave::Path).collect();
+    } else {
+        match wiki_save_entries(app_id).await {
+            Ok(entries) => saves = select_saves(entries, &context),
+            Err(e) if override_saves.is_empty() => return Err(e),
+            Err(e) => info!("PCGamingWiki: {} (AppID {}), using override paths", e, app_id),
+        }
+    }
+
+    saves.extend(override_saves);
+    Ok((context, saves))
+}
+
+/// Resolve wiki entries to file system locations for this machine, picking
+/// the platform the same way as for saves. Registry keys are left out.
+pub(crate) fn resolve_file_locations(
+    app_id: &str,
+    steam_user_id: Option<&str>,
+    entries: Vec<SavePathEntry>,
+) -> Vec<PathBuf> {
+    let Some(context) = PathContext::for_game(app_id, steam_user_id) else {
+        return Vec::new();
+    };
+    select_saves(entries, &context)
+        .into_iter()
+        .filter_map(|save| match save {
+            ResolvedSave::Path(path) => Some(path),
+            ResolvedSave::Registry(..) => None,
+        })
+        .collect()
+}
+
+/// Turn resolved saves into directories to scan. Registry saves are synced
+/// as exports of their key, kept in the game's registry directory.
+fn save_paths(app_id: &str, saves: Vec<ResolvedSave>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for save in saves {
+        let path = match save {
+            ResolvedSave::Path(path) => path,
+            ResolvedSave::Registry(_, key) => match cloudsync_registry::registry_root(app_id) {
+                Ok(root) => root,
+                Err(e) => {
+                    warn!("Skipping registry save {}: {}", key, e);
+                    continue;
+                }
+            },
+        };
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Save entries the wiki lists for a game, from the cache while it is fresh.
+/// An expired entry is still used when the wiki can't be reached.
+async fn wiki_save_entries(app_id: &str) -> Result<Vec<SavePathEntry>, String> {
+    let now = Utc::now().timestamp();
+    let cached = pcgamingwiki_cache::load_cached(app_id);
+
+    let saves = match cached {
+        Some(cached) if cached.is_fresh(now) => cached,
+        cached => match fetch_save_entries(app_id).await {
+            Ok(entries) => {
+                let saves = CachedSaves {
+                    fetched_at: now,
+                    found: entries.is_some(),
+                    entries: entries.unwrap_or_default(),
+                };
+                if let Err(e) = pcgamingwiki_cache::store_cached(app_id, &saves) {
+                    warn!("{}", e);
+                }
+                saves
+            }
+            Err(e) => match cached {
+                Some(cached) => {
+                    warn!("PCGamingWiki: {}, using cached save locations for AppID {}", e, app_id);
+                    cached
+                }
+                None => return Err(e),
+            },
+        },
+    };
+
+    if saves.found {
+        Ok(saves.entries)
+    } else {
+        Err("No save locations on PCGamingWiki".to_string())
+    }
+}
+
+/// Look a game's save entries up on the wiki. None if it has no page or no
+/// save section.
+async fn fetch_save_entries(app_id: &str) -> Result<Option<Vec<SavePathEntry>>, String> {
     // 1. Find PageName
-    let page_name = get_page_name(app_id).await?;
+    let page_name = match get_page_name(app_id).await? {
+        Some(page_name) => page_name,
+        None => return Ok(None),
+    };
     info!("PCGamingWiki: Found page '{}' for AppID {}", page_name, app_id);
 
     // 2. Find Save Data Section
-    let section_idx = get_save_data_section_index(&page_name).await?;
-    
+    let section_idx = match get_save_data_section_index(&page_name).await? {
+        Some(section_idx) => section_idx,
+        None => return Ok(None),
+    };
+
     // 3. Get Wikitext
-    let wikitext = get_section_wikitext(&page_name, &section_idx).await?;
-    
-    // 4. Parse & Resolve Paths
-    let paths = parse_and_resolve_paths(&wikitext, app_id, steam_user_id);
-    
-    Ok(paths)
+    let wikitext = get_wikitext(&page_name, Some(&section_idx)).await?;
+
+    // 4. Parse
+    Ok(Some(parse_save_entries(&wikitext)))
 }
 
-async fn get_page_name(app_id: &str) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let resp = client.get(API_BASE_URL)
+/// HTTP client shared by all wiki requests
+pub(crate) fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+async fn get_page_name(app_id: &str) -> Result<Option<String>, String> {
+    let resp = http_client().get(API_BASE_URL)
         .query(&[
             ("action", "cargoquery"),
             ("tables", "Infobox_game"),
@@ -86,14 +241,11 @@ async fn get_page_name(app_id: &str) -> Result<String, String> {
 
     let data: CargoResponse = resp.json().await.map_err(|e| format!("Parse error: {}", e))?;
     
-    data.cargoquery.first()
-        .map(|entry| entry.title.page_name.clone())
-        .ok_or_else(|| "Game not found on PCGamingWiki".to_string())
+    Ok(data.cargoquery.first().map(|entry| entry.title.page_name.clone()))
 }
 
-async fn get_save_data_section_index(page_name: &str) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let resp = client.get(API_BASE_URL)
+async fn get_save_data_section_index(page_name: &str) -> Result<Option<String>, String> {
+    let resp = http_client().get(API_BASE_URL)
         .query(&[
             ("action", "parse"),
             ("page", page_name),
@@ -109,30 +261,33 @@ async fn get_save_data_section_index(page_name: &str) -> Result<String, String>
     // Look for "Save game data location" or fallback to "Game data"
     for section in &data.parse.sections {
         if section.line.to_lowercase().contains("save game data location") {
-            return Ok(section.index.clone());
+            return Ok(Some(section.index.clone()));
         }
     }
     
     // Fallback pass
     for section in &data.parse.sections {
          if section.line.to_lowercase() == "game data" {
-            return Ok(section.index.clone());
+            return Ok(Some(section.index.clone()));
         }
     }
 
-    Err("Save data section not found".to_string())
+    Ok(None)
 }
 
-async fn get_section_wikitext(page_name: &str, section_index: &str) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let resp = client.get(API_BASE_URL)
-        .query(&[
-            ("action", "parse"),
-            ("page", page_name),
-            ("section", section_index),
-            ("prop", "wikitext"),
-            ("format", "json"),
-        ])
+/// Wikitext of a page, or of one of its sections
+pub(crate) async fn get_wikitext(page_name: &str, section_index: Option<&str>) -> Result<String, String> {
+    let mut query = vec![
+        ("action", "parse"),
+        ("page", page_name),
+        ("prop", "wikitext"),
+        ("format", "json"),
+    ];
+    if let Some(section_index) = section_index {
+        query.push(("section", section_index));
+    }
+    let resp = http_client().get(API_BASE_URL)
+        .query(&query)
         .send()
         .await
         .map_err(|e| format!("Request failed: {}", e))?;
@@ -141,114 +296,354 @@ async fn get_section_wikitext(page_name: &str, section_index: &str) -> Result<St
     Ok(data.parse.wikitext.content)
 }
 
-use regex::Regex;
+/// Platform column of a `Game data/saves` row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavePlatform {
+    Windows,
+    Linux,
+    /// Paths inside a Proton prefix
+    SteamPlay,
+    MacOs,
+    /// Steam's own userdata folder, the same on every platform
+    Steam,
+    /// Other stores (Microsoft Store, GOG.com, ...) and DOS
+    Other,
+}
 
-fn parse_and_resolve_paths(wikitext: &str, app_id: &str, steam_user_id: Option<&str>) -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    
-    // Regex to find Game data/saves templates
-    // Matches: {{Game data/saves|Type|Path|...}}
-    // note: PCGamingWiki templates can be messy. We look for pipe-separated values.
-    let re = Regex::new(r"\{\{Game data/saves\|[^|]*\|([^}|]+)").unwrap();
-    
-    // Also look for straightforward Windows paths if not in template, but that's riskier.
-    // Let's stick to the template for now.
-    
-    for cap in re.captures_iter(wikitext) {
-        if let Some(raw_path_match) = cap.get(1) {
-            let raw_path = raw_path_match.as_str().trim();
-            if !raw_path.is_empty() {
-                if let Some(resolved) = resolve_windows_path_to_linux(raw_path, app_id, steam_user_id) {
-                    paths.push(resolved);
-                }
-            }
+impl SavePlatform {
+    fn from_wiki(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "windows" => Self::Windows,
+            "linux" => Self::Linux,
+            "steam play (linux)" | "steam play" => Self::SteamPlay,
+            "os x" | "macos" | "mac os" | "mac os x" => Self::MacOs,
+            "steam" => Self::Steam,
+            _ => Self::Other,
         }
     }
+}
 
-    // Also handle second/third args in the template if they exist (some games have multiple paths)
-    // A more robust parser would tokenise the template, but let's iterate on lines for now as wikicode often breaks lines
-    
-    paths
+/// One save path listed on a wiki page, still in wiki notation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavePathEntry {
+    pub platform: SavePlatform,
+    pub path: String,
 }
 
-fn resolve_windows_path_to_linux(win_path: &str, app_id: &str, steam_user_id: Option<&str>) -> Option<PathBuf> {
-    let mut path_str = win_path.to_string();
+/// Every path of every `{{Game data/saves|<platform>|<path>|<path>...}}` row
+pub fn parse_save_entries(wikitext: &str) -> Vec<SavePathEntry> {
+    parse_game_data(wikitext, "Game data/saves")
+}
 
-    // 1. Replace Template Variables
-    // {{p|game}} -> install dir (hard to know exactly, but usually we can find it via library.rs logic if we had access)
-    // For now, let's focus on Proton prefix paths
-    
-    // {{p|uid}} -> steam user id
-    if let Some(uid) = steam_user_id {
-        path_str = path_str.replace("{{p|uid}}", uid);
+/// Every path of every `{{Game data/config|...}}` row, in the same form
+pub fn parse_config_entries(wikitext: &str) -> Vec<SavePathEntry> {
+    parse_game_data(wikitext, "Game data/config")
+}
+
+fn parse_game_data(wikitext: &str, template: &str) -> Vec<SavePathEntry> {
+    let text = wikitext::strip_markup(wikitext);
+    wikitext::find_templates(&text, template)
+        .into_iter()
+        .flat_map(|template| {
+            let mut args = template.args.into_iter();
+            let platform = SavePlatform::from_wiki(&args.next().unwrap_or_default());
+            args.filter(|path| !path.is_empty())
+                .map(move |path| SavePathEntry { platform, path })
+        })
+        .collect()
+}
+
+/// A save location from the wiki, resolved for this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResolvedSave {
+    Path(PathBuf),
+    /// A registry key in the game's Proton prefix
+    Registry(Hive, String),
+}
+
+/// What `{{p|...}}` placeholders point to for one game
+struct PathContext {
+    steam_user_id: Option<String>,
+    steam_root: PathBuf,
+    install_dir: Option<PathBuf>,
+    /// Proton prefix (pfx), whether or not it exists yet
+    prefix: PathBuf,
+    home: PathBuf,
+}
+
+impl PathContext {
+    /// The install dir comes from the library scan, the prefix from the game's
+    /// compat tool (or where Steam would create it)
+    fn for_game(app_id: &str, steam_user_id: Option<&str>) -> Option<Self> {
+        let steam_root = cloudsync_roots::steam_root()?;
+        let location = cloudsync_roots::locate_game(app_id);
+        let prefix = location.proton_prefix.unwrap_or_else(|| {
+            steam_root.join("steamapps/compatdata").join(app_id).join("pfx")
+        });
+
+        Some(Self {
+            steam_user_id: steam_user_id.map(|s| s.to_string()),
+            install_dir: crate::commands::library::local_install_dir(app_id).or(location.install_dir),
+            steam_root,
+            prefix,
+            home: dirs::home_dir()?,
+        })
     }
-    
-    // {{p|steam}} -> ~/.local/share/Steam
-    let steam_root = if cfg!(target_os = "macos") {
-         match dirs::home_dir() {
-             Some(h) => h.join("Library/Application Support/Steam"),
-             None => return None,
-         }
+
+    fn placeholder(&self, name: &str) -> Option<PathBuf> {
+        let drive_c = self.prefix.join("drive_c");
+        let profile = drive_c.join("users/steamuser");
+        let windows_root = |id: i32| resolve_proton_root(id, &self.prefix);
+
+        match name.trim().to_lowercase().replace('\\', "/").as_str() {
+            "game" => self.install_dir.clone(),
+            "uid" => self.steam_user_id.as_ref().map(PathBuf::from),
+            "steam" => Some(self.steam_root.clone()),
+            "steamapps" => Some(self.steam_root.join("steamapps")),
+            "username" => Some(PathBuf::from("steamuser")),
+            // "user" is not a wiki placeholder, but older pages used it for the profile
+            "userprofile" | "user" => Some(profile),
+            "userprofile/documents" => windows_root(root::WIN_MY_DOCUMENTS),
+            "userprofile/appdata/locallow" => windows_root(root::WIN_APP_DATA_LOCAL_LOW),
+            "appdata" => windows_root(root::WIN_APP_DATA_ROAMING),
+            "localappdata" => windows_root(root::WIN_APP_DATA_LOCAL),
+            "public" => Some(drive_c.join("users/Public")),
+            "allusersprofile" | "programdata" => windows_root(root::WIN_PROGRAM_DATA),
+            "windir" => Some(drive_c.join("windows")),
+            "syswow64" => Some(drive_c.join("windows/syswow64")),
+            "ubisoftconnect" | "uplay" => {
+                Some(drive_c.join("Program Files (x86)/Ubisoft/Ubisoft Game Launcher"))
+            }
+            // Inside registry paths
+            "wow64" => Some(PathBuf::from("Wow6432Node")),
+            "linuxhome" | "osxhome" => Some(self.home.clone()),
+            "xdgdatahome" => Some(dirs::data_dir().unwrap_or_else(|| self.home.join(".local/share"))),
+            "xdgconfighome" => Some(dirs::config_dir().unwrap_or_else(|| self.home.join(".config"))),
+            _ => None,
+        }
+    }
+
+    /// Replace every `{{p|...}}` in a wiki path. None if one of them can't be
+    /// resolved (unknown placeholder, no install dir, no Steam account).
+    fn expand(&self, raw: &str) -> Option<String> {
+        let mut expanded = String::new();
+        let mut rest = raw.trim();
+
+        while let Some(start) = rest.find("{{") {
+            let end = start + rest[start..].find("}}")?;
+            let (template, name) = rest[start + 2..end].split_once('|')?;
+            if !template.trim().eq_ignore_ascii_case("p") {
+                return None;
+            }
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(&self.placeholder(name)?.to_string_lossy());
+            rest = &rest[end + 2..];
+        }
+        expanded.push_str(rest);
+
+        Some(match expanded.strip_prefix('~') {
+            Some(after_home) => format!("{}{}", self.home.to_string_lossy(), after_home),
+            None => expanded,
+        })
+    }
+
+    /// Resolve a save path from the wiki: registry keys (`{{p|hkcu}}\...`) or
+    /// a file system path
+    fn resolve(&self, raw: &str) -> Option<ResolvedSave> {
+        let raw = raw.trim();
+        for (placeholder, hive) in [("hkcu", Hive::CurrentUser), ("hklm", Hive::LocalMachine)] {
+            let lower = raw.to_lowercase();
+            if lower.starts_with(&format!("{{{{p|{}}}}}", placeholder)) {
+                let key = self.expand(&raw[placeholder.len() + 6..])?;
+                return Some(ResolvedSave::Registry(hive, key.trim_matches('\\').to_string()));
+            }
+        }
+
+        // Older pages write the install dir out
+        let path = self.expand(&raw.replace("<path-to-game>", "{{p|game}}"))?;
+        if path.contains("{{") || path.contains('<') {
+            warn!("Skipping path with unresolved placeholders: {}", raw);
+            return None;
+        }
+        Some(ResolvedSave::Path(PathBuf::from(path.replace('\\', "/"))))
+    }
+
+    fn exists(&self, save: &ResolvedSave) -> bool {
+        match save {
+            ResolvedSave::Path(path) => path.exists(),
+            ResolvedSave::Registry(hive, key) => cloudsync_registry::key_exists(&self.prefix, *hive, key),
+        }
+    }
+}
+
+/// Native saves win when some of them exist, or when none of the Proton
+/// ones do either
+fn prefer_native(
+    native: Vec<ResolvedSave>,
+    proton: Vec<ResolvedSave>,
+    exists: impl Fn(&ResolvedSave) -> bool,
+) -> Vec<ResolvedSave> {
+    let has_saves = |saves: &[ResolvedSave]| saves.iter().any(&exists);
+    if !native.is_empty() && (has_saves(&native) || !has_saves(&proton)) {
+        native
     } else {
-         match dirs::home_dir() {
-             Some(h) => h.join(".local/share/Steam"),
-             None => return None,
-         }
+        proton
+    }
+}
+
+/// Pick the saves to use for this machine from a game's wiki entries
+fn select_saves(entries: Vec<SavePathEntry>, context: &PathContext) -> Vec<ResolvedSave> {
+    let native_platform = if cfg!(target_os = "macos") {
+        SavePlatform::MacOs
+    } else {
+        SavePlatform::Linux
     };
-    
-    path_str = path_str.replace("{{p|steam}}", &steam_root.to_string_lossy());
-    path_str = path_str.replace("{{P|steam}}", &steam_root.to_string_lossy());
 
-    // Proton Prefix Base: ~/.local/share/Steam/steamapps/compatdata/[APPID]/pfx/drive_c/
-    let compat_data = steam_root.join("steamapps/compatdata").join(app_id).join("pfx/drive_c");
-    
-    // User Profile Base: .../users/steamuser/
-    let user_profile = compat_data.join("users/steamuser");
+    let mut native = Vec::new();
+    let mut proton = Vec::new();
+    let mut steam = Vec::new();
+    for entry in entries {
+        let group = match entry.platform {
+            p if p == native_platform => &mut native,
+            SavePlatform::SteamPlay | SavePlatform::Windows => &mut proton,
+            SavePlatform::Steam => &mut steam,
+            _ => continue,
+        };
+        if let Some(save) = context.resolve(&entry.path) {
+            if !group.contains(&save) {
+                group.push(save);
+            }
+        }
+    }
 
-    // Replace common variables
-    if path_str.contains("{{p|user}}") || path_str.contains("{{P|user}}") {
-        path_str = path_str.replace("{{p|user}}", &user_profile.to_string_lossy())
-                           .replace("{{P|user}}", &user_profile.to_string_lossy());
+    let mut saves = prefer_native(native, proton, |save| context.exists(save));
+    saves.extend(steam);
+    saves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn entry(platform: SavePlatform, path: &str) -> SavePathEntry {
+        SavePathEntry {
+            platform,
+            path: path.to_string(),
+        }
     }
-    
-    // AppData
-    let appdata = user_profile.join("AppData/Roaming");
-    if path_str.contains("{{p|appdata}}") || path_str.contains("{{P|appdata}}") {
-        path_str = path_str.replace("{{p|appdata}}", &appdata.to_string_lossy())
-                           .replace("{{P|appdata}}", &appdata.to_string_lossy());
+
+    #[test]
+    fn test_parse_hollow_knight() {
+        assert_eq!(
+            parse_save_entries(include_str!("../testdata/pcgamingwiki/hollow_knight.wikitext")),
+            vec![
+                entry(SavePlatform::Windows, "{{P|userprofile}}\\AppData\\LocalLow\\Team Cherry\\Hollow Knight\\"),
+                entry(SavePlatform::MacOs, "{{P|osxhome}}/Library/Application Support/unity.Team Cherry.Hollow Knight/"),
+                entry(SavePlatform::Linux, "{{P|xdgconfighome}}/unity3d/Team Cherry/Hollow Knight/"),
+            ]
+        );
     }
 
-    // LocalAppData
-    let localappdata = user_profile.join("AppData/Local");
-    if path_str.contains("{{p|localappdata}}") || path_str.contains("{{P|localappdata}}") {
-        path_str = path_str.replace("{{p|localappdata}}", &localappdata.to_string_lossy())
-                           .replace("{{P|localappdata}}", &localappdata.to_string_lossy());
+    #[test]
+    fn test_parse_stardew_valley() {
+        let parsed = parse_save_entries(include_str!("../testdata/pcgamingwiki/stardew_valley.wikitext"));
+        assert_eq!(parsed.len(), 5);
+        // The footnote is not part of the path
+        assert_eq!(parsed[0], entry(SavePlatform::Windows, "{{p|appdata}}\\StardewValley\\Saves\\"));
+        assert_eq!(parsed[1].platform, SavePlatform::Other);
+        // Both Linux paths of the row
+        assert_eq!(
+            &parsed[3..],
+            &[
+                entry(SavePlatform::Linux, "~/.config/StardewValley/Saves/"),
+                entry(SavePlatform::Linux, "{{p|xdgconfighome}}/StardewValley/Saves/"),
+            ]
+        );
     }
-    
-    // Saved Games
-    let saved_games = user_profile.join("Saved Games");
-     if path_str.contains("{{p|userprofile}}\\Saved Games") { // Common raw string
-         path_str = path_str.replace("{{p|userprofile}}\\Saved Games", &saved_games.to_string_lossy());
+
+    #[test]
+    fn test_parse_multi_path() {
+        assert_eq!(
+            parse_save_entries(include_str!("../testdata/pcgamingwiki/multi_path.wikitext")),
+            vec![
+                entry(SavePlatform::Steam, "{{p|steam}}\\userdata\\{{p|uid}}\\4000\\remote\\"),
+                entry(SavePlatform::Windows, "{{p|appdata}}\\Example\\Saves\\"),
+                entry(SavePlatform::Windows, "{{p|userprofile}}\\Documents\\My Games\\Example\\"),
+                entry(SavePlatform::SteamPlay, "{{p|steam}}/steamapps/compatdata/4000/pfx/drive_c/users/steamuser/AppData/Roaming/Example/Saves/"),
+            ]
+        );
     }
 
-    // Documents
-    let documents = user_profile.join("Documents");
-     if path_str.contains("{{p|userprofile}}\\Documents") {
-         path_str = path_str.replace("{{p|userprofile}}\\Documents", &documents.to_string_lossy());
+    fn context(root: &Path) -> PathContext {
+        PathContext {
+            steam_user_id: Some("42".to_string()),
+            steam_root: root.join("steam"),
+            install_dir: Some(root.join("steam/steamapps/common/Example")),
+            prefix: root.join("steam/steamapps/compatdata/4000/pfx"),
+            home: root.join("home"),
+        }
     }
 
-    
-    // Normalize slashes: Linux uses forward slash
-    let normalized = path_str.replace("\\", "/");
-    
-    // If it looks like a relative path starting with game dir {{p|game}}, we might need the install dir.
-    // If we can't resolve {{p|game}}, we might return None or try to guess.
-    if normalized.contains("{{p|game}}") || normalized.contains("{{P|game}}") {
-        // We need the actual game install path here.
-        // For now, let's log and skip, or we need to pass the install path to this function.
-        warn!("Skipping path with unresolved {{p|game}}: {}", normalized);
-        return None;
+    #[test]
+    fn test_resolve_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let context = context(root);
+        let path = |p: &str| Some(ResolvedSave::Path(root.join(p)));
+
+        assert_eq!(
+            context.resolve("{{p|game}}\\saves\\"),
+            path("steam/steamapps/common/Example/saves/")
+        );
+        assert_eq!(context.resolve("<path-to-game>/saves"), path("steam/steamapps/common/Example/saves"));
+        assert_eq!(
+            context.resolve("{{P|steam}}\\userdata\\{{P|uid}}\\4000\\remote"),
+            path("steam/userdata/42/4000/remote")
+        );
+        assert_eq!(
+            context.resolve("{{p|programdata}}\\Example"),
+            path("steam/steamapps/compatdata/4000/pfx/drive_c/ProgramData/Example")
+        );
+        assert_eq!(
+            context.resolve("{{p|public}}\\Documents\\Example"),
+            path("steam/steamapps/compatdata/4000/pfx/drive_c/users/Public/Documents/Example")
+        );
+        assert_eq!(
+            context.resolve("{{p|userprofile\\Documents}}\\My Games\\Example"),
+            path("steam/steamapps/compatdata/4000/pfx/drive_c/users/steamuser/Documents/My Games/Example")
+        );
+        assert_eq!(context.resolve("{{p|linuxhome}}/.example"), path("home/.example"));
+        assert_eq!(
+            context.resolve("{{p|hkcu}}\\Software\\{{p|wow64}}\\Example"),
+            Some(ResolvedSave::Registry(
+                Hive::CurrentUser,
+                "Software\\Wow6432Node\\Example".to_string()
+            ))
+        );
+        assert_eq!(context.resolve("{{p|unknown}}\\Example"), None);
+        assert_eq!(context.resolve("{{note|Example}}"), None);
+
+        let no_install = PathContext {
+            install_dir: None,
+            ..context
+        };
+        assert_eq!(no_install.resolve("{{p|game}}\\saves"), None);
     }
 
-    Some(PathBuf::from(normalized))
+    #[test]
+    fn test_prefer_native() {
+        let native = vec![ResolvedSave::Path(PathBuf::from("/native"))];
+        let proton = vec![ResolvedSave::Registry(Hive::CurrentUser, "Software\\Example".to_string())];
+
+        // Linux saves win unless only the Proton ones exist
+        assert_eq!(prefer_native(native.clone(), proton.clone(), |_| false), native);
+        assert_eq!(
+            prefer_native(native.clone(), proton.clone(), |s| matches!(s, ResolvedSave::Registry(..))),
+            proton
+        );
+        assert_eq!(prefer_native(native.clone(), proton.clone(), |_| true), native);
+        assert_eq!(prefer_native(Vec::new(), proton.clone(), |_| false), proton);
+    }
 }
@@ -0,0 +1,208 @@
+//! PCGamingWiki Cache - Save locations kept on disk, plus user overrides
+//!
+//! What the wiki lists for a game is cached per app_id, still in wiki
+//! notation: it is resolved against the current install dir and Proton prefix
+//! on every lookup. Found games are refetched after `CACHE_TTL_SECS`, games
+//! the wiki has no saves for after `MISS_TTL_SECS`. When the wiki can't be
+//! reached an expired entry is used anyway.
+//!
+//! overrides.json pins or adds save paths per game, in the same notation
+//! (`{{p|appdata}}\Game`, `~/.game`, absolute paths, registry keys):
+//! `{ "367520": { "pin": true, "paths": ["{{p|game}}\\saves"] } }`
+//! Pinned paths replace everything else; otherwise they are added to what
+//! the Ludusavi manifest or the wiki found, or used alone if neither has
+//! the game.
+//!
+//! Game metadata (see `pcgamingwiki_metadata`) is cached the same way.
+//!
+//! Local layout (~/.local/share/boilerroom/pcgamingwiki):
+//! - cache/<app_id>.json            (save locations)
+//! - cache/<app_id>.metadata.json   (game metadata)
+//! - overrides.json
+
+use crate::pcgamingwiki::SavePathEntry;
+use crate::pcgamingwiki_metadata::GameMetadata;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How long a game's save locations are trusted
+const CACHE_TTL_SECS: i64 = 7 * 24 * 3600;
+
+/// How long a game the wiki doesn't know (or lists no saves for) stays unknown
+const MISS_TTL_SECS: i64 = 24 * 3600;
+
+/// The wiki's answer for a game
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CachedSaves {
+    /// Unix time of the lookup
+    pub fetched_at: i64,
+    /// False when the wiki has no page or no save section for the game
+    pub found: bool,
+    pub entries: Vec<SavePathEntry>,
+}
+
+impl CachedSaves {
+    pub fn is_fresh(&self, now: i64) -> bool {
+        is_fresh(self.fetched_at, self.found, now)
+    }
+}
+
+/// The wiki's metadata for a game
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CachedMetadata {
+    /// Unix time of the lookup
+    pub fetched_at: i64,
+    /// None when the wiki has no page for the game
+    pub metadata: Option<GameMetadata>,
+}
+
+impl CachedMetadata {
+    pub fn is_fresh(&self, now: i64) -> bool {
+        is_fresh(self.fetched_at, self.metadata.is_some(), now)
+    }
+}
+
+fn is_fresh(fetched_at: i64, found: bool, now: i64) -> bool {
+    let ttl = if found { CACHE_TTL_SECS } else { MISS_TTL_SECS };
+    now - fetched_at < ttl
+}
+
+/// Save paths the user configured for a game
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct SaveOverride {
+    /// Use only these paths
+    #[serde(default)]
+    pub pin: bool,
+    #[serde(default)]
+    pub paths: Vec<String>,
+}
+
+fn pcgamingwiki_root() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home.join(".local/share/boilerroom/pcgamingwiki"))
+}
+
+fn load_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn store_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create PCGamingWiki cache dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize PCGamingWiki cache: {}", e))?;
+
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| format!("Failed to write PCGamingWiki cache: {}", e))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("Failed to save PCGamingWiki cache: {}", e))
+}
+
+fn saves_file(root: &Path, app_id: &str) -> PathBuf {
+    root.join("cache").join(format!("{}.json", app_id))
+}
+
+fn metadata_file(root: &Path, app_id: &str) -> PathBuf {
+    root.join("cache").join(format!("{}.metadata.json", app_id))
+}
+
+fn load_cached_in(root: &Path, app_id: &str) -> Option<CachedSaves> {
+    load_json(&saves_file(root, app_id))
+}
+
+fn store_cached_in(root: &Path, app_id: &str, saves: &CachedSaves) -> Result<(), String> {
+    store_json(&saves_file(root, app_id), saves)
+}
+
+fn load_override_in(root: &Path, app_id: &str) -> Result<Option<SaveOverride>, String> {
+    let content = match std::fs::read_to_string(root.join("overrides.json")) {
+        Ok(content) => content,
+        Err(_) => return Ok(None),
+    };
+    let mut overrides: HashMap<String, SaveOverride> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse save overrides: {}", e))?;
+    Ok(overrides.remove(app_id))
+}
+
+/// Cached save locations of a game, fresh or not
+pub fn load_cached(app_id: &str) -> Option<CachedSaves> {
+    load_cached_in(&pcgamingwiki_root().ok()?, app_id)
+}
+
+/// Cache what the wiki returned for a game
+pub fn store_cached(app_id: &str, saves: &CachedSaves) -> Result<(), String> {
+    store_cached_in(&pcgamingwiki_root()?, app_id, saves)
+}
+
+/// Cached metadata of a game, fresh or not
+pub fn load_cached_metadata(app_id: &str) -> Option<CachedMetadata> {
+    load_json(&metadata_file(&pcgamingwiki_root().ok()?, app_id))
+}
+
+/// Cache the metadata the wiki returned for a game
+pub fn store_cached_metadata(app_id: &str, metadata: &CachedMetadata) -> Result<(), String> {
+    store_json(&metadata_file(&pcgamingwiki_root()?, app_id), metadata)
+}
+
+/// The user's save paths for a game, if any
+pub fn load_override(app_id: &str) -> Result<Option<SaveOverride>, String> {
+    load_override_in(&pcgamingwiki_root()?, app_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcgamingwiki::SavePlatform;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let saves = CachedSaves {
+            fetched_at: 1_000_000,
+            found: true,
+            entries: vec![SavePathEntry {
+                platform: SavePlatform::Windows,
+                path: "{{p|appdata}}\\Example".to_string(),
+            }],
+        };
+        assert_eq!(load_cached_in(root.path(), "4000"), None);
+        store_cached_in(root.path(), "4000", &saves).unwrap();
+        assert_eq!(load_cached_in(root.path(), "4000"), Some(saves.clone()));
+
+        assert!(saves.is_fresh(1_000_000 + CACHE_TTL_SECS - 1));
+        assert!(!saves.is_fresh(1_000_000 + CACHE_TTL_SECS));
+        let miss = CachedSaves {
+            found: false,
+            entries: Vec::new(),
+            ..saves
+        };
+        assert!(!miss.is_fresh(1_000_000 + MISS_TTL_SECS));
+    }
+
+    #[test]
+    fn test_load_override() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(load_override_in(root.path(), "4000"), Ok(None));
+
+        std::fs::write(
+            root.path().join("overrides.json"),
+            r#"{ "4000": { "pin": true, "paths": ["{{p|game}}\\saves"] }, "4001": { "paths": ["~/.example"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            load_override_in(root.path(), "4000"),
+            Ok(Some(SaveOverride {
+                pin: true,
+                paths: vec!["{{p|game}}\\saves".to_string()],
+            }))
+        );
+        assert!(!load_override_in(root.path(), "4001").unwrap().unwrap().pin);
+
+        std::fs::write(root.path().join("overrides.json"), "{ not json").unwrap();
+        assert!(load_override_in(root.path(), "4000").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+//! PCGamingWiki Metadata - What the wiki knows about a game besides its saves
+//!
+//! Infobox fields (developers, engines, platforms) and the API table
+//! (graphics APIs, native executables) come from the Cargo API. The page
+//! wikitext gives the config file locations (`{{Game data/config}}` rows),
+//! the fixes (`{{Fixbox}}`) and the notes about Linux, Proton and the Steam
+//! Deck. Results are cached with the save locations (see `pcgamingwiki_cache`).
+
+use crate::pcgamingwiki::{self, SavePathEntry};
+use crate::pcgamingwiki_cache::{self, CachedMetadata};
+use crate::pcgamingwiki_wikitext as wikitext;
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Infobox_game fields, aliased (Cargo turns `_` in field names into spaces)
+const INFOBOX_FIELDS: &str = "_pageName=PageName,Developers=Developers,Publishers=Publishers,\
+    Engines=Engines,Released=Released,Available_on=Platforms";
+
+const API_FIELDS: &str = "Direct3D_versions=Direct3D,Vulkan_versions=Vulkan,OpenGL_versions=OpenGL,\
+    Windows_64bit_executable=Windows64,Linux_32bit_executable=Linux32,Linux_64bit_executable=Linux64";
+
+/// Words that make a note relevant to playing on Linux
+const LINUX_KEYWORDS: &[&str] = &["linux", "proton", "wine", "steam deck", "steamos"];
+
+/// Graphics APIs and native executables of a game
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GameApi {
+    pub direct3d_versions: Vec<String>,
+    pub vulkan_versions: Vec<String>,
+    pub opengl_versions: Vec<String>,
+    /// None when the wiki doesn't say
+    pub windows_64bit: Option<bool>,
+    pub linux_32bit: Option<bool>,
+    pub linux_64bit: Option<bool>,
+}
+
+/// A game's PCGamingWiki metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GameMetadata {
+    pub app_id: String,
+    pub page_name: String,
+    pub developers: Vec<String>,
+    pub publishers: Vec<String>,
+    pub engines: Vec<String>,
+    pub release_dates: Vec<String>,
+    /// Platforms the game is available on ("Windows", "Linux", "OS X", ...)
+    pub platforms: Vec<String>,
+    pub api: GameApi,
+    /// Config file locations as the wiki writes them
+    pub config_entries: Vec<SavePathEntry>,
+    /// The same locations resolved for this machine (never cached)
+    #[serde(default)]
+    pub config_paths: Vec<PathBuf>,
+    /// Descriptions of the fixes listed on the page
+    pub fixes: Vec<String>,
+    /// Notes mentioning Linux, Proton, Wine or the Steam Deck
+    pub linux_notes: Vec<String>,
+}
+
+// ============================================================================
+// Cargo API
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+struct CargoRows {
+    cargoquery: Vec<CargoRow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoRow {
+    title: CargoFields,
+}
+
+type CargoFields = HashMap<String, serde_json::Value>;
+
+async fn cargo_query(table: &str, fields: &str, condition: &str) -> Result<Option<CargoFields>, String> {
+    let resp = pcgamingwiki::http_client()
+        .get(pcgamingwiki::API_BASE_URL)
+        .query(&[
+            ("action", "cargoquery"),
+            ("tables", table),
+            ("fields", fields),
+            ("where", condition),
+            ("format", "json"),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let data: CargoRows = resp.json().await.map_err(|e| format!("Parse error: {}", e))?;
+    Ok(data.cargoquery.into_iter().next().map(|row| row.title))
+}
+
+fn field<'a>(fields: &'a CargoFields, name: &str) -> &'a str {
+    fields.get(name).and_then(|v| v.as_str()).unwrap_or_default()
+}
+
+/// A Cargo list field, without the `Company:`/`Engine:` page prefixes
+fn list_field(fields: &CargoFields, name: &str) -> Vec<String> {
+    field(fields, name)
+        .split([',', ';'])
+        .map(|item| item.trim())
+        .map(|item| item.split_once(':').map(|(_, name)| name).unwrap_or(item))
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+        .collect()
+}
+
+fn bool_field(fields: &CargoFields, name: &str) -> Option<bool> {
+    match field(fields, name) {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Page Content
+// ============================================================================
+
+fn fixes(wikitext: &str) -> Vec<String> {
+    wikitext::find_templates(&wikitext::strip_markup(wikitext), "Fixbox")
+        .iter()
+        .filter_map(|fixbox| fixbox.named("description"))
+        .map(wikitext::plain_text)
+        .filter(|description| !description.is_empty())
+        .collect()
+}
+
+fn linux_notes(wikitext: &str) -> Vec<String> {
+    let mut notes: Vec<String> = Vec::new();
+    for line in wikitext::strip_markup(wikitext).lines() {
+        let line = line.trim();
+        let is_note = ["{{ii}}", "{{++}}", "{{--}}", "{{mm}}"]
+            .iter()
+            .any(|marker| line.starts_with(marker));
+        if !is_note {
+            continue;
+        }
+        let note = wikitext::plain_text(line);
+        let lower = note.to_lowercase();
+        if LINUX_KEYWORDS.iter().any(|k| lower.contains(k)) && !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+    notes
+}
+
+fn from_wiki(app_id: &str, infobox: &CargoFields, api: Option<&CargoFields>, wikitext: &str) -> GameMetadata {
+    let api = api
+        .map(|api| GameApi {
+            direct3d_versions: list_field(api, "Direct3D"),
+            vulkan_versions: list_field(api, "Vulkan"),
+            opengl_versions: list_field(api, "OpenGL"),
+            windows_64bit: bool_field(api, "Windows64"),
+            linux_32bit: bool_field(api, "Linux32"),
+            linux_64bit: bool_field(api, "Linux64"),
+        })
+        .unwrap_or_default();
+
+    GameMetadata {
+        app_id: app_id.to_string(),
+        page_name: field(infobox, "PageName").to_string(),
+        developers: list_field(infobox, "Developers"),
+        publishers: list_field(infobox, "Publishers"),
+        engines: list_field(infobox, "Engines"),
+        release_dates: list_field(infobox, "Released"),
+        platforms: list_field(infobox, "Platforms"),
+        api,
+        config_entries: pcgamingwiki::parse_config_entries(wikitext),
+        config_paths: Vec::new(),
+        fixes: fixes(wikitext),
+        linux_notes: linux_notes(wikitext),
+    }
+}
+
+/// Look a game up on the wiki. None if it has no page.
+async fn fetch_metadata(app_id: &str) -> Result<Option<GameMetadata>, String> {
+    let condition = format!("Infobox_game.Steam_AppID HOLDS '{}'", app_id);
+    let Some(infobox) = cargo_query("Infobox_game", INFOBOX_FIELDS, &condition).await? else {
+        return Ok(None);
+    };
+    let page_name = field(&infobox, "PageName").to_string();
+    info!("PCGamingWiki: Fetching metadata of '{}' for AppID {}", page_name, app_id);
+
+    let condition = format!("API._pageName=\"{}\"", page_name.replace('"', "\\\""));
+    let api = cargo_query("API", API_FIELDS, &condition).await?;
+    let wikitext = pcgamingwiki::get_wikitext(&page_name, None).await?;
+
+    Ok(Some(from_wiki(app_id, &infobox, api.as_ref(), &wikitext)))
+}
+
+/// Metadata of a game, from the cache while it is fresh. An expired entry is
+/// still used when the wiki can't be reached.
+pub async fn game_metadata(app_id: &str, steam_user_id: Option<&str>) -> Result<GameMetadata, String> {
+    let now = Utc::now().timestamp();
+    let cached = pcgamingwiki_cache::load_cached_metadata(app_id);
+
+    let entry = match cached {
+        Some(cached) if cached.is_fresh(now) => cached,
+        cached => match fetch_metadata(app_id).await {
+            Ok(metadata) => {
+                let entry = CachedMetadata {
+                    fetched_at: now,
+                    metadata,
+                };
+                if let Err(e) = pcgamingwiki_cache::store_cached_metadata(app_id, &entry) {
+                    warn!("{}", e);
+                }
+                entry
+            }
+            Err(e) => match cached {
+                Some(cached) => {
+                    warn!("PCGamingWiki: {}, using cached metadata for AppID {}", e, app_id);
+                    cached
+                }
+                None => return Err(e),
+            },
+        },
+    };
+
+    let mut metadata = entry.metadata.ok_or("Game not found on PCGamingWiki")?;
+    metadata.config_paths =
+        pcgamingwiki::resolve_file_locations(app_id, steam_user_id, metadata.config_entries.clone());
+    Ok(metadata)
+}
+
+/// Config file locations of a game on this machine (empty if unknown)
+pub async fn config_locations(app_id: &str, steam_user_id: Option<&str>) -> Vec<PathBuf> {
+    match game_metadata(app_id, steam_user_id).await {
+        Ok(metadata) => metadata.config_paths,
+        Err(e) => {
+            info!("PCGamingWiki: No config locations for AppID {}: {}", app_id, e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcgamingwiki::SavePlatform;
+
+    fn fields(json: &str) -> CargoFields {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_from_wiki() {
+        let infobox = fields(
+            r#"{"PageName": "Example", "Developers": "Company:Example Studio,Company:Porting House",
+                "Publishers": "Company:Example Publishing", "Engines": "Engine:Unity",
+                "Released": "2024-01-02", "Platforms": "Windows,Linux"}"#,
+        );
+        let api = fields(
+            r#"{"Direct3D": "11", "Vulkan": "", "OpenGL": "4.1",
+                "Windows64": "true", "Linux32": "false", "Linux64": "unknown"}"#,
+        );
+        let metadata = from_wiki(
+            "4000",
+            &infobox,
+            Some(&api),
+            include_str!("../testdata/pcgamingwiki/metadata_page.wikitext"),
+        );
+
+        assert_eq!(metadata.page_name, "Example");
+        assert_eq!(metadata.developers, vec!["Example Studio", "Porting House"]);
+        assert_eq!(metadata.engines, vec!["Unity"]);
+        assert_eq!(metadata.platforms, vec!["Windows", "Linux"]);
+        assert_eq!(
+            metadata.api,
+            GameApi {
+                direct3d_versions: vec!["11".to_string()],
+                vulkan_versions: vec![],
+                opengl_versions: vec!["4.1".to_string()],
+                windows_64bit: Some(true),
+                linux_32bit: Some(false),
+                linux_64bit: None,
+            }
+        );
+
+        // Config rows, not the save rows
+        let config: Vec<(SavePlatform, &str)> = metadata
+            .config_entries
+            .iter()
+            .map(|e| (e.platform, e.path.as_str()))
+            .collect();
+        assert_eq!(
+            config,
+            vec![
+                (SavePlatform::Windows, "{{p|localappdata}}\\Example\\config.ini"),
+                (SavePlatform::Windows, "{{p|hkcu}}\\Software\\Example Studio\\Example"),
+                (SavePlatform::Linux, "{{p|xdgconfighome}}/unity3d/Example Studio/Example/prefs"),
+            ]
+        );
+
+        assert_eq!(metadata.fixes, vec!["Use the launch option -force-d3d11"]);
+        assert_eq!(
+            metadata.linux_notes,
+            vec![
+                "Runs well under Proton 8.0 or later.",
+                "Videos don't play on the Steam Deck without GE-Proton.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_wiki_without_api() {
+        let metadata = from_wiki("4000", &fields(r#"{"PageName": "Example"}"#), None, "");
+        assert_eq!(metadata.api, GameApi::default());
+        assert!(metadata.developers.is_empty());
+        assert!(metadata.config_entries.is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+//! PCGamingWiki Wikitext - Tokenizer for MediaWiki templates
+//!
+//! PCGamingWiki sections are wikitext full of nested templates, e.g.
+//! `{{Game data/saves|Windows|{{p|appdata}}\Game\|{{p|game}}\saves\}}`.
+//! Templates are split into their name and arguments on the pipes that
+//! belong to them; pipes of nested templates (`{{p|appdata}}`) and links
+//! (`[[Page|text]]`) stay inside the argument. Comments and `<ref>`
+//! footnotes are dropped first, since they can hold pipes and braces too.
+
+use regex::Regex;
+
+/// A template call with its raw (still templated) arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Template {
+    /// Whether this is the template `name` (case-insensitive, `_` for spaces)
+    pub fn is(&self, name: &str) -> bool {
+        normalize_name(&self.name) == normalize_name(name)
+    }
+
+    /// Value of a named argument (`|description=...`)
+    pub fn named(&self, key: &str) -> Option<&str> {
+        self.args.iter().find_map(|arg| {
+            let (name, value) = arg.split_once('=')?;
+            (normalize_name(name) == normalize_name(key)).then(|| value.trim())
+        })
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().replace('_', " ").to_lowercase()
+}
+
+/// Remove everything between `open` and `close` (inclusive). An unterminated
+/// `open` drops the rest of the text.
+fn remove_between(text: &str, open: &str, close: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(open) {
+        result.push_str(&rest[..start]);
+        match rest[start..].find(close) {
+            Some(end) => rest = &rest[start + end + close.len()..],
+            None => return result,
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Drop comments, `<ref>` footnotes and `<br>` line breaks
+pub fn strip_markup(text: &str) -> String {
+    let text = remove_between(text, "<!--", "-->");
+    // Self-closing refs first, so they aren't taken as the start of a block
+    let mut stripped = String::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("<ref") {
+        stripped.push_str(&rest[..start]);
+        let tag_end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => {
+                rest = "";
+                break;
+            }
+        };
+        if rest[..tag_end].ends_with('/') {
+            rest = &rest[tag_end + 1..];
+            continue;
+        }
+        match rest[tag_end..].find("</ref>") {
+            Some(end) => rest = &rest[tag_end + end + "</ref>".len()..],
+            None => rest = "",
+        }
+    }
+    stripped.push_str(rest);
+
+    stripped
+        .replace("<br>", " ")
+        .replace("<br/>", " ")
+        .replace("<br />", " ")
+}
+
+/// Readable text of wikitext: links become their label, templates and
+/// bold/italic quotes are dropped
+pub fn plain_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+
+    loop {
+        let next_template = rest.find("{{");
+        let next_link = rest.find("[[");
+        let start = match (next_template, next_link) {
+            (Some(t), Some(l)) => t.min(l),
+            (Some(t), None) => t,
+            (None, Some(l)) => l,
+            (None, None) => break,
+        };
+        plain.push_str(&rest[..start]);
+
+        if Some(start) == next_template {
+            match parse_template_at(rest, start) {
+                Some((_, end)) => rest = &rest[end..],
+                None => rest = "",
+            }
+        } else {
+            match rest[start..].find("]]") {
+                Some(end) => {
+                    let link = &rest[start + 2..start + end];
+                    plain.push_str(link.rsplit('|').next().unwrap_or(link));
+                    rest = &rest[start + end + 2..];
+                }
+                None => {
+                    plain.push_str(&rest[start + 2..]);
+                    rest = "";
+                }
+            }
+        }
+    }
+    plain.push_str(rest);
+
+    // External links keep their label, HTML tags (<code>, <sup>) go
+    let external_link = Regex::new(r"\[https?://\S+\s+([^\]]+)\]").unwrap();
+    let html_tag = Regex::new(r"</?[a-zA-Z][^>]*>").unwrap();
+    let plain = external_link.replace_all(&plain, "$1");
+    let plain = html_tag.replace_all(&plain, "");
+
+    plain
+        .replace("'''", "")
+        .replace("''", "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Top-level templates of a text. Unterminated templates are ignored.
+pub fn parse_templates(text: &str) -> Vec<Template> {
+    let bytes = text.as_bytes();
+    let mut templates = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < bytes.len() {
+        if &bytes[i..i + 2] == b"{{" {
+            match parse_template_at(text, i) {
+                Some((template, end)) => {
+                    templates.push(template);
+                    i = end;
+                    continue;
+                }
+                None => return templates,
+            }
+        }
+        i += 1;
+    }
+    templates
+}
+
+/// Parse the template starting at `start` (on its `{{`). Returns it and the
+/// index just after its closing `}}`.
+fn parse_template_at(text: &str, start: usize) -> Option<(Template, usize)> {
+    let bytes = text.as_bytes();
+    let mut parts: Vec<String> = Vec::new();
+    let mut part_start = start + 2;
+    // Nesting inside this template: templates and links
+    let mut braces = 0usize;
+    let mut brackets = 0usize;
+    let mut i = start + 2;
+
+    while i < bytes.len() {
+        let pair = bytes.get(i..i + 2);
+        if pair == Some(b"{{") {
+            braces += 1;
+            i += 2;
+        } else if pair == Some(b"}}") {
+            if braces == 0 {
+                parts.push(text[part_start..i].to_string());
+                let mut parts = parts.into_iter();
+                let name = parts.next().unwrap_or_default().trim().to_string();
+                let args = parts.map(|arg| arg.trim().to_string()).collect();
+                return Some((Template { name, args }, i + 2));
+            }
+            braces -= 1;
+            i += 2;
+        } else if pair == Some(b"[[") {
+            brackets += 1;
+            i += 2;
+        } else if pair == Some(b"]]") {
+            brackets = brackets.saturating_sub(1);
+            i += 2;
+        } else {
+            if bytes[i] == b'|' && braces == 0 && brackets == 0 {
+                parts.push(text[part_start..i].to_string());
+                part_start = i + 1;
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Every template called `name`, at any depth
+pub fn find_templates(text: &str, name: &str) -> Vec<Template> {
+    let mut found = Vec::new();
+    for template in parse_templates(text) {
+        for arg in &template.args {
+            found.extend(find_templates(arg, name));
+        }
+        if template.is(name) {
+            found.push(template);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_templates() {
+        let templates =
+            parse_templates("x {{Game data/saves|Windows|{{p|appdata}}\\Game\\|[[Link|text]]}} y");
+        assert_eq!(
+            templates,
+            vec![Template {
+                name: "Game data/saves".to_string(),
+                args: vec![
+                    "Windows".to_string(),
+                    "{{p|appdata}}\\Game\\".to_string(),
+                    "[[Link|text]]".to_string(),
+                ],
+            }]
+        );
+        assert!(parse_templates("{{Game data/saves|Windows|{{p|appdata}}").is_empty());
+    }
+
+    #[test]
+    fn test_find_templates() {
+        let text = "{{Game data|\n{{Game data/saves|Windows|a}}\n{{Game_data/saves|Linux|b}}\n}}";
+        let found = find_templates(text, "game data/saves");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].args, vec!["Linux".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn test_named_args_and_plain_text() {
+        let fixbox = &parse_templates("{{Fixbox|description=Use [[Proton]] 8|fix=x}}")[0];
+        assert_eq!(fixbox.named("Description"), Some("Use [[Proton]] 8"));
+        assert_eq!(fixbox.named("missing"), None);
+
+        assert_eq!(
+            plain_text("{{ii}} '''Crashes''' on [[Steam Deck|the Deck]] with\n [[Proton]] 7.{{CN}}"),
+            "Crashes on the Deck with Proton 7."
+        );
+        assert_eq!(
+            plain_text("Set <code>-dx11</code>, see [https://example.com/a the forum]"),
+            "Set -dx11, see the forum"
+        );
+    }
+
+    #[test]
+    fn test_strip_markup() {
+        assert_eq!(
+            strip_markup("a<!-- {{x|y}} -->b<ref name=\"n\"/>c<ref>{{Refcheck|user=X}}</ref>d<br>e"),
+            "abcd e"
+        );
+    }
+}
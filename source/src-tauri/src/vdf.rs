@@ -0,0 +1,709 @@
+//! VDF - Steam's text KeyValues files, parsed one way and typed
+//!
+//! Steam keeps its local state in text VDF: quoted keys and values, `{ }`
+//! blocks, `//` comments and backslash escapes (`"C:\\Games"`, `"\"x\""`).
+//! Files are parsed with `keyvalues-parser`; files with bare backslashes
+//! (written by old clients or by hand) are retried with backslashes taken
+//! literally. Keys are matched case-insensitively, since Steam's casing
+//! changed between client versions (`MostRecent` vs `mostrecent`).
+//!
+//! Typed models:
+//! - `LibraryFolders`  steamapps/libraryfolders.vdf
+//! - `AppManifest`     steamapps/appmanifest_<appid>.acf
+//! - `LoginUsers`      config/loginusers.vdf
+//! - `RemoteCache`     userdata/<account_id>/<appid>/remotecache.vdf
+//!
+//! `AppManifest` and `LoginUsers` go through `keyvalues-serde`. The other two
+//! put plain values next to blocks at the same level (`"contentstatsid"`
+//! beside `"0" { }`, `"ChangeNumber"` beside one block per file), which serde
+//! structs can't express, so they are read from and rendered as a tree.
+//! Every model renders back with `to_vdf`; keys come out sorted, and
+//! comments and keys the model doesn't carry are dropped.
+
+use keyvalues_parser::{Obj, Parser, Value, Vdf};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// Parse text VDF
+pub fn parse(content: &str) -> Result<Vdf<'_>, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let parsed = match keyvalues_parser::parse(content) {
+        Ok(parsed) => parsed,
+        Err(_) => Parser::new()
+            .literal_special_chars(true)
+            .parse(content)
+            .map_err(|e| format!("Failed to parse VDF: {}", e))?,
+    };
+    Ok(Vdf::from(parsed))
+}
+
+/// The `"key" { ... }` block at the first `"key"` of a text that has other
+/// output around it (SteamCMD prints log lines before and after)
+pub fn find_block<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let start = text.find(&format!("\"{}\"", key))?;
+    let mut depth = 0usize;
+    let mut in_quote = false;
+    let mut escaped = false;
+
+    for (i, c) in text[start..].char_indices() {
+        if in_quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_quote = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quote = true,
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(&text[start..start + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// First value of a key
+pub fn get<'a>(obj: &'a Obj<'a>, key: &str) -> Option<&'a Value<'a>> {
+    obj.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .and_then(|(_, values)| values.first())
+}
+
+pub fn get_str<'a>(obj: &'a Obj<'a>, key: &str) -> Option<&'a str> {
+    get(obj, key)?.get_str()
+}
+
+pub fn get_obj<'a>(obj: &'a Obj<'a>, key: &str) -> Option<&'a Obj<'a>> {
+    get(obj, key)?.get_obj()
+}
+
+/// Value at a path of nested keys
+pub fn lookup<'a>(obj: &'a Obj<'a>, path: &[&str]) -> Option<&'a Value<'a>> {
+    let (last, parents) = path.split_last()?;
+    let mut obj = obj;
+    for key in parents {
+        obj = get_obj(obj, key)?;
+    }
+    get(obj, last)
+}
+
+pub fn lookup_str<'a>(obj: &'a Obj<'a>, path: &[&str]) -> Option<&'a str> {
+    lookup(obj, path)?.get_str()
+}
+
+/// Number value of a key, 0 when missing or malformed
+fn get_num<T: FromStr + Default>(obj: &Obj<'_>, key: &str) -> T {
+    get_str(obj, key)
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+/// Block `key` of `obj`, created if missing
+pub fn child_obj_mut<'a, 'text>(obj: &'a mut Obj<'text>, key: &str) -> &'a mut Obj<'text> {
+    let existing = obj
+        .iter()
+        .find(|(k, values)| k.eq_ignore_ascii_case(key) && values.iter().any(|v| v.is_obj()))
+        .map(|(k, _)| k.clone());
+    let values = obj
+        .entry(existing.unwrap_or_else(|| Cow::Owned(key.to_string())))
+        .or_default();
+
+    let index = match values.iter().position(|v| v.is_obj()) {
+        Some(index) => index,
+        None => {
+            values.push(Value::Obj(Obj::new()));
+            values.len() - 1
+        }
+    };
+    match &mut values[index] {
+        Value::Obj(obj) => obj,
+        Value::Str(_) => unreachable!("index points at a block"),
+    }
+}
+
+/// Set `key` of `obj` to a plain value
+pub fn insert_str(obj: &mut Obj<'_>, key: &str, value: impl ToString) {
+    obj.insert(
+        Cow::Owned(key.to_string()),
+        vec![Value::Str(Cow::Owned(value.to_string()))],
+    );
+}
+
+// ============================================================================
+// libraryfolders.vdf
+// ============================================================================
+
+/// Steam library folders
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryFolders {
+    /// Libraries by index; "0" is the one inside the Steam installation
+    pub folders: BTreeMap<String, LibraryFolder>,
+    pub content_stats_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryFolder {
+    /// Directory holding the library's steamapps folder
+    pub path: String,
+    pub label: String,
+    pub content_id: Option<String>,
+    pub total_size: Option<u64>,
+    /// Installed app IDs with their size on disk
+    pub apps: BTreeMap<String, u64>,
+}
+
+impl LibraryFolders {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let vdf = parse(content)?;
+        let root = vdf
+            .value
+            .get_obj()
+            .ok_or("libraryfolders.vdf has no folder list")?;
+        let mut library = LibraryFolders::default();
+
+        for (key, values) in root.iter() {
+            match values.first() {
+                Some(Value::Obj(folder)) => {
+                    let apps = get_obj(folder, "apps")
+                        .map(|apps| {
+                            apps.keys()
+                                .map(|app_id| (app_id.to_string(), get_num(apps, app_id)))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    library.folders.insert(
+                        key.to_string(),
+                        LibraryFolder {
+                            path: get_str(folder, "path").unwrap_or_default().to_string(),
+                            label: get_str(folder, "label").unwrap_or_default().to_string(),
+                            content_id: get_str(folder, "contentid").map(|id| id.to_string()),
+                            total_size: get_str(folder, "totalsize").and_then(|s| s.parse().ok()),
+                            apps,
+                        },
+                    );
+                }
+                // Clients before 2021 listed libraries as "1" "<path>"
+                Some(Value::Str(path)) if key.chars().all(|c| c.is_ascii_digit()) => {
+                    library.folders.insert(
+                        key.to_string(),
+                        LibraryFolder {
+                            path: path.to_string(),
+                            ..Default::default()
+                        },
+                    );
+                }
+                Some(Value::Str(id)) if key.eq_ignore_ascii_case("contentstatsid") => {
+                    library.content_stats_id = Some(id.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(library)
+    }
+
+    /// The libraries as libraryfolders.vdf, in the current format
+    pub fn to_vdf(&self) -> String {
+        let mut root = Obj::new();
+        if let Some(id) = &self.content_stats_id {
+            insert_str(&mut root, "contentstatsid", id);
+        }
+
+        for (key, folder) in &self.folders {
+            let mut obj = Obj::new();
+            insert_str(&mut obj, "path", &folder.path);
+            insert_str(&mut obj, "label", &folder.label);
+            if let Some(id) = &folder.content_id {
+                insert_str(&mut obj, "contentid", id);
+            }
+            if let Some(size) = folder.total_size {
+                insert_str(&mut obj, "totalsize", size);
+            }
+            let apps = child_obj_mut(&mut obj, "apps");
+            for (app_id, size) in &folder.apps {
+                insert_str(apps, app_id, size);
+            }
+            root.insert(Cow::Borrowed(key.as_str()), vec![Value::Obj(obj)]);
+        }
+
+        Vdf::new(Cow::Borrowed("libraryfolders"), Value::Obj(root)).to_string()
+    }
+
+    /// Directories of all libraries
+    pub fn paths(&self) -> Vec<String> {
+        self.folders
+            .values()
+            .map(|folder| folder.path.clone())
+            .filter(|path| !path.is_empty())
+            .collect()
+    }
+}
+
+// ============================================================================
+// appmanifest_<appid>.acf
+// ============================================================================
+
+/// An installed app
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppManifest {
+    #[serde(alias = "appID", alias = "AppID")]
+    pub appid: String,
+    #[serde(
+        rename = "Universe",
+        alias = "universe",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub universe: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(
+        rename = "StateFlags",
+        alias = "stateflags",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub state_flags: Option<u32>,
+    /// Directory name under steamapps/common
+    #[serde(default, alias = "InstallDir")]
+    pub installdir: String,
+    #[serde(
+        rename = "LastUpdated",
+        alias = "lastupdated",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_updated: Option<u64>,
+    #[serde(
+        rename = "SizeOnDisk",
+        alias = "sizeondisk",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub size_on_disk: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buildid: Option<String>,
+    #[serde(
+        rename = "InstalledDepots",
+        alias = "installeddepots",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub installed_depots: BTreeMap<String, InstalledDepot>,
+    /// Per-user settings such as `platform_override_source`
+    #[serde(
+        rename = "UserConfig",
+        alias = "userconfig",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub user_config: Option<BTreeMap<String, String>>,
+    /// Settings the installed build was set up with
+    #[serde(
+        rename = "MountedConfig",
+        alias = "mountedconfig",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mounted_config: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstalledDepot {
+    pub manifest: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl AppManifest {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        keyvalues_serde::from_vdf(parse(content)?)
+            .map_err(|e| format!("Failed to parse app manifest: {}", e))
+    }
+
+    /// `UserConfig`/`MountedConfig` entries that make Steam install the
+    /// Windows build on Linux, to run it through Proton
+    pub fn windows_platform_override() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("platform_override_dest".to_string(), "linux".to_string()),
+            ("platform_override_source".to_string(), "windows".to_string()),
+        ])
+    }
+
+    /// The manifest as an .acf file
+    pub fn to_vdf(&self) -> Result<String, String> {
+        keyvalues_serde::to_string_with_key(self, "AppState")
+            .map_err(|e| format!("Failed to serialize app manifest: {}", e))
+    }
+}
+
+// ============================================================================
+// loginusers.vdf
+// ============================================================================
+
+/// Accounts that logged in on this machine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoginUsers {
+    /// Accounts by SteamID64
+    pub users: BTreeMap<String, LoginUser>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoginUser {
+    #[serde(
+        rename = "AccountName",
+        alias = "accountname",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub account_name: Option<String>,
+    #[serde(
+        rename = "PersonaName",
+        alias = "personaname",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub persona_name: Option<String>,
+    #[serde(rename = "RememberPassword", alias = "rememberpassword", default)]
+    pub remember_password: bool,
+    /// The account Steam logged in last
+    #[serde(rename = "MostRecent", alias = "mostrecent", default)]
+    pub most_recent: bool,
+    #[serde(rename = "Timestamp", alias = "timestamp", default)]
+    pub timestamp: u64,
+}
+
+impl LoginUsers {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let users = keyvalues_serde::from_vdf(parse(content)?)
+            .map_err(|e| format!("Failed to parse loginusers.vdf: {}", e))?;
+        Ok(LoginUsers { users })
+    }
+
+    /// The accounts as loginusers.vdf
+    pub fn to_vdf(&self) -> Result<String, String> {
+        keyvalues_serde::to_string_with_key(&self.users, "users")
+            .map_err(|e| format!("Failed to serialize loginusers.vdf: {}", e))
+    }
+}
+
+// ============================================================================
+// remotecache.vdf
+// ============================================================================
+
+/// Steam Cloud's record of a game's synced files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteCache {
+    pub app_id: String,
+    pub change_number: i64,
+    pub ostype: Option<String>,
+    /// Files by path relative to their root
+    pub files: BTreeMap<String, RemoteCacheFile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemoteCacheFile {
+    /// Steam Cloud root ID (see `cloudsync_roots::root`)
+    pub root: i32,
+    pub size: u64,
+    pub localtime: u64,
+    pub time: u64,
+    pub remotetime: u64,
+    pub sha: String,
+    pub syncstate: i32,
+    pub persiststate: i32,
+    pub platformstosync2: i32,
+}
+
+impl RemoteCache {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let vdf = parse(content)?;
+        let root = vdf
+            .value
+            .get_obj()
+            .ok_or("remotecache.vdf has no file list")?;
+        let mut cache = RemoteCache {
+            app_id: vdf.key.to_string(),
+            change_number: get_num(root, "ChangeNumber"),
+            ostype: get_str(root, "ostype").map(|s| s.to_string()),
+            files: BTreeMap::new(),
+        };
+
+        for (path, values) in root.iter() {
+            let Some(file) = values.first().and_then(|v| v.get_obj()) else {
+                continue;
+            };
+            cache.files.insert(
+                path.to_string(),
+                RemoteCacheFile {
+                    root: get_num(file, "root"),
+                    size: get_num(file, "size"),
+                    localtime: get_num(file, "localtime"),
+                    time: get_num(file, "time"),
+                    remotetime: get_num(file, "remotetime"),
+                    sha: get_str(file, "sha").unwrap_or_default().to_string(),
+                    syncstate: get_num(file, "syncstate"),
+                    persiststate: get_num(file, "persiststate"),
+                    platformstosync2: get_num(file, "platformstosync2"),
+                },
+            );
+        }
+
+        Ok(cache)
+    }
+
+    /// The record as remotecache.vdf
+    pub fn to_vdf(&self) -> String {
+        let mut root = Obj::new();
+        insert_str(&mut root, "ChangeNumber", self.change_number);
+        if let Some(ostype) = &self.ostype {
+            insert_str(&mut root, "ostype", ostype);
+        }
+
+        for (path, file) in &self.files {
+            let mut obj = Obj::new();
+            insert_str(&mut obj, "root", file.root);
+            insert_str(&mut obj, "size", file.size);
+            insert_str(&mut obj, "localtime", file.localtime);
+            insert_str(&mut obj, "time", file.time);
+            insert_str(&mut obj, "remotetime", file.remotetime);
+            insert_str(&mut obj, "sha", &file.sha);
+            insert_str(&mut obj, "syncstate", file.syncstate);
+            insert_str(&mut obj, "persiststate", file.persiststate);
+            insert_str(&mut obj, "platformstosync2", file.platformstosync2);
+            // Paths differing only in case are separate files
+            root.insert(Cow::Borrowed(path.as_str()), vec![Value::Obj(obj)]);
+        }
+
+        Vdf::new(Cow::Borrowed(self.app_id.as_str()), Value::Obj(root)).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quirks() {
+        // Comments, escapes, one-line blocks and keys in another casing
+        let vdf = parse(
+            "// written by Steam\n\"Root\" { \"Path\" \"C:\\\\Games\\\\\\\"x\\\"\" \"Block\" { \"a\" \"1\" } }",
+        )
+        .unwrap();
+        let root = vdf.value.get_obj().unwrap();
+        assert_eq!(get_str(root, "path"), Some("C:\\Games\\\"x\""));
+        assert_eq!(lookup_str(root, &["block", "A"]), Some("1"));
+        assert_eq!(lookup_str(root, &["block", "b"]), None);
+
+        // Bare backslashes are taken literally
+        let vdf = parse("\"Root\"\n{\n\t\"path\"\t\"D:\\SteamLibrary\"\n}\n").unwrap();
+        assert_eq!(
+            get_str(vdf.value.get_obj().unwrap(), "path"),
+            Some("D:\\SteamLibrary")
+        );
+
+        assert!(parse("\"Root\" { \"unterminated\" ").is_err());
+    }
+
+    #[test]
+    fn test_find_block() {
+        let output = "AppID : 730, change number : 1\n\"730\"\n{\n\t\"name\" \"}\\\"\"\n\t\"x\" { }\n}\nUnloading Steam API...}";
+        assert_eq!(
+            find_block(output, "730"),
+            Some("\"730\"\n{\n\t\"name\" \"}\\\"\"\n\t\"x\" { }\n}")
+        );
+        assert_eq!(find_block(output, "570"), None);
+    }
+
+    #[test]
+    fn test_child_obj_mut() {
+        let mut vdf = parse("\"Root\" { \"Software\" { \"keep\" \"1\" } }").unwrap();
+        let root = vdf.value.get_mut_obj().unwrap();
+        insert_str(
+            child_obj_mut(child_obj_mut(root, "software"), "Valve"),
+            "x",
+            2,
+        );
+
+        let rendered = vdf.to_string();
+        let reparsed = parse(&rendered).unwrap();
+        let root = reparsed.value.get_obj().unwrap();
+        assert_eq!(lookup_str(root, &["Software", "keep"]), Some("1"));
+        assert_eq!(lookup_str(root, &["Software", "Valve", "x"]), Some("2"));
+        assert!(!rendered.contains("\"software\""));
+    }
+
+    #[test]
+    fn test_library_folders() {
+        let content = r#"
+"libraryfolders"
+{
+	"contentstatsid"		"-123"
+	"0"
+	{
+		"path"		"/home/deck/.local/share/Steam"
+		"label"		""
+		"contentid"		"42"
+		"totalsize"		"0"
+		"apps"
+		{
+			"228980"		"365000"
+			"1245620"		"50000000"
+		}
+	}
+	"1"
+	{
+		"path"		"/run/media/mmcblk0p1"
+		"label"		"SD"
+		"apps"		{ }
+	}
+}
+"#;
+        let library = LibraryFolders::parse(content).unwrap();
+        assert_eq!(library.content_stats_id.as_deref(), Some("-123"));
+        assert_eq!(
+            library.paths(),
+            vec!["/home/deck/.local/share/Steam", "/run/media/mmcblk0p1"]
+        );
+        assert_eq!(library.folders["0"].apps["1245620"], 50000000);
+        assert_eq!(library.folders["1"].total_size, None);
+        assert_eq!(LibraryFolders::parse(&library.to_vdf()).unwrap(), library);
+
+        // Pre-2021 format
+        let legacy = LibraryFolders::parse(
+            "\"LibraryFolders\"\n{\n\t\"TimeNextStatsReport\"\t\"1\"\n\t\"1\"\t\"D:\\\\SteamLibrary\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(legacy.paths(), vec!["D:\\SteamLibrary"]);
+        // ...is written in the current one
+        let rendered = legacy.to_vdf();
+        assert!(rendered.contains("\"path\"\t\"D:\\\\SteamLibrary\""));
+        assert_eq!(LibraryFolders::parse(&rendered).unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_app_manifest() {
+        let content = r#"
+"AppState"
+{
+	"appid"		"1245620"
+	"Universe"		"1"
+	"name"		"ELDEN RING"
+	"StateFlags"		"4"
+	"installdir"		"ELDEN RING"
+	"LastUpdated"		"1700000000"
+	"SizeOnDisk"		"50000000"
+	"buildid"		"12345"
+	"InstalledDepots"
+	{
+		"1245621"
+		{
+			"manifest"		"8000000000000000000"
+			"size"		"49000000"
+		}
+	}
+	"UserConfig"
+	{
+		"language"		"english"
+	}
+}
+"#;
+        let manifest = AppManifest::parse(content).unwrap();
+        assert_eq!(manifest.appid, "1245620");
+        assert_eq!(manifest.installdir, "ELDEN RING");
+        assert_eq!(manifest.state_flags, Some(4));
+        assert_eq!(manifest.installed_depots["1245621"].size, Some(49000000));
+        assert_eq!(manifest.user_config.as_ref().unwrap()["language"], "english");
+        assert_eq!(
+            AppManifest::parse(&manifest.to_vdf().unwrap()).unwrap(),
+            manifest
+        );
+
+        // Written manifests escape what they hold
+        let written = AppManifest {
+            appid: "4000".to_string(),
+            name: Some("Say \"Hi\" \\ Bye".to_string()),
+            installdir: "Say Hi".to_string(),
+            ..Default::default()
+        };
+        let rendered = written.to_vdf().unwrap();
+        assert!(rendered.contains(r#""Say \"Hi\" \\ Bye""#));
+        assert_eq!(AppManifest::parse(&rendered).unwrap(), written);
+
+        // Old clients wrote appID
+        let old =
+            AppManifest::parse("\"AppState\" { \"appID\" \"70\" \"installdir\" \"Half-Life\" }")
+                .unwrap();
+        assert_eq!(old.appid, "70");
+        assert!(AppManifest::parse("\"AppState\" { \"name\" \"x\" }").is_err());
+    }
+
+    #[test]
+    fn test_login_users() {
+        let content = r#"
+"users"
+{
+	"76561198000000000"
+	{
+		"AccountName"		"deck"
+		"PersonaName"		"Deck \"User\""
+		"RememberPassword"		"1"
+		"WantsOfflineMode"		"0"
+		"mostrecent"		"1"
+		"Timestamp"		"1600000000"
+	}
+}
+"#;
+        let login_users = LoginUsers::parse(content).unwrap();
+        let user = &login_users.users["76561198000000000"];
+        assert_eq!(user.persona_name.as_deref(), Some("Deck \"User\""));
+        assert!(user.most_recent);
+        assert_eq!(user.timestamp, 1600000000);
+
+        let rendered = login_users.to_vdf().unwrap();
+        assert!(rendered.contains("\"MostRecent\"\t\"1\""));
+        assert_eq!(LoginUsers::parse(&rendered).unwrap(), login_users);
+    }
+
+    #[test]
+    fn test_remote_cache() {
+        let content = r#"
+"730"
+{
+	"ChangeNumber"		"5"
+	"ostype"		"-184"
+	"cfg/config.cfg"
+	{
+		"root"		"0"
+		"size"		"24"
+		"localtime"		"1522861973"
+		"time"		"1522861973"
+		"remotetime"		"1522861973"
+		"sha"		"c7bdc563982fc4ddddb9e6f8853b298967d858f9"
+		"syncstate"		"1"
+		"persiststate"		"0"
+		"platformstosync2"		"-1"
+	}
+	"slot 1.sav" { "root" "2" "size" "8" }
+}
+"#;
+        let cache = RemoteCache::parse(content).unwrap();
+        assert_eq!(cache.app_id, "730");
+        assert_eq!(cache.change_number, 5);
+        assert_eq!(cache.files.len(), 2);
+        assert_eq!(cache.files["cfg/config.cfg"].platformstosync2, -1);
+        assert_eq!(cache.files["slot 1.sav"].root, 2);
+        assert_eq!(RemoteCache::parse(&cache.to_vdf()).unwrap(), cache);
+
+        // Paths differing only in case stay apart
+        let mut cased = cache.clone();
+        cased.files.insert("Slot 1.sav".to_string(), RemoteCacheFile::default());
+        assert_eq!(RemoteCache::parse(&cased.to_vdf()).unwrap(), cased);
+    }
+}
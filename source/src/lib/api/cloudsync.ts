@@ -1,11 +1,31 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
 export interface CloudSyncConfig {
     enabled: boolean;
-    provider: string; // "webdav", "gdrive", "dropbox", "onedrive"
+    provider: string; // "webdav", "local", "s3", "gdrive", "dropbox", "onedrive"
     webdav_url: string;
     username: string;
     password: string;
+    snapshot_retention_count?: number; // 0 disables snapshots
+    snapshot_retention_days?: number; // 0 = no age limit
+    local_path?: string; // provider "local"
+    s3_endpoint?: string; // provider "s3"
+    s3_bucket?: string;
+    s3_region?: string;
+    s3_access_key?: string;
+    s3_secret_key?: string;
+    encryption_enabled?: boolean; // passphrase is set separately
+    scan_local_tree?: boolean; // also sync files on disk that remotecache.vdf doesn't list
+    game_rules?: Record<string, GameFileRules>; // keyed by app ID
+    deletion_mode?: "trash" | "delete"; // trash keeps a copy of remotely deleted saves (default)
+    sync_config_files?: boolean; // also sync PCGamingWiki config files of games without Steam Cloud
+}
+
+export interface GameFileRules {
+    scan_local_tree?: boolean | null; // overrides the global setting
+    include?: string[]; // globs relative to the cloud root; empty = everything
+    exclude?: string[];
 }
 
 export interface GameCloudStatus {
@@ -15,6 +35,8 @@ export interface GameCloudStatus {
     pending_files?: number | null;
     error_message?: string | null;
     source: "steam_cloud" | "pcgamingwiki" | "none";
+    files_to_upload?: string[]; // what the next sync would upload
+    files_to_download?: string[];
 }
 
 export interface GlobalCloudStatus {
@@ -31,7 +53,145 @@ export interface SyncResult {
     message: string;
     files_uploaded: number;
     files_downloaded: number;
+    files_deleted: number; // on either side
     conflicts: string[];
+    planned?: PlannedFile[]; // dry runs only
+}
+
+// Result of syncing every installed game with cloud saves
+export interface BulkSyncResult {
+    success: boolean;
+    message: string;
+    files_uploaded: number;
+    files_downloaded: number;
+    files_deleted: number; // on either side
+    conflicts: string[]; // "<app_id>/<path>"
+    games: GameSyncOutcome[];
+}
+
+export interface GameSyncOutcome {
+    app_id: string;
+    result: SyncResult | null; // set when the sync ran
+    error: string | null; // set when it failed outright
+}
+
+export interface PlannedFile {
+    path: string;
+    action: "none" | "upload" | "download" | "conflict" | "delete_local" | "delete_remote";
+}
+
+export interface CloudFile {
+    path: string;
+    root: number;
+    size: number;
+    localtime: number;
+    remotetime: number;
+    sha: string;
+    syncstate: number;
+    resolved_path?: string | null;
+}
+
+export interface SnapshotInfo {
+    id: string;
+    app_id: string;
+    created_at: string;
+    files: CloudFile[];
+}
+
+export type ConflictResolution = "keep_local" | "keep_remote" | "keep_both";
+
+export interface LayoutMigrationResult {
+    games_checked: number;
+    files_migrated: number;
+    states_adopted: number;
+    errors: string[];
+}
+
+export interface RollbackFile {
+    path: string;
+    local_path: string;
+    size: number;
+}
+
+export interface RollbackInfo {
+    id: string;
+    app_id: string;
+    created_at: string;
+    files: RollbackFile[];
+}
+
+export type ArchiveFormat = "zip" | "7z";
+
+export interface ArchivedFile {
+    archive_path: string;
+    file: CloudFile;
+}
+
+export interface ArchiveManifest {
+    version: number;
+    app_id: string;
+    account_id: string;
+    created_at: string;
+    source_home: string | null;
+    source_location: {
+        install_dir: string | null;
+        proton_prefix: string | null;
+    };
+    files: ArchivedFile[];
+}
+
+export interface TransferProgress {
+    app_id: string;
+    file_path: string;
+    direction: "upload" | "download";
+    bytes_done: number;
+    bytes_total: number;
+}
+
+export interface SyncStatusEvent {
+    app_id: string;
+    status: "queued" | "deferred" | "started" | "finished" | "failed"; // deferred = game running
+    result: SyncResult | null; // set when finished
+    error: string | null; // set when failed
+}
+
+export type SyncMode =
+    | "full"
+    | "dry_run" // report what a full sync would do
+    | "upload_only"
+    | "download_only"
+    | "force_upload" // remote copy made to match local, overriding conflicts
+    | "force_download";
+
+export interface SyncRun {
+    started_at: string;
+    finished_at: string;
+    mode: SyncMode;
+    direction: "upload" | "download" | "both" | "none";
+    files_uploaded: number;
+    files_downloaded: number;
+    files_deleted: number; // on either side
+    bytes_uploaded: number;
+    bytes_downloaded: number;
+    conflicts: number;
+    files_pending: number; // failed, or held while the game was running
+    errors: string[];
+    success: boolean;
+    message: string;
+}
+
+export interface SteamAccount {
+    account_id: string; // userdata/<account_id>
+    steam_id: string | null;
+    account_name: string | null;
+    persona_name: string | null;
+    most_recent: boolean; // the active account
+    has_userdata: boolean;
+}
+
+export interface GameSessionEvent {
+    app_id: string;
+    running: boolean;
 }
 
 export async function saveCloudSyncConfig(config: CloudSyncConfig): Promise<void> {
@@ -46,16 +206,119 @@ export async function testCloudSyncConnection(config: CloudSyncConfig): Promise<
     return invoke("test_cloudsync_connection", { config });
 }
 
-export async function getGameCloudStatus(appId: string): Promise<GameCloudStatus> {
-    return invoke("get_game_cloud_status", { appId });
+// accountId selects the Steam account; the active one is used if omitted
+export async function getGameCloudStatus(appId: string, accountId?: string): Promise<GameCloudStatus> {
+    return invoke("get_game_cloud_status", { appId, accountId: accountId ?? null });
+}
+
+export async function getCloudSyncHistory(appId: string, accountId?: string): Promise<SyncRun[]> {
+    return invoke("get_cloud_sync_history", { appId, accountId: accountId ?? null });
+}
+
+export async function listSteamAccounts(): Promise<SteamAccount[]> {
+    return invoke("list_steam_accounts");
 }
 
 export async function getGlobalCloudStatus(): Promise<GlobalCloudStatus> {
     return invoke("get_global_cloud_status");
 }
 
-export async function syncGameCloudSaves(appId: string): Promise<SyncResult> {
-    return invoke("sync_game_cloud_saves", { appId });
+// mode defaults to "full"; files limits the sync to those save paths
+export async function syncGameCloudSaves(
+    appId: string,
+    accountId?: string,
+    mode?: SyncMode,
+    files?: string[]
+): Promise<SyncResult> {
+    return invoke("sync_game_cloud_saves", {
+        appId,
+        accountId: accountId ?? null,
+        mode: mode ?? null,
+        files: files ?? null,
+    });
+}
+
+// Also available headless: boilerroom --sync-all-cloud-saves [--account <id>] [--mode <mode>]
+export async function syncAllCloudSaves(accountId?: string, mode?: SyncMode): Promise<BulkSyncResult> {
+    return invoke("sync_all_cloud_saves", { accountId: accountId ?? null, mode: mode ?? null });
+}
+
+export async function listCloudSnapshots(appId: string, accountId?: string): Promise<SnapshotInfo[]> {
+    return invoke("list_cloud_snapshots", { appId, accountId: accountId ?? null });
+}
+
+export async function restoreCloudSnapshot(
+    appId: string,
+    snapshotId: string,
+    accountId?: string
+): Promise<SyncResult> {
+    return invoke("restore_cloud_snapshot", { appId, snapshotId, accountId: accountId ?? null });
+}
+
+export async function getCloudConflicts(appId: string, accountId?: string): Promise<string[]> {
+    return invoke("get_cloud_conflicts", { appId, accountId: accountId ?? null });
+}
+
+export async function resolveCloudConflict(
+    appId: string,
+    filePath: string,
+    resolution: ConflictResolution,
+    accountId?: string
+): Promise<void> {
+    return invoke("resolve_cloud_conflict", { appId, filePath, resolution, accountId: accountId ?? null });
+}
+
+export async function resolveCloudConflicts(
+    appId: string,
+    resolutions: Record<string, ConflictResolution>,
+    accountId?: string
+): Promise<void> {
+    return invoke("resolve_cloud_conflicts", { appId, resolutions, accountId: accountId ?? null });
+}
+
+export async function listLocalRollbacks(appId: string): Promise<RollbackInfo[]> {
+    return invoke("list_local_rollbacks", { appId });
+}
+
+export async function restoreLocalRollback(appId: string, rollbackId: string): Promise<SyncResult> {
+    return invoke("restore_local_rollback", { appId, rollbackId });
+}
+
+export async function exportCloudSaves(
+    appId: string,
+    destination: string,
+    accountId?: string,
+    format?: ArchiveFormat
+): Promise<ArchiveManifest> {
+    return invoke("export_cloud_saves", {
+        appId,
+        destination,
+        accountId: accountId ?? null,
+        format: format ?? null,
+    });
+}
+
+export async function importCloudSaves(archivePath: string, accountId?: string): Promise<SyncResult> {
+    return invoke("import_cloud_saves", { archivePath, accountId: accountId ?? null });
+}
+
+export async function migrateCloudStorageLayout(
+    appId?: string,
+    accountId?: string
+): Promise<LayoutMigrationResult> {
+    return invoke("migrate_cloud_storage_layout", { appId: appId ?? null, accountId: accountId ?? null });
+}
+
+export async function setCloudEncryptionPassphrase(passphrase: string): Promise<void> {
+    return invoke("set_cloud_encryption_passphrase", { passphrase });
+}
+
+export async function hasCloudEncryptionPassphrase(): Promise<boolean> {
+    return invoke("has_cloud_encryption_passphrase");
+}
+
+export async function rotateCloudEncryptionKey(appId: string, accountId?: string): Promise<number> {
+    return invoke("rotate_cloud_encryption_key", { appId, accountId: accountId ?? null });
 }
 
 export async function startCloudWatcher(appIds: string[]): Promise<void> {
@@ -69,3 +332,29 @@ export async function stopCloudWatcher(): Promise<void> {
 export async function isCloudWatcherRunning(): Promise<boolean> {
     return invoke("is_cloud_watcher_running");
 }
+
+export async function onCloudTransferProgress(
+    handler: (progress: TransferProgress) => void
+): Promise<UnlistenFn> {
+    return listen<TransferProgress>("cloudsync-progress", (event) => handler(event.payload));
+}
+
+export async function onCloudSyncStatus(
+    handler: (event: SyncStatusEvent) => void
+): Promise<UnlistenFn> {
+    return listen<SyncStatusEvent>("cloudsync-sync-status", (event) => handler(event.payload));
+}
+
+export async function launchGameWithCloudSync(appId: string): Promise<SyncResult> {
+    return invoke("launch_game_with_cloud_sync", { appId });
+}
+
+export async function isGameSessionActive(appId: string): Promise<boolean> {
+    return invoke("is_game_session_active", { appId });
+}
+
+export async function onGameSessionChange(
+    handler: (event: GameSessionEvent) => void
+): Promise<UnlistenFn> {
+    return listen<GameSessionEvent>("cloudsync-session", (event) => handler(event.payload));
+}
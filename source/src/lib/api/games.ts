@@ -51,6 +51,35 @@ export async function checkGameUpdate(
     return invoke<boolean>("check_game_update", { appId });
 }
 
+// PCGamingWiki metadata
+export interface GameApi {
+    direct3d_versions: string[];
+    vulkan_versions: string[];
+    opengl_versions: string[];
+    windows_64bit: boolean | null; // null when the wiki doesn't say
+    linux_32bit: boolean | null;
+    linux_64bit: boolean | null;
+}
+
+export interface GameMetadata {
+    app_id: string;
+    page_name: string;
+    developers: string[];
+    publishers: string[];
+    engines: string[];
+    release_dates: string[];
+    platforms: string[];
+    api: GameApi;
+    config_entries: { platform: string; path: string }[]; // as written on the wiki
+    config_paths: string[]; // resolved on this machine
+    fixes: string[];
+    linux_notes: string[];
+}
+
+export async function getGameMetadata(appId: string): Promise<GameMetadata> {
+    return invoke<GameMetadata>("get_game_metadata", { appId });
+}
+
 export async function getSteamLibraries(
     config: SshConfig
 ): Promise<string[]> {